@@ -0,0 +1,165 @@
+use std::hint::black_box;
+
+use ark_bn254::Fr;
+use circom_witness_rs::{
+    bytecode,
+    graph::{self, Node, Operation},
+    nodes::Nodes,
+    prime::Prime,
+    Graph,
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::Rng;
+use ruint::aliases::U256;
+
+/// Builds `x^5` the way circom emits it, as a chain of multiplications.
+fn pow5(nodes: &mut Nodes, x: usize) -> usize {
+    let x2 = nodes.push(Node::Op(Operation::Mul, x, x));
+    let x4 = nodes.push(Node::Op(Operation::Mul, x2, x2));
+    nodes.push(Node::Op(Operation::Mul, x4, x))
+}
+
+fn random_constant(nodes: &mut Nodes) -> usize {
+    let c = rand::thread_rng().gen::<U256>() % circom_witness_rs::M;
+    nodes.push(Node::Constant(c))
+}
+
+/// MiMC style hash chain over `inputs` field elements
+fn mimc(inputs: usize, rounds: usize) -> (Nodes, Vec<usize>) {
+    let mut nodes = Nodes::new();
+    let key = nodes.push(Node::Input(1));
+    let mut outputs = vec![];
+    for i in 0..inputs {
+        let mut x = nodes.push(Node::Input(2 + i));
+        for _ in 0..rounds {
+            let c = random_constant(&mut nodes);
+            let t = nodes.push(Node::Op(Operation::Add, x, key));
+            let t = nodes.push(Node::Op(Operation::Add, t, c));
+            x = pow5(&mut nodes, t);
+        }
+        outputs.push(x);
+    }
+    (nodes, outputs)
+}
+
+/// Poseidon style permutation with full rounds over `width` elements
+fn poseidon(width: usize, rounds: usize) -> (Nodes, Vec<usize>) {
+    let mut nodes = Nodes::new();
+    let mut state = (0..width)
+        .map(|i| nodes.push(Node::Input(1 + i)))
+        .collect::<Vec<_>>();
+    for _ in 0..rounds {
+        for x in state.iter_mut() {
+            let c = random_constant(&mut nodes);
+            let t = nodes.push(Node::Op(Operation::Add, *x, c));
+            *x = pow5(&mut nodes, t);
+        }
+        state = (0..width)
+            .map(|_| {
+                let mut acc = None;
+                for &x in state.iter() {
+                    let m = random_constant(&mut nodes);
+                    let t = nodes.push(Node::Op(Operation::Mul, x, m));
+                    acc = Some(match acc {
+                        None => t,
+                        Some(acc) => nodes.push(Node::Op(Operation::Add, acc, t)),
+                    });
+                }
+                acc.unwrap()
+            })
+            .collect();
+    }
+    (nodes, state)
+}
+
+/// Optimizes the nodes and wraps them in a BN254 graph
+fn optimized((mut nodes, mut outputs): (Nodes, Vec<usize>)) -> Graph {
+    graph::optimize::<Fr>(&mut nodes, &mut outputs);
+    Graph {
+        prime: Prime::Bn128,
+        metadata: Default::default(),
+        nodes,
+        signals: outputs,
+        input_mapping: vec![],
+        inputs_size: 1 << 13,
+    }
+}
+
+fn random_inputs() -> Vec<U256> {
+    let mut rng = rand::thread_rng();
+    (0..1 << 13)
+        .map(|i| match i {
+            0 => U256::from(1),
+            _ => rng.gen::<U256>() % circom_witness_rs::M,
+        })
+        .collect()
+}
+
+fn bench_graph(c: &mut Criterion, name: &str, graph: Graph) {
+    let (nodes, outputs) = (&graph.nodes, &graph.signals);
+    let program = bytecode::lower(&graph).unwrap();
+
+    let inputs = random_inputs();
+    assert_eq!(
+        graph::evaluate::<Fr>(nodes, &inputs, outputs, None).unwrap(),
+        program.evaluate(&inputs, None).unwrap()
+    );
+
+    let mut group = c.benchmark_group(name);
+    group.bench_function("graph", |b| {
+        b.iter(|| graph::evaluate::<Fr>(nodes, black_box(&inputs), outputs, None).unwrap())
+    });
+    group.bench_function("bytecode", |b| {
+        b.iter(|| program.evaluate(black_box(&inputs), None).unwrap())
+    });
+    #[cfg(feature = "jit")]
+    {
+        let jit = circom_witness_rs::jit::JitGraph::compile(&program).unwrap();
+        assert_eq!(
+            program.evaluate(&inputs, None).unwrap(),
+            jit.evaluate(&inputs, None).unwrap()
+        );
+        group.bench_function("jit", |b| {
+            b.iter(|| jit.evaluate(black_box(&inputs), None).unwrap())
+        });
+    }
+    group.finish();
+}
+
+#[cfg(feature = "parallel")]
+fn bench_parallel(c: &mut Criterion, name: &str, graph: Graph) {
+    use circom_witness_rs::parallel::ParallelGraph;
+
+    let (nodes, outputs) = (&graph.nodes, &graph.signals);
+    let parallel = ParallelGraph::new(&graph).unwrap();
+
+    let inputs = random_inputs();
+    assert_eq!(
+        graph::evaluate::<Fr>(nodes, &inputs, outputs, None).unwrap(),
+        parallel.evaluate(&inputs, None).unwrap()
+    );
+
+    let mut group = c.benchmark_group(name);
+    group.bench_function("graph", |b| {
+        b.iter(|| graph::evaluate::<Fr>(nodes, black_box(&inputs), outputs, None).unwrap())
+    });
+    group.bench_function("parallel", |b| {
+        b.iter(|| parallel.evaluate(black_box(&inputs), None).unwrap())
+    });
+    group.finish();
+}
+
+fn evaluate(c: &mut Criterion) {
+    bench_graph(c, "mimc", optimized(mimc(16, 220)));
+    bench_graph(c, "poseidon", optimized(poseidon(3, 64)));
+    #[cfg(feature = "parallel")]
+    {
+        // Levels of 16 nodes stay below `MIN_CHUNK` and are evaluated
+        // sequentially.
+        bench_parallel(c, "mimc_narrow", optimized(mimc(16, 220)));
+        bench_parallel(c, "mimc_wide", optimized(mimc(4096, 20)));
+    }
+}
+
+criterion_group!(benches, evaluate);
+criterion_main!(benches);
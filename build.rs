@@ -1,44 +1,231 @@
-use std::{env, fs, path::Path, process::Command};
+#[cfg(feature = "build-witness")]
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+#[cfg(feature = "build-witness")]
+use circom_witness_transform::{CircomVersion, SUPPORTED_VERSIONS};
 
 fn main() {
-    if cfg!(feature = "build-witness") {
-        let witness_cpp = env::var("WITNESS_CPP").unwrap();
-        let library_path = env::var("CIRCOM_LIBRARY_PATH");
-        let circuit_file = Path::new(&witness_cpp);
-        let circuit_name = circuit_file.file_stem().unwrap().to_str().unwrap();
-
-        let mut cmd = Command::new("circom");
-        cmd.arg(fs::canonicalize(circuit_file).unwrap())
-            .arg("--c")
-            .arg("--O2");
-        if let Ok(library_path) = library_path {
-            cmd.arg("-l").arg(fs::canonicalize(library_path).unwrap());
+    #[cfg(feature = "build-witness")]
+    {
+        // `WITNESS_CPP` lists the circuits separated like `PATH`. An entry is
+        // either a circom source, identified by its file stem, or a `<name>_cpp`
+        // directory generated by `circom --c` before, which needs no circom.
+        let witness_cpp = env::var_os("WITNESS_CPP").expect("WITNESS_CPP is not set");
+        let prime = env::var("WITNESS_PRIME").unwrap_or_else(|_| "bn128".to_string());
+        let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+
+        // Flags passed to circom, the prime is set by `WITNESS_PRIME`.
+        let optimization = env::var("CIRCOM_OPTIMIZATION").unwrap_or_else(|_| "O2".to_string());
+        assert!(
+            ["O0", "O1", "O2"].contains(&optimization.as_str()),
+            "CIRCOM_OPTIMIZATION must be O0, O1 or O2, not {optimization:?}"
+        );
+        let mut circom_flags: Vec<OsString> = vec![
+            "--c".into(),
+            "--sym".into(),
+            format!("--{optimization}").into(),
+            "--prime".into(),
+            prime.clone().into(),
+        ];
+        if let Some(library_paths) = env::var_os("CIRCOM_LIBRARY_PATH") {
+            for library_path in env::split_paths(&library_paths) {
+                circom_flags.push("-l".into());
+                circom_flags.push(fs::canonicalize(library_path).unwrap().into_os_string());
+            }
         }
-        let status = cmd.status().unwrap();
-        assert!(status.success());
 
-        let cpp = Path::new("./")
-            .join(circuit_name.to_owned() + "_cpp")
-            .join(circuit_name.to_owned() + ".cpp");
+        // Pre-generated directories declare the circom version that produced
+        // them in `CIRCOM_VERSION`, otherwise it is asked from circom.
+        let declared_version = env::var("CIRCOM_VERSION").ok().map(|v| {
+            v.parse::<CircomVersion>()
+                .unwrap_or_else(|e| panic!("CIRCOM_VERSION: {e}"))
+        });
+        let mut installed_version = None;
+
+        let mut build = cxx_build::bridge("src/generate.rs");
+        let mut names: Vec<String> = vec![];
+        let mut table = String::from("#include \"witness/include/witness.h\"\n\n");
+        let mut dats = String::from("[\n");
+        for circuit_file in env::split_paths(&witness_cpp) {
+            let (circuit_name, cpp_dir, version) = if circuit_file.is_dir() {
+                let dir_name = circuit_file.file_name().unwrap().to_str().unwrap();
+                let circuit_name = dir_name.strip_suffix("_cpp").unwrap_or_else(|| {
+                    panic!("{dir_name:?} is not a circom `<name>_cpp` directory")
+                });
+                check_circom_version(circuit_name, declared_version);
+                check_prime(&circuit_file, &prime);
+                (
+                    circuit_name.to_owned(),
+                    fs::canonicalize(&circuit_file).unwrap(),
+                    declared_version,
+                )
+            } else {
+                let circuit_name = circuit_file.file_stem().unwrap().to_str().unwrap();
+                let version = *installed_version.get_or_insert_with(circom_version);
+                check_circom_version(circuit_name, Some(version));
+                let status = Command::new("circom")
+                    .arg(fs::canonicalize(&circuit_file).unwrap())
+                    .args(&circom_flags)
+                    .arg("-o")
+                    .arg(&out_dir)
+                    .status()
+                    .unwrap();
+                assert!(status.success());
+                (
+                    circuit_name.to_owned(),
+                    out_dir.join(circuit_name.to_owned() + "_cpp"),
+                    Some(version),
+                )
+            };
+            let circuit_name = circuit_name.as_str();
+            assert!(
+                !circuit_name.is_empty()
+                    && !circuit_name.starts_with(|c: char| c.is_ascii_digit())
+                    && circuit_name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_'),
+                "circuit name {circuit_name:?} is not an identifier"
+            );
+            assert!(
+                !names.iter().any(|n| n == circuit_name),
+                "duplicate circuit name {circuit_name:?}"
+            );
+
+            let cpp = cpp_dir.join(circuit_name.to_owned() + ".cpp");
+            let dat = cpp_dir.join(circuit_name.to_owned() + ".dat");
 
-        println!("cargo:warning=\"{}\"", cpp.to_str().unwrap());
+            // circom writes the symbols next to the `<name>_cpp` directory,
+            // checked-in directories may also contain them.
+            let sym_name = circuit_name.to_owned() + ".sym";
+            let sym = [cpp_dir.join(&sym_name), cpp_dir.with_file_name(&sym_name)]
+                .into_iter()
+                .find(|path| path.exists())
+                .unwrap_or_else(|| {
+                    panic!(
+                        "{sym_name} is missing from {} and its parent directory, the input names \
+                         are read from it. Generate it with `circom --sym`.",
+                        cpp_dir.display()
+                    )
+                });
+            let sym_source = fs::read_to_string(&sym).unwrap();
+            let main_signals = circom_witness_transform::main_signals(&sym_source)
+                .and_then(|signals| circom_witness_transform::signal_arrays(&signals))
+                .unwrap_or_else(|e| panic!("{}: {e}", sym.display()));
+            let main_signals: Vec<_> = main_signals
+                .into_iter()
+                .map(|array| format!("({}, {:?}, &{:?})", array.first, array.name, array.shape))
+                .collect();
+
+            println!("cargo:warning=\"{}\"", cpp.to_str().unwrap());
+
+            // Translate the circuit into its own namespace.
+            let namespace = format!("circuit_{circuit_name}");
+            let cc = out_dir.join(namespace.clone() + ".cc");
+            let source = fs::read_to_string(&cpp).unwrap();
+            let translated = circom_witness_transform::transform(&source, &namespace)
+                .unwrap_or_else(|e| panic!("{}:{e}", cpp.display()));
+            fs::write(&cc, translated).unwrap();
+            build.file(&cc);
+
+            table += &format!("DECLARE_CIRCUIT({namespace})\n");
+            let version = version.map(|v| v.to_string());
+            dats += &format!(
+                "    CircuitData {{\n        name: {circuit_name:?},\n        dat: include_bytes!({dat:?}),\n        circom_version: {version:?},\n        main_signals: &[{}],\n    }},\n",
+                main_signals.join(", ")
+            );
+            names.push(circuit_name.to_owned());
+            println!("cargo:rerun-if-changed={}", circuit_file.display());
+        }
+        assert!(!names.is_empty(), "WITNESS_CPP lists no circuits");
 
-        let status = Command::new("./script/replace.sh")
-            .arg(cpp.to_str().unwrap())
-            .status()
-            .unwrap();
-        assert!(status.success());
+        let entries: Vec<_> = names
+            .iter()
+            .map(|name| format!("CIRCUIT(circuit_{name})"))
+            .collect();
+        table += &format!("\nconst Circuit CIRCUITS[] = {{{}}};\n", entries.join(", "));
+        dats += "]\n";
+        fs::write(out_dir.join("circuits.cc"), table).unwrap();
+        fs::write(out_dir.join("circuits.rs"), dats).unwrap();
 
-        cxx_build::bridge("src/generate.rs")
-            .file("src/circuit.cc")
+        build
+            .file(out_dir.join("circuits.cc"))
+            .file("src/circuits.cc")
             .flag_if_supported("-std=c++14")
             .flag_if_supported("-w")
             .flag_if_supported("-d")
             .flag_if_supported("-g")
             .compile("witness");
 
-        println!("cargo:rerun-if-changed=src/main.rs");
-        println!("cargo:rerun-if-changed=src/circuit.cc");
-        println!("cargo:rerun-if-changed=include/circuit.h");
+        println!("cargo:rustc-env=WITNESS_PRIME={prime}");
+        println!("cargo:rerun-if-env-changed=WITNESS_PRIME");
+        println!("cargo:rerun-if-env-changed=WITNESS_CPP");
+        println!("cargo:rerun-if-env-changed=CIRCOM_OPTIMIZATION");
+        println!("cargo:rerun-if-env-changed=CIRCOM_LIBRARY_PATH");
+        println!("cargo:rerun-if-env-changed=CIRCOM_VERSION");
+        println!("cargo:rerun-if-env-changed=CIRCOM_VERSION_CHECK");
+        println!("cargo:rerun-if-changed=src/circuits.cc");
+        println!("cargo:rerun-if-changed=include/witness.h");
     }
 }
+
+/// Version of the installed circom
+#[cfg(feature = "build-witness")]
+fn circom_version() -> CircomVersion {
+    let output = Command::new("circom")
+        .arg("--version")
+        .output()
+        .expect("failed to run circom");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    CircomVersion::from_output(&stdout)
+        .unwrap_or_else(|| panic!("unrecognized `circom --version` output {stdout:?}"))
+}
+
+/// Fails the build for circom versions the C++ transformation is not tested
+/// with and for unknown versions, unless `CIRCOM_VERSION_CHECK=warn`
+#[cfg(feature = "build-witness")]
+fn check_circom_version(circuit_name: &str, version: Option<CircomVersion>) {
+    let message = match version {
+        Some(version) if version.is_supported() => return,
+        Some(version) => format!(
+            "{circuit_name} is generated by circom {version}, supported versions are {} to {}",
+            SUPPORTED_VERSIONS.start(),
+            SUPPORTED_VERSIONS.end()
+        ),
+        None => format!(
+            "circom version of {circuit_name} is unknown, the generated C++ does not record it. \
+             Set CIRCOM_VERSION to the version that generated it"
+        ),
+    };
+    if env::var("CIRCOM_VERSION_CHECK").as_deref() == Ok("warn") {
+        println!("cargo:warning={message}");
+    } else {
+        panic!("{message}. Set CIRCOM_VERSION_CHECK=warn to build anyway.");
+    }
+}
+
+/// Fails the build when a pre-generated directory is for another prime than
+/// `WITNESS_PRIME`, detected from the field arithmetic circom writes into it
+#[cfg(feature = "build-witness")]
+fn check_prime(cpp_dir: &Path, prime: &str) {
+    let source: String = ["fr.hpp", "fr.cpp", "fr.asm"]
+        .into_iter()
+        .filter_map(|file| fs::read_to_string(cpp_dir.join(file)).ok())
+        .collect();
+    let detected = circom_witness_transform::detect_prime(&source).unwrap_or_else(|| {
+        panic!(
+            "can't detect the prime of {}, it needs the fr.cpp that `circom --c` generates",
+            cpp_dir.display()
+        )
+    });
+    assert!(
+        detected == prime,
+        "{} is generated for the prime {detected}, but WITNESS_PRIME is {prime}",
+        cpp_dir.display()
+    );
+}
@@ -0,0 +1,7 @@
+//! Builds the C interface of circom-witness-rs as `libcwr`.
+//!
+//! The functions are defined in `circom_witness_rs::capi`, this crate only
+//! adds the cdylib, so that the runtime itself stays an rlib that builds
+//! without `std`.
+
+pub use circom_witness_rs::capi::*;
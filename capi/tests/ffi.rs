@@ -0,0 +1,145 @@
+//! Calls the C interface through its extern functions.
+
+use std::{
+    ffi::{c_int, c_void, CStr},
+    ptr, slice,
+};
+
+use circom_witness_rs::{
+    format,
+    graph::{Node, Operation},
+    nodes::Nodes,
+    prime::Prime,
+    Graph, HashSignalInfo, Metadata,
+};
+use cwr::*;
+use ruint::aliases::U256;
+
+fn fnv1a(s: &str) -> u64 {
+    let mut hash: u64 = 0xCBF29CE484222325;
+    for c in s.bytes() {
+        hash ^= c as u64;
+        hash = hash.wrapping_mul(0x100000001B3);
+    }
+    hash
+}
+
+/// Witness `[1, a, f(a), a * f(a)]` with the black box function `f`
+fn graph_bytes() -> Vec<u8> {
+    let mut nodes = Nodes::new();
+    let one = nodes.push(Node::Constant(U256::from(1)));
+    let a = nodes.push(Node::Input(1));
+    let f = nodes.push(Node::BBF("f_0".to_string(), vec![a]));
+    let product = nodes.push(Node::Op(Operation::Mul, a, f));
+    format::encode(&Graph {
+        prime: Prime::Bn128,
+        metadata: Metadata::default(),
+        nodes,
+        signals: vec![one, a, f, product],
+        input_mapping: vec![HashSignalInfo {
+            hash: fnv1a("a"),
+            signalid: 1,
+            signalsize: 1,
+            name: "a".to_string(),
+            shape: vec![],
+        }],
+        inputs_size: 2,
+    })
+}
+
+/// Adds `user_data` to the argument
+unsafe extern "C" fn add(
+    user_data: *mut c_void,
+    args: *const u8,
+    nargs: usize,
+    out: *mut u8,
+) -> c_int {
+    assert_eq!(nargs, 1);
+    let arg = U256::from_le_slice(slice::from_raw_parts(args, 32));
+    let value = arg + U256::from(user_data as usize);
+    out.copy_from_nonoverlapping(value.to_le_bytes::<32>().as_ptr(), 32);
+    0
+}
+
+unsafe extern "C" fn fail(_: *mut c_void, _: *const u8, _: usize, _: *mut u8) -> c_int {
+    7
+}
+
+fn last_error() -> String {
+    let error = cwr_last_error();
+    assert!(!error.is_null());
+    unsafe { CStr::from_ptr(error) }
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+unsafe fn load() -> *mut CwrGraph {
+    let bytes = graph_bytes();
+    let graph = cwr_graph_load(bytes.as_ptr(), bytes.len());
+    assert!(!graph.is_null());
+    graph
+}
+
+#[test]
+fn calculate_witness() {
+    unsafe {
+        let graph = load();
+        assert_eq!(
+            cwr_graph_register_bbf(graph, c"f".as_ptr(), Some(add), 2 as *mut c_void),
+            0
+        );
+        let size = cwr_witness_size(graph);
+        assert_eq!(size, 4 * 32);
+
+        let mut out = vec![0u8; size];
+        let status = cwr_calculate_witness(graph, c"{\"a\": 3}".as_ptr(), out.as_mut_ptr(), size);
+        assert_eq!(status, 0);
+        let witness: Vec<U256> = out.chunks(32).map(U256::from_le_slice).collect();
+        assert_eq!(witness, [1, 3, 5, 15].map(U256::from));
+        cwr_graph_free(graph);
+    }
+}
+
+#[test]
+fn small_buffer() {
+    unsafe {
+        let graph = load();
+        let mut out = vec![0u8; 100];
+        let status = cwr_calculate_witness(graph, c"{\"a\": 3}".as_ptr(), out.as_mut_ptr(), 100);
+        assert_eq!(status, -1);
+        assert_eq!(
+            last_error(),
+            "output buffer of 100 bytes is too small, 128 bytes are needed"
+        );
+        cwr_graph_free(graph);
+    }
+}
+
+#[test]
+fn failing_black_box_function() {
+    unsafe {
+        let graph = load();
+        assert_eq!(
+            cwr_graph_register_bbf(graph, c"f".as_ptr(), Some(fail), ptr::null_mut()),
+            0
+        );
+        let mut out = vec![0u8; 128];
+        let status = cwr_calculate_witness(graph, c"{\"a\": 3}".as_ptr(), out.as_mut_ptr(), 128);
+        assert_eq!(status, -1);
+        assert_eq!(
+            last_error(),
+            "panic: black box function \"f\" failed with status 7"
+        );
+        cwr_graph_free(graph);
+    }
+}
+
+#[test]
+fn invalid_graph() {
+    unsafe {
+        let graph = cwr_graph_load(b"CWRG".as_ptr(), 4);
+        assert!(graph.is_null());
+        assert_eq!(last_error(), "truncated graph header: 4 of 48 bytes");
+    }
+}
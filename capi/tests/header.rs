@@ -0,0 +1,47 @@
+//! Checks that `include/cwr.h` matches the C interface. Set `BLESS=1` to
+//! regenerate it.
+
+use std::{env, fs, path::Path};
+
+#[test]
+fn header_is_current() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let config = cbindgen::Config {
+        language: cbindgen::Language::C,
+        include_guard: Some("CWR_H".to_string()),
+        autogen_warning: Some(
+            "/* Generated by cbindgen from src/capi.rs, do not edit. */".to_string(),
+        ),
+        cpp_compat: true,
+        usize_is_size_t: true,
+        export: cbindgen::ExportConfig {
+            item_types: vec![
+                cbindgen::ItemType::Functions,
+                cbindgen::ItemType::Typedefs,
+                cbindgen::ItemType::OpaqueItems,
+            ],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut header = Vec::new();
+    cbindgen::Builder::new()
+        .with_crate(root)
+        .with_config(config)
+        .generate()
+        .expect("failed to generate C header")
+        .write(&mut header);
+    let header = String::from_utf8(header).unwrap();
+
+    let path = root.join("include/cwr.h");
+    if env::var_os("BLESS").is_some() {
+        fs::write(&path, &header).unwrap();
+    } else {
+        let committed = fs::read_to_string(&path).unwrap();
+        assert!(
+            committed == header,
+            "{} is out of date, run the tests with BLESS=1",
+            path.display()
+        );
+    }
+}
@@ -0,0 +1,98 @@
+#ifndef CWR_H
+#define CWR_H
+
+/* Generated by cbindgen from src/capi.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Opaque graph handle
+ */
+typedef struct CwrGraph CwrGraph;
+
+/**
+ * Black box function callback.
+ *
+ * Receives `nargs` field elements in `args` and writes the result to `out`,
+ * all as 32 byte little-endian integers. Returns 0 on success. The callback
+ * may be called from any thread that evaluates the graph.
+ */
+typedef int (*CwrBlackBoxFn)(void *user_data, const uint8_t *args, size_t nargs, uint8_t *out);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Returns the message of the last error on this thread, or null.
+ *
+ * The string is valid until the next failing call on the same thread.
+ */
+const char *cwr_last_error(void);
+
+/**
+ * Loads a graph from `len` bytes, returns null on failure.
+ *
+ * # Safety
+ *
+ * `bytes` must point to `len` readable bytes.
+ */
+struct CwrGraph *cwr_graph_load(const uint8_t *bytes, size_t len);
+
+/**
+ * Frees a graph loaded with [`cwr_graph_load`], null is ignored.
+ *
+ * # Safety
+ *
+ * `graph` must be null or a handle returned by [`cwr_graph_load`] that is
+ * not used afterwards.
+ */
+void cwr_graph_free(struct CwrGraph *graph);
+
+/**
+ * Registers the black box function `name`, without its numeric suffix.
+ * Returns 0 on success.
+ *
+ * # Safety
+ *
+ * `graph` must be a valid handle that is not used concurrently and `name` a
+ * NUL terminated string. `f` is called with `user_data`, both must stay
+ * valid and be safe to use from any thread while the graph is alive.
+ */
+int cwr_graph_register_bbf(struct CwrGraph *graph,
+                           const char *name,
+                           CwrBlackBoxFn f,
+                           void *user_data);
+
+/**
+ * Number of bytes written by [`cwr_calculate_witness`], 32 per element.
+ *
+ * # Safety
+ *
+ * `graph` must be a valid handle.
+ */
+size_t cwr_witness_size(const struct CwrGraph *graph);
+
+/**
+ * Calculates the witness for the JSON encoded inputs and writes it to
+ * `out_buf` as 32 byte little-endian integers. Returns 0 on success.
+ *
+ * # Safety
+ *
+ * `graph` must be a valid handle, `inputs_json` a NUL terminated string and
+ * `out_buf` must point to `out_len` writable bytes.
+ */
+int cwr_calculate_witness(const struct CwrGraph *graph,
+                          const char *inputs_json,
+                          uint8_t *out_buf,
+                          size_t out_len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CWR_H */
@@ -8,12 +8,53 @@ typedef uint8_t u8;
 
 struct Circom_CalcWit;
 
-void run(Circom_CalcWit *buf);
-uint get_size_of_io_map();
-uint get_total_signal_no();
-uint get_main_input_signal_no();
-uint get_main_input_signal_start();
-uint get_number_of_components();
-uint get_size_of_constants();
-uint get_size_of_input_hashmap();
-uint get_size_of_witness();
+/// Entry points of a circuit, compiled into the namespace `circuit_<name>`
+struct Circuit {
+  void (*run)(Circom_CalcWit *buf);
+  uint (*get_size_of_io_map)();
+  uint (*get_total_signal_no)();
+  uint (*get_main_input_signal_no)();
+  uint (*get_main_input_signal_start)();
+  uint (*get_number_of_components)();
+  uint (*get_size_of_constants)();
+  uint (*get_size_of_input_hashmap)();
+  uint (*get_size_of_witness)();
+  uint (*get_size_of_bus_field_map)();
+};
+
+#define DECLARE_CIRCUIT(ns)                                                    \
+  namespace ns {                                                               \
+  void run(Circom_CalcWit *buf);                                               \
+  uint get_size_of_io_map();                                                   \
+  uint get_total_signal_no();                                                  \
+  uint get_main_input_signal_no();                                             \
+  uint get_main_input_signal_start();                                          \
+  uint get_number_of_components();                                             \
+  uint get_size_of_constants();                                                \
+  uint get_size_of_input_hashmap();                                            \
+  uint get_size_of_witness();                                                  \
+  uint get_size_of_bus_field_map();                                            \
+  }
+
+#define CIRCUIT(ns)                                                            \
+  {                                                                            \
+    ns::run, ns::get_size_of_io_map, ns::get_total_signal_no,                  \
+        ns::get_main_input_signal_no, ns::get_main_input_signal_start,         \
+        ns::get_number_of_components, ns::get_size_of_constants,               \
+        ns::get_size_of_input_hashmap, ns::get_size_of_witness,                \
+        ns::get_size_of_bus_field_map                                          \
+  }
+
+/// All circuits in the order of `WITNESS_CPP`, generated by the build script
+extern const Circuit CIRCUITS[];
+
+void run(size_t circuit, Circom_CalcWit *buf);
+uint get_size_of_io_map(size_t circuit);
+uint get_total_signal_no(size_t circuit);
+uint get_main_input_signal_no(size_t circuit);
+uint get_main_input_signal_start(size_t circuit);
+uint get_number_of_components(size_t circuit);
+uint get_size_of_constants(size_t circuit);
+uint get_size_of_input_hashmap(size_t circuit);
+uint get_size_of_witness(size_t circuit);
+uint get_size_of_bus_field_map(size_t circuit);
@@ -0,0 +1,477 @@
+//! Bytecode lowering of optimized graphs.
+//!
+//! [`lower`] turns the nodes into a linear program of [`Instruction`]s. Most
+//! Poseidon and MiMC graphs are dominated by additions and multiplications
+//! with constants, multiply-add chains and fifth powers, so these patterns get
+//! dedicated instructions that are evaluated in a single step.
+//!
+//! Instructions read and write registers. The first registers are preloaded
+//! with the constants that are used as regular operands, constants of
+//! [`Instruction::AddConst`] and [`Instruction::MulConst`] are read from a
+//! separate pool instead. After lowering, a liveness analysis assigns
+//! reusable registers to the instruction results, so the memory needed for
+//! evaluation is bounded by the largest set of simultaneously live values
+//! rather than by the number of nodes.
+//!
+//! [`lower`] produces BN254 programs and rejects graphs over other primes.
+//! The C [code generator](crate::codegen::c) lowers graphs over any prime.
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use ark_bn254::Fr;
+use ark_ff::{AdditiveGroup, Field};
+use ruint::aliases::U256;
+
+use crate::{
+    error::{bail, ensure, Error},
+    graph::{strip_suffix_number, Operation},
+    nodes::{Nodes, Opcode},
+    prime::{GraphField, Prime},
+    BlackBoxFunction, Graph, HashMap,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Input(u32),
+    Add(u32, u32),
+    Sub(u32, u32),
+    Mul(u32, u32),
+    Neg(u32),
+    /// `a + constants[c]`
+    AddConst(u32, u32),
+    /// `a * constants[c]`
+    MulConst(u32, u32),
+    /// `a * b + c`
+    MulAdd(u32, u32, u32),
+    Square(u32),
+    Pow5(u32),
+    /// Any other operation, evaluated through [`Operation::eval_fr`]
+    Op(Operation, u32, u32),
+    /// Black box function call, index into the call table
+    BBF(u32),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct BlackBoxCall {
+    pub(crate) name: String,
+    pub(crate) params: Vec<u32>,
+}
+
+impl Instruction {
+    /// Calls `f` with every register operand, except black box parameters
+    fn for_each_operand_mut(&mut self, mut f: impl FnMut(&mut u32)) {
+        use Instruction::*;
+        match self {
+            Input(_) | BBF(_) => (),
+            Add(a, b) | Sub(a, b) | Mul(a, b) | Op(_, a, b) => {
+                f(a);
+                f(b);
+            }
+            Neg(a) | AddConst(a, _) | MulConst(a, _) | Square(a) | Pow5(a) => f(a),
+            MulAdd(a, b, c) => {
+                f(a);
+                f(b);
+                f(c);
+            }
+        }
+    }
+}
+
+/// Output `position` is read from `register` after `step` instructions.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Output {
+    pub(crate) step: u32,
+    pub(crate) register: u32,
+    pub(crate) position: u32,
+}
+
+/// A lowered graph over the field `F`
+#[derive(Debug, Clone)]
+pub struct Program<F = Fr> {
+    pub(crate) preload: Vec<F>,
+    pub(crate) constants: Vec<F>,
+    pub(crate) instructions: Vec<Instruction>,
+    pub(crate) destinations: Vec<u32>,
+    pub(crate) registers: usize,
+    pub(crate) bbfs: Vec<BlackBoxCall>,
+    /// Sorted by step
+    pub(crate) outputs: Vec<Output>,
+}
+
+/// Instruction with node indices as operands, before slot assignment
+#[derive(Debug, Clone)]
+enum Plan<F> {
+    Input(usize),
+    Add(usize, usize),
+    Sub(usize, usize),
+    Mul(usize, usize),
+    Neg(usize),
+    AddConst(usize, F),
+    MulConst(usize, F),
+    MulAdd(usize, usize, usize),
+    Square(usize),
+    Pow5(usize),
+    Op(Operation, usize, usize),
+    Call(usize),
+}
+
+impl<F> Plan<F> {
+    fn for_each_operand(&self, nodes: &Nodes, mut f: impl FnMut(usize)) {
+        match *self {
+            Plan::Input(_) => (),
+            Plan::Add(a, b) | Plan::Sub(a, b) | Plan::Mul(a, b) | Plan::Op(_, a, b) => {
+                f(a);
+                f(b);
+            }
+            Plan::Neg(a)
+            | Plan::AddConst(a, _)
+            | Plan::MulConst(a, _)
+            | Plan::Square(a)
+            | Plan::Pow5(a) => f(a),
+            Plan::MulAdd(a, b, c) => {
+                f(a);
+                f(b);
+                f(c);
+            }
+            Plan::Call(i) => nodes.params(i).iter().for_each(|&p| f(p as usize)),
+        }
+    }
+}
+
+fn constant<F: GraphField>(nodes: &Nodes, i: usize) -> Option<F> {
+    match nodes.opcode(i) {
+        Opcode::Constant => Some(F::from_u256(nodes.constant(i))),
+        Opcode::MontConstant => Some(nodes.mont_constant(i)),
+        _ => None,
+    }
+}
+
+/// Lowers the graph into a program that evaluates its signals
+pub fn lower(graph: &Graph) -> Result<Program, Error> {
+    let prime = graph.prime;
+    ensure!(
+        prime == Prime::Bn128,
+        "bytecode is not supported for {prime}"
+    );
+    Ok(lower_nodes(&graph.nodes, &graph.signals))
+}
+
+/// Lowers nodes of a graph over `F` into a program that evaluates `outputs`
+pub(crate) fn lower_nodes<F: GraphField>(nodes: &Nodes, outputs: &[usize]) -> Program<F> {
+    let n = nodes.len();
+
+    // Count uses, outputs count as a use so they are never fused.
+    let mut uses = vec![0u32; n];
+    for i in 0..n {
+        nodes.for_each_dependency(i, |j| uses[j] += 1);
+    }
+    for &i in outputs.iter() {
+        uses[i] += 1;
+    }
+
+    // Select instructions, fused nodes are folded into their only user.
+    let mut plans: Vec<Option<Plan<F>>> = Vec::with_capacity(n);
+    let mut fused = vec![false; n];
+    for i in 0..n {
+        let (a, b) = nodes.operands(i);
+        let plan = match nodes.opcode(i) {
+            Opcode::Constant | Opcode::MontConstant => None,
+            Opcode::Input => Some(Plan::Input(a)),
+            Opcode::BBF => Some(Plan::Call(i)),
+            Opcode::Op(Operation::Add) => Some(match (constant(nodes, a), constant(nodes, b)) {
+                (_, Some(c)) => Plan::AddConst(a, c),
+                (Some(c), _) => Plan::AddConst(b, c),
+                _ => {
+                    let single_mul = |j: usize| match plans[j] {
+                        Some(Plan::Mul(x, y)) if uses[j] == 1 => Some((x, y)),
+                        _ => None,
+                    };
+                    if let Some((x, y)) = single_mul(a) {
+                        fused[a] = true;
+                        Plan::MulAdd(x, y, b)
+                    } else if let Some((x, y)) = single_mul(b) {
+                        fused[b] = true;
+                        Plan::MulAdd(x, y, a)
+                    } else {
+                        Plan::Add(a, b)
+                    }
+                }
+            }),
+            Opcode::Op(Operation::Sub) => Some(match constant::<F>(nodes, b) {
+                Some(c) => Plan::AddConst(a, -c),
+                None => Plan::Sub(a, b),
+            }),
+            Opcode::Op(Operation::Mul) => Some(match (constant(nodes, a), constant(nodes, b)) {
+                (_, Some(c)) => Plan::MulConst(a, c),
+                (Some(c), _) => Plan::MulConst(b, c),
+                _ if a == b => Plan::Square(a),
+                _ => {
+                    // x^5 = (x^2)^2 * x
+                    let fourth_power = |q: usize, x: usize| match plans[q] {
+                        Some(Plan::Square(s)) if uses[q] == 1 && uses[s] == 2 => {
+                            matches!(plans[s], Some(Plan::Square(y)) if y == x).then_some(s)
+                        }
+                        _ => None,
+                    };
+                    if let Some(s) = fourth_power(a, b) {
+                        fused[a] = true;
+                        fused[s] = true;
+                        Plan::Pow5(b)
+                    } else if let Some(s) = fourth_power(b, a) {
+                        fused[b] = true;
+                        fused[s] = true;
+                        Plan::Pow5(a)
+                    } else {
+                        Plan::Mul(a, b)
+                    }
+                }
+            }),
+            Opcode::Op(Operation::Neg) => Some(Plan::Neg(a)),
+            Opcode::Op(op) => Some(Plan::Op(op, a, b)),
+        };
+        plans.push(plan);
+    }
+
+    // Constants used as regular operands or outputs are preloaded.
+    let mut slots = vec![u32::MAX; n];
+    let mut preload = Vec::new();
+    let mut preload_constant = |j: usize, slots: &mut [u32]| {
+        if slots[j] == u32::MAX {
+            if let Some(c) = constant(nodes, j) {
+                slots[j] = preload.len() as u32;
+                preload.push(c);
+            }
+        }
+    };
+    for (i, plan) in plans.iter().enumerate() {
+        if let (Some(plan), false) = (plan, fused[i]) {
+            plan.for_each_operand(nodes, |j| preload_constant(j, &mut slots));
+        }
+    }
+    for &i in outputs.iter() {
+        preload_constant(i, &mut slots);
+    }
+
+    // Assign slots and emit instructions.
+    let mut program = Program {
+        constants: Vec::new(),
+        instructions: Vec::new(),
+        destinations: Vec::new(),
+        registers: preload.len(),
+        bbfs: Vec::new(),
+        outputs: Vec::new(),
+        preload,
+    };
+    let mut steps = vec![0; n];
+    let mut constant_map = HashMap::new();
+    for (i, plan) in plans.into_iter().enumerate() {
+        let Some(plan) = plan.filter(|_| !fused[i]) else {
+            continue;
+        };
+        let s = |j: usize| {
+            debug_assert_ne!(slots[j], u32::MAX);
+            slots[j]
+        };
+        let mut pool = |c: F| {
+            *constant_map.entry(c).or_insert_with(|| {
+                program.constants.push(c);
+                program.constants.len() as u32 - 1
+            })
+        };
+        let instruction = match plan {
+            Plan::Input(j) => Instruction::Input(j as u32),
+            Plan::Add(a, b) => Instruction::Add(s(a), s(b)),
+            Plan::Sub(a, b) => Instruction::Sub(s(a), s(b)),
+            Plan::Mul(a, b) => Instruction::Mul(s(a), s(b)),
+            Plan::Neg(a) => Instruction::Neg(s(a)),
+            Plan::AddConst(a, c) => Instruction::AddConst(s(a), pool(c)),
+            Plan::MulConst(a, c) => Instruction::MulConst(s(a), pool(c)),
+            Plan::MulAdd(a, b, c) => Instruction::MulAdd(s(a), s(b), s(c)),
+            Plan::Square(a) => Instruction::Square(s(a)),
+            Plan::Pow5(a) => Instruction::Pow5(s(a)),
+            Plan::Op(op, a, b) => Instruction::Op(op, s(a), s(b)),
+            Plan::Call(j) => {
+                program.bbfs.push(BlackBoxCall {
+                    name: strip_suffix_number(nodes.name(j).to_string()),
+                    params: nodes.params(j).iter().map(|&p| s(p as usize)).collect(),
+                });
+                Instruction::BBF(program.bbfs.len() as u32 - 1)
+            }
+        };
+        program.instructions.push(instruction);
+        program.destinations.push(program.registers as u32);
+        slots[i] = program.registers as u32;
+        steps[i] = program.instructions.len() as u32;
+        program.registers += 1;
+    }
+    program.outputs = outputs
+        .iter()
+        .enumerate()
+        .map(|(position, &i)| Output {
+            step: steps[i],
+            register: slots[i],
+            position: position as u32,
+        })
+        .collect();
+    program.outputs.sort_by_key(|output| output.step);
+
+    #[cfg(feature = "std")]
+    eprintln!(
+        "Lowered {n} nodes to {} instructions and {} preloaded constants",
+        program.instructions.len(),
+        program.preload.len()
+    );
+
+    allocate_registers(&mut program);
+    program
+}
+
+/// Linear scan register allocation.
+///
+/// Registers are released after the last instruction reading them and reused
+/// for later results. Outputs are copied out right after they are computed,
+/// so they do not need to stay live until the end.
+fn allocate_registers<F>(program: &mut Program<F>) {
+    let preloaded = program.preload.len();
+    let values = program.registers;
+
+    // Find the last instruction reading every register.
+    let mut last_use = vec![None; values];
+    for (k, instruction) in program.instructions.iter_mut().enumerate() {
+        instruction.for_each_operand_mut(|r| last_use[*r as usize] = Some(k));
+        if let Instruction::BBF(i) = *instruction {
+            for &r in program.bbfs[i as usize].params.iter() {
+                last_use[r as usize] = Some(k);
+            }
+        }
+    }
+
+    let mut map: Vec<u32> = (0..values as u32).collect();
+    let mut free = Vec::new();
+    let mut registers = preloaded;
+    let mut operands = Vec::new();
+    for k in 0..program.instructions.len() {
+        let instruction = &mut program.instructions[k];
+        instruction.for_each_operand_mut(|r| {
+            operands.push(*r);
+            *r = map[*r as usize];
+        });
+        if let Instruction::BBF(i) = *instruction {
+            for r in program.bbfs[i as usize].params.iter_mut() {
+                operands.push(*r);
+                *r = map[*r as usize];
+            }
+        }
+
+        // Operands are read before the result is written, so the result can
+        // reuse the register of an operand that dies here.
+        for r in operands.drain(..) {
+            if r as usize >= preloaded && last_use[r as usize] == Some(k) {
+                last_use[r as usize] = None;
+                free.push(map[r as usize]);
+            }
+        }
+
+        let value = program.destinations[k] as usize;
+        let register = free.pop().unwrap_or_else(|| {
+            registers += 1;
+            registers as u32 - 1
+        });
+        map[value] = register;
+        program.destinations[k] = register;
+        if last_use[value].is_none() {
+            free.push(register);
+        }
+    }
+    for output in program.outputs.iter_mut() {
+        output.register = map[output.register as usize];
+    }
+    program.registers = registers;
+
+    #[cfg(feature = "std")]
+    eprintln!("Allocated {registers} registers for {values} values");
+}
+
+impl<F> Program<F> {
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// Number of registers needed for evaluation
+    pub fn registers(&self) -> usize {
+        self.registers
+    }
+}
+
+impl Program {
+    /// Calls black box function `i` with the parameters read through `v`
+    pub(crate) fn call_bbf(
+        &self,
+        i: u32,
+        bbfs: Option<&HashMap<String, BlackBoxFunction>>,
+        v: impl Fn(u32) -> Fr,
+    ) -> Result<Fr, Error> {
+        let call = &self.bbfs[i as usize];
+        let Some(bbfs) = bbfs else {
+            bail!("no black box functions provided");
+        };
+        let Some(bbf) = bbfs.get(&call.name) else {
+            bail!("black box function {:?} not found", call.name);
+        };
+        let params = call.params.iter().map(|&p| v(p)).collect::<Vec<_>>();
+        Ok(bbf(&params))
+    }
+
+    /// Evaluates the program and returns the outputs
+    pub fn evaluate(
+        &self,
+        inputs: &[U256],
+        bbfs: Option<&HashMap<String, BlackBoxFunction>>,
+    ) -> Result<Vec<U256>, Error> {
+        let mut values = vec![Fr::ZERO; self.registers];
+        values[..self.preload.len()].copy_from_slice(&self.preload);
+
+        // Convert from Montgomery form when copying out the outputs.
+        let mut out = vec![U256::ZERO; self.outputs.len()];
+        let mut outputs = self.outputs.iter().peekable();
+        let mut copy_outputs = |step: usize, values: &[Fr]| {
+            while let Some(output) = outputs.next_if(|output| output.step as usize == step) {
+                out[output.position as usize] = values[output.register as usize].into();
+            }
+        };
+        copy_outputs(0, &values);
+
+        for (k, instruction) in self.instructions.iter().enumerate() {
+            use Instruction::*;
+            let v = |i: u32| values[i as usize];
+            let value = match *instruction {
+                Input(i) => match inputs.get(i as usize) {
+                    Some(input) => Fr::new((*input).into()),
+                    None => Fr::new(U256::MAX.into()),
+                },
+                Add(a, b) => v(a) + v(b),
+                Sub(a, b) => v(a) - v(b),
+                Mul(a, b) => v(a) * v(b),
+                Neg(a) => -v(a),
+                AddConst(a, c) => v(a) + self.constants[c as usize],
+                MulConst(a, c) => v(a) * self.constants[c as usize],
+                MulAdd(a, b, c) => v(a) * v(b) + v(c),
+                Square(a) => v(a).square(),
+                Pow5(a) => {
+                    let x = v(a);
+                    x.square().square() * x
+                }
+                Op(op, a, b) => op.eval_fr(v(a), v(b)),
+                BBF(i) => self.call_bbf(i, bbfs, v)?,
+            };
+            values[self.destinations[k] as usize] = value;
+            copy_outputs(k + 1, &values);
+        }
+
+        Ok(out)
+    }
+}
@@ -0,0 +1,236 @@
+//! Stable C interface of the runtime, enabled by the `capi` feature. The
+//! `circom-witness-capi` crate builds it as the C library `libcwr`. The header
+//! `include/cwr.h` is generated by its tests with `BLESS=1`, which fail when
+//! it is out of date.
+//!
+//! Functions report failures through their return value, the message of the
+//! last error on the calling thread is available from [`cwr_last_error`].
+//! Panics are caught at the boundary and reported as errors.
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    ffi::{c_char, c_int, c_void, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr, slice,
+    sync::Arc,
+};
+
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use eyre::{bail, eyre};
+use ruint::aliases::U256;
+
+use crate::{
+    calculate_witness, flatten_inputs, init_graph, BlackBoxFunction, Graph, HashMap, InputValue,
+};
+
+/// Black box function callback.
+///
+/// Receives `nargs` field elements in `args` and writes the result to `out`,
+/// all as 32 byte little-endian integers. Returns 0 on success. The callback
+/// may be called from any thread that evaluates the graph.
+pub type CwrBlackBoxFn = Option<
+    unsafe extern "C" fn(
+        user_data: *mut c_void,
+        args: *const u8,
+        nargs: usize,
+        out: *mut u8,
+    ) -> c_int,
+>;
+
+/// Opaque graph handle
+pub struct CwrGraph {
+    graph: Graph,
+    bbfs: HashMap<String, BlackBoxFunction>,
+}
+
+struct Callback {
+    name: String,
+    f: unsafe extern "C" fn(*mut c_void, *const u8, usize, *mut u8) -> c_int,
+    user_data: *mut c_void,
+}
+
+// SAFETY: The caller of `cwr_graph_register_bbf` guarantees that the callback
+// and its user data can be used from any thread.
+unsafe impl Send for Callback {}
+unsafe impl Sync for Callback {}
+
+impl Callback {
+    fn call(&self, args: &[Fr]) -> Fr {
+        let args = args
+            .iter()
+            .flat_map(|&a| U256::from_limbs(a.into_bigint().0).to_le_bytes::<32>())
+            .collect::<Vec<_>>();
+        let mut out = [0u8; 32];
+        // SAFETY: The buffers have the sizes documented for `CwrBlackBoxFn`.
+        let status = unsafe {
+            (self.f)(
+                self.user_data,
+                args.as_ptr(),
+                args.len() / 32,
+                out.as_mut_ptr(),
+            )
+        };
+        if status != 0 {
+            panic!(
+                "black box function {:?} failed with status {status}",
+                self.name
+            );
+        }
+        Fr::new(U256::from_le_bytes(out).into())
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(error: String) {
+    let error = CString::new(error.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(error));
+}
+
+/// Runs `f`, storing errors and panics as the last error
+fn guard<T>(f: impl FnOnce() -> eyre::Result<T>) -> Option<T> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(error)) => {
+            set_last_error(format!("{error:#}"));
+            None
+        }
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<String>()
+                .map(String::as_str)
+                .or_else(|| panic.downcast_ref::<&str>().copied())
+                .unwrap_or("unknown panic");
+            set_last_error(format!("panic: {message}"));
+            None
+        }
+    }
+}
+
+/// Returns the message of the last error on this thread, or null.
+///
+/// The string is valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn cwr_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Loads a graph from `len` bytes, returns null on failure.
+///
+/// # Safety
+///
+/// `bytes` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cwr_graph_load(bytes: *const u8, len: usize) -> *mut CwrGraph {
+    guard(|| {
+        if bytes.is_null() {
+            bail!("graph bytes are null");
+        }
+        let graph = init_graph(slice::from_raw_parts(bytes, len))?;
+        Ok(Box::into_raw(Box::new(CwrGraph {
+            graph,
+            bbfs: HashMap::new(),
+        })))
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Frees a graph loaded with [`cwr_graph_load`], null is ignored.
+///
+/// # Safety
+///
+/// `graph` must be null or a handle returned by [`cwr_graph_load`] that is
+/// not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn cwr_graph_free(graph: *mut CwrGraph) {
+    if !graph.is_null() {
+        let _ = catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(graph))));
+    }
+}
+
+/// Registers the black box function `name`, without its numeric suffix.
+/// Returns 0 on success.
+///
+/// # Safety
+///
+/// `graph` must be a valid handle that is not used concurrently and `name` a
+/// NUL terminated string. `f` is called with `user_data`, both must stay
+/// valid and be safe to use from any thread while the graph is alive.
+#[no_mangle]
+pub unsafe extern "C" fn cwr_graph_register_bbf(
+    graph: *mut CwrGraph,
+    name: *const c_char,
+    f: CwrBlackBoxFn,
+    user_data: *mut c_void,
+) -> c_int {
+    guard(|| {
+        let (Some(graph), false) = (graph.as_mut(), name.is_null()) else {
+            bail!("graph or name is null");
+        };
+        let Some(f) = f else {
+            bail!("black box function is null");
+        };
+        let name = CStr::from_ptr(name).to_str()?.to_string();
+        let callback = Callback {
+            name: name.clone(),
+            f,
+            user_data,
+        };
+        let bbf: BlackBoxFunction = Arc::new(move |args| callback.call(args));
+        graph.bbfs.insert(name, bbf);
+        Ok(0)
+    })
+    .unwrap_or(-1)
+}
+
+/// Number of bytes written by [`cwr_calculate_witness`], 32 per element.
+///
+/// # Safety
+///
+/// `graph` must be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn cwr_witness_size(graph: *const CwrGraph) -> usize {
+    graph
+        .as_ref()
+        .map_or(0, |graph| graph.graph.signals.len() * 32)
+}
+
+/// Calculates the witness for the JSON encoded inputs and writes it to
+/// `out_buf` as 32 byte little-endian integers. Returns 0 on success.
+///
+/// # Safety
+///
+/// `graph` must be a valid handle, `inputs_json` a NUL terminated string and
+/// `out_buf` must point to `out_len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cwr_calculate_witness(
+    graph: *const CwrGraph,
+    inputs_json: *const c_char,
+    out_buf: *mut u8,
+    out_len: usize,
+) -> c_int {
+    guard(|| {
+        let (Some(graph), false) = (graph.as_ref(), inputs_json.is_null()) else {
+            bail!("graph or inputs are null");
+        };
+        let size = graph.graph.signals.len() * 32;
+        if out_buf.is_null() || out_len < size {
+            bail!("output buffer of {out_len} bytes is too small, {size} bytes are needed");
+        }
+        let inputs = CStr::from_ptr(inputs_json).to_str()?;
+        let inputs: BTreeMap<String, InputValue> =
+            serde_json::from_str(inputs).map_err(|e| eyre!("invalid inputs: {e}"))?;
+        let inputs = flatten_inputs(inputs)?;
+        let witness = calculate_witness(inputs, &graph.graph, Some(&graph.bbfs))?;
+        let out = slice::from_raw_parts_mut(out_buf, size);
+        for (chunk, value) in out.chunks_exact_mut(32).zip(witness) {
+            chunk.copy_from_slice(&value.to_le_bytes::<32>());
+        }
+        Ok(0)
+    })
+    .unwrap_or(-1)
+}
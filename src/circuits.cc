@@ -0,0 +1,39 @@
+#include "witness/include/witness.h"
+
+void run(size_t circuit, Circom_CalcWit *buf) { CIRCUITS[circuit].run(buf); }
+
+uint get_size_of_io_map(size_t circuit) {
+  return CIRCUITS[circuit].get_size_of_io_map();
+}
+
+uint get_total_signal_no(size_t circuit) {
+  return CIRCUITS[circuit].get_total_signal_no();
+}
+
+uint get_main_input_signal_no(size_t circuit) {
+  return CIRCUITS[circuit].get_main_input_signal_no();
+}
+
+uint get_main_input_signal_start(size_t circuit) {
+  return CIRCUITS[circuit].get_main_input_signal_start();
+}
+
+uint get_number_of_components(size_t circuit) {
+  return CIRCUITS[circuit].get_number_of_components();
+}
+
+uint get_size_of_constants(size_t circuit) {
+  return CIRCUITS[circuit].get_size_of_constants();
+}
+
+uint get_size_of_input_hashmap(size_t circuit) {
+  return CIRCUITS[circuit].get_size_of_input_hashmap();
+}
+
+uint get_size_of_witness(size_t circuit) {
+  return CIRCUITS[circuit].get_size_of_witness();
+}
+
+uint get_size_of_bus_field_map(size_t circuit) {
+  return CIRCUITS[circuit].get_size_of_bus_field_map();
+}
@@ -0,0 +1,439 @@
+//! Ahead-of-time code generation for Rust and C.
+//!
+//! [`rust`] turns an optimized graph into a standalone Rust source file with
+//! a straight-line `witness` function. Every node becomes a `let` binding and
+//! constants become `const` items, so the compiler can optimize the whole
+//! computation and the file can be audited in code review. Black box calls
+//! become methods of a generated `BlackBox` trait, named after the function
+//! without its numeric suffix and as a raw identifier if that is a keyword.
+//!
+//! The generated file depends on `ark-bn254` and `ark-ff`, and on
+//! `num-bigint` if the graph contains integer division or modulo.
+//!
+//! [`c`] emits a self-contained C library from the
+//! [bytecode](crate::bytecode) of a graph, with a portable Montgomery
+//! implementation of the field and the same register reuse as the bytecode
+//! interpreter. Values cross the C interface as 32 byte little-endian
+//! integers.
+//!
+//! The Rust generator only supports BN254 graphs and returns an error for
+//! other primes, the C generator supports every [`Prime`].
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Write,
+};
+
+use eyre::bail;
+use ruint::aliases::U256;
+
+use crate::{
+    bytecode::{self, Instruction},
+    graph::{assert_valid, strip_suffix_number, Operation},
+    nodes::Opcode,
+    prime::{with_field, GraphField, Prime},
+    Graph,
+};
+
+/// Generates the source of `fn witness(inputs: &[Fr], bbfs: &impl BlackBox) -> Vec<Fr>`.
+///
+/// The inputs are indexed like the inputs of [`crate::graph::evaluate`], the
+/// result contains the values of the signals in Montgomery form.
+pub fn rust(graph: &Graph) -> eyre::Result<String> {
+    let prime = graph.prime;
+    eyre::ensure!(
+        prime == Prime::Bn128,
+        "Rust code generation is not supported for {prime}"
+    );
+    let (nodes, outputs) = (&graph.nodes, &graph.signals);
+    assert_valid(nodes);
+
+    let mut constants = HashMap::new();
+    let mut bbfs = BTreeSet::new();
+    let mut bigint = false;
+    let mut body = String::new();
+    for i in 0..nodes.len() {
+        let (a, b) = nodes.operands(i);
+        let value = match nodes.opcode(i) {
+            Opcode::Input => format!("inputs[{a}]"),
+            opcode @ (Opcode::Constant | Opcode::MontConstant) => {
+                let key = (opcode == Opcode::MontConstant, nodes.constant(i));
+                let next = constants.len();
+                format!("C{}", constants.entry(key).or_insert(next))
+            }
+            Opcode::Op(op) => match op {
+                Operation::Add => format!("v{a} + v{b}"),
+                Operation::Sub => format!("v{a} - v{b}"),
+                Operation::Mul => format!("v{a} * v{b}"),
+                Operation::Div => format!("v{a} / v{b}"),
+                Operation::Neg => format!("-v{a}"),
+                Operation::Eq => format!("Fr::from(v{a} == v{b})"),
+                Operation::IDiv => {
+                    bigint = true;
+                    format!("idiv(v{a}, v{b})")
+                }
+                Operation::Mod => {
+                    bigint = true;
+                    format!("modulo(v{a}, v{b})")
+                }
+                _ => bail!("operator {op:?} not implemented for Montgomery form"),
+            },
+            Opcode::BBF => {
+                let name = strip_suffix_number(nodes.name(i).to_string());
+                let Some(name) = rust_identifier(&name) else {
+                    bail!("black box function {name:?} is not a valid identifier");
+                };
+                let params = nodes
+                    .params(i)
+                    .iter()
+                    .map(|p| format!("v{p}"))
+                    .collect::<Vec<_>>();
+                let call = format!("bbfs.{name}(&[{}])", params.join(", "));
+                bbfs.insert(name);
+                call
+            }
+        };
+        writeln!(body, "    let v{i} = {value};")?;
+    }
+
+    let mut out = String::new();
+    writeln!(out, "// Generated by circom-witness-rs, do not edit.")?;
+    writeln!(out)?;
+    writeln!(out, "use ark_bn254::Fr;")?;
+    if !constants.is_empty() {
+        writeln!(out, "use ark_ff::BigInt;")?;
+    }
+    if bigint {
+        writeln!(out, "use num_bigint::BigUint;")?;
+    }
+    writeln!(out)?;
+
+    writeln!(out, "pub trait BlackBox {{")?;
+    for name in bbfs.iter() {
+        writeln!(out, "    fn {name}(&self, args: &[Fr]) -> Fr;")?;
+    }
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+    if bbfs.is_empty() {
+        writeln!(out, "impl BlackBox for () {{}}")?;
+        writeln!(out)?;
+    }
+
+    let mut constants = constants.into_iter().collect::<Vec<_>>();
+    constants.sort_unstable_by_key(|&(_, k)| k);
+    for ((mont, c), k) in constants {
+        let limbs = limbs(c);
+        if mont {
+            writeln!(out, "const C{k}: Fr = Fr::new_unchecked(BigInt({limbs}));")?;
+        } else {
+            writeln!(out, "const C{k}: Fr = Fr::new(BigInt({limbs}));")?;
+        }
+    }
+    writeln!(out)?;
+
+    if bigint {
+        writeln!(out, "fn idiv(a: Fr, b: Fr) -> Fr {{")?;
+        writeln!(out, "    Fr::from(BigUint::from(a) / BigUint::from(b))")?;
+        writeln!(out, "}}")?;
+        writeln!(out)?;
+        writeln!(out, "fn modulo(a: Fr, b: Fr) -> Fr {{")?;
+        writeln!(out, "    Fr::from(BigUint::from(a) % BigUint::from(b))")?;
+        writeln!(out, "}}")?;
+        writeln!(out)?;
+    }
+
+    writeln!(out, "#[allow(unused_variables)]")?;
+    writeln!(
+        out,
+        "pub fn witness(inputs: &[Fr], bbfs: &impl BlackBox) -> Vec<Fr> {{"
+    )?;
+    out.push_str(&body);
+    let outputs = outputs.iter().map(|i| format!("v{i}")).collect::<Vec<_>>();
+    writeln!(out, "    vec![{}]", outputs.join(", "))?;
+    writeln!(out, "}}")?;
+
+    eprintln!(
+        "Generated {} lines of Rust for {} nodes",
+        out.lines().count(),
+        nodes.len()
+    );
+    Ok(out)
+}
+
+/// Header and source of a generated C library
+#[derive(Debug, Clone)]
+pub struct CLibrary {
+    pub header: String,
+    pub source: String,
+}
+
+/// Generates a C library with `int witness_calculate(const uint8_t *inputs, uint8_t *out)`.
+///
+/// The source includes the header as `"{name}.h"`. Inputs and outputs are
+/// arrays of 32 byte little-endian integers, indexed like the inputs of
+/// [`crate::graph::evaluate`] and in the order of the signals. Black box
+/// functions are declared in the header as
+/// `void witness_bbf_<name>(const uint8_t *args, size_t nargs, uint8_t *out)`
+/// and have to be provided by the application. The field arithmetic is
+/// generated for the prime of the graph.
+pub fn c(graph: &Graph, name: &str) -> eyre::Result<CLibrary> {
+    assert_valid(&graph.nodes);
+    with_field!(graph.prime, F => c_library::<F>(graph, name))
+}
+
+fn c_library<F: GraphField>(graph: &Graph, name: &str) -> eyre::Result<CLibrary> {
+    let program = bytecode::lower_nodes::<F>(&graph.nodes, &graph.signals);
+
+    let inputs = graph.inputs_size;
+    for instruction in program.instructions.iter() {
+        if let Instruction::Input(i) = *instruction {
+            eyre::ensure!(
+                (i as usize) < inputs,
+                "input {i} is out of bounds of the {inputs} inputs"
+            );
+        }
+    }
+    let mut bbfs = BTreeSet::new();
+    for call in program.bbfs.iter() {
+        if !is_identifier(&call.name) {
+            bail!(
+                "black box function {:?} is not a valid identifier",
+                call.name
+            );
+        }
+        bbfs.insert(call.name.as_str());
+    }
+
+    let guard = format!("{}_H", name.to_ascii_uppercase().replace(['-', '.'], "_"));
+    let mut header = String::new();
+    writeln!(header, "/* Generated by circom-witness-rs, do not edit. */")?;
+    writeln!(header)?;
+    writeln!(header, "#ifndef {guard}")?;
+    writeln!(header, "#define {guard}")?;
+    writeln!(header)?;
+    writeln!(header, "#include <stddef.h>")?;
+    writeln!(header, "#include <stdint.h>")?;
+    writeln!(header)?;
+    writeln!(header, "#ifdef __cplusplus")?;
+    writeln!(header, "extern \"C\" {{")?;
+    writeln!(header, "#endif")?;
+    writeln!(header)?;
+    writeln!(header, "#define WITNESS_NUM_INPUTS {inputs}")?;
+    writeln!(
+        header,
+        "#define WITNESS_NUM_OUTPUTS {}",
+        graph.signals.len()
+    )?;
+    writeln!(header)?;
+    writeln!(header, "#define WITNESS_OK 0")?;
+    writeln!(header, "#define WITNESS_ERR_ALLOC 1")?;
+    writeln!(header, "#define WITNESS_ERR_DIV_ZERO 2")?;
+    writeln!(header)?;
+    for name in bbfs.iter() {
+        writeln!(
+            header,
+            "void witness_bbf_{name}(const uint8_t *args, size_t nargs, uint8_t *out);"
+        )?;
+    }
+    if !bbfs.is_empty() {
+        writeln!(header)?;
+    }
+    writeln!(
+        header,
+        "/* Reads WITNESS_NUM_INPUTS and writes WITNESS_NUM_OUTPUTS values of 32 bytes. */"
+    )?;
+    writeln!(
+        header,
+        "int witness_calculate(const uint8_t *inputs, uint8_t *out);"
+    )?;
+    writeln!(header)?;
+    writeln!(header, "#ifdef __cplusplus")?;
+    writeln!(header, "}}")?;
+    writeln!(header, "#endif")?;
+    writeln!(header)?;
+    writeln!(header, "#endif")?;
+
+    let mut source = String::new();
+    writeln!(source, "/* Generated by circom-witness-rs, do not edit. */")?;
+    writeln!(source)?;
+    writeln!(source, "#include \"{name}.h\"")?;
+    writeln!(source)?;
+    writeln!(source, "#include <stdlib.h>")?;
+    writeln!(source, "#include <string.h>")?;
+    writeln!(source)?;
+    let m = F::modulus();
+    let fr = |f: F| c_limbs(f.montgomery());
+    let inv = (0..63).fold(1u64, |inv, _| {
+        inv.wrapping_mul(inv).wrapping_mul(m.as_limbs()[0])
+    });
+    let r2 = F::from_u256(U256::MAX % m + U256::from(1));
+    writeln!(source, "typedef struct {{")?;
+    writeln!(source, "    uint64_t l[4];")?;
+    writeln!(source, "}} fr_t;")?;
+    writeln!(source)?;
+    writeln!(source, "static const fr_t FR_P = {};", c_limbs(m))?;
+    writeln!(source, "static const fr_t FR_R2 = {};", fr(r2))?;
+    writeln!(source, "static const fr_t FR_ONE = {};", fr(F::ONE))?;
+    writeln!(
+        source,
+        "static const uint64_t FR_INV = {:#018x}ULL;",
+        inv.wrapping_neg()
+    )?;
+    writeln!(source)?;
+    source.push_str(include_str!("codegen/fr.c"));
+    writeln!(source)?;
+
+    if !program.preload.is_empty() {
+        writeln!(
+            source,
+            "static const fr_t PRELOAD[{}] = {{",
+            program.preload.len()
+        )?;
+        for &c in program.preload.iter() {
+            writeln!(source, "    {},", fr(c))?;
+        }
+        writeln!(source, "}};")?;
+        writeln!(source)?;
+    }
+    if !program.constants.is_empty() {
+        writeln!(
+            source,
+            "static const fr_t K[{}] = {{",
+            program.constants.len()
+        )?;
+        for &c in program.constants.iter() {
+            writeln!(source, "    {},", fr(c))?;
+        }
+        writeln!(source, "}};")?;
+        writeln!(source)?;
+    }
+
+    writeln!(
+        source,
+        "int witness_calculate(const uint8_t *inputs, uint8_t *out) {{"
+    )?;
+    writeln!(
+        source,
+        "    fr_t *r = malloc({} * sizeof(fr_t));",
+        program.registers.max(1)
+    )?;
+    writeln!(source, "    int status = WITNESS_OK;")?;
+    writeln!(source, "    if (!r) return WITNESS_ERR_ALLOC;")?;
+    if !program.preload.is_empty() {
+        writeln!(source, "    memcpy(r, PRELOAD, sizeof PRELOAD);")?;
+    }
+    let mut outputs = program.outputs.iter().peekable();
+    let mut copy_outputs = |source: &mut String, step: usize| -> std::fmt::Result {
+        while let Some(output) = outputs.next_if(|output| output.step as usize == step) {
+            let (position, register) = (output.position, output.register);
+            writeln!(
+                source,
+                "    fr_to_bytes(out + {position} * 32, &r[{register}]);"
+            )?;
+        }
+        Ok(())
+    };
+    copy_outputs(&mut source, 0)?;
+    for (k, instruction) in program.instructions.iter().enumerate() {
+        use Instruction::*;
+        let d = program.destinations[k];
+        match *instruction {
+            Input(i) => writeln!(source, "    fr_from_bytes(&r[{d}], inputs + {i} * 32);")?,
+            Add(a, b) => writeln!(source, "    fr_add(&r[{d}], &r[{a}], &r[{b}]);")?,
+            Sub(a, b) => writeln!(source, "    fr_sub(&r[{d}], &r[{a}], &r[{b}]);")?,
+            Mul(a, b) => writeln!(source, "    fr_mul(&r[{d}], &r[{a}], &r[{b}]);")?,
+            Neg(a) => writeln!(source, "    fr_neg(&r[{d}], &r[{a}]);")?,
+            AddConst(a, c) => writeln!(source, "    fr_add(&r[{d}], &r[{a}], &K[{c}]);")?,
+            MulConst(a, c) => writeln!(source, "    fr_mul(&r[{d}], &r[{a}], &K[{c}]);")?,
+            MulAdd(a, b, c) => writeln!(
+                source,
+                "    fr_mul_add(&r[{d}], &r[{a}], &r[{b}], &r[{c}]);"
+            )?,
+            Square(a) => writeln!(source, "    fr_square(&r[{d}], &r[{a}]);")?,
+            Pow5(a) => writeln!(source, "    fr_pow5(&r[{d}], &r[{a}]);")?,
+            Op(op, a, b) => {
+                let call = match op {
+                    Operation::Add => format!("fr_add(&r[{d}], &r[{a}], &r[{b}])"),
+                    Operation::Sub => format!("fr_sub(&r[{d}], &r[{a}], &r[{b}])"),
+                    Operation::Mul => format!("fr_mul(&r[{d}], &r[{a}], &r[{b}])"),
+                    Operation::Neg => format!("fr_neg(&r[{d}], &r[{a}])"),
+                    Operation::Eq => format!("fr_eq(&r[{d}], &r[{a}], &r[{b}])"),
+                    Operation::Div => format!("status = fr_div(&r[{d}], &r[{a}], &r[{b}])"),
+                    Operation::IDiv => {
+                        format!("status = fr_divmod(&r[{d}], NULL, &r[{a}], &r[{b}])")
+                    }
+                    Operation::Mod => {
+                        format!("status = fr_divmod(NULL, &r[{d}], &r[{a}], &r[{b}])")
+                    }
+                    _ => bail!("operator {op:?} not implemented for Montgomery form"),
+                };
+                writeln!(source, "    {call};")?;
+                if call.starts_with("status") {
+                    writeln!(source, "    if (status) goto done;")?;
+                }
+            }
+            BBF(i) => {
+                let call = &program.bbfs[i as usize];
+                let n = call.params.len();
+                writeln!(source, "    {{")?;
+                writeln!(source, "        uint8_t args[{}], res[32];", n.max(1) * 32)?;
+                for (j, p) in call.params.iter().enumerate() {
+                    writeln!(source, "        fr_to_bytes(args + {j} * 32, &r[{p}]);")?;
+                }
+                writeln!(source, "        witness_bbf_{}(args, {n}, res);", call.name)?;
+                writeln!(source, "        fr_from_bytes(&r[{d}], res);")?;
+                writeln!(source, "    }}")?;
+            }
+        }
+        copy_outputs(&mut source, k + 1)?;
+    }
+    writeln!(source, "done:")?;
+    writeln!(source, "    free(r);")?;
+    writeln!(source, "    return status;")?;
+    writeln!(source, "}}")?;
+
+    eprintln!(
+        "Generated {} lines of C for {} instructions",
+        header.lines().count() + source.lines().count(),
+        program.instructions.len()
+    );
+    Ok(CLibrary { header, source })
+}
+
+fn limbs(c: U256) -> String {
+    let limbs = c.as_limbs().map(|l| format!("{l:#018x}"));
+    format!("[{}]", limbs.join(", "))
+}
+
+/// Initializer of an `fr_t` with the limbs of `c`
+fn c_limbs(c: U256) -> String {
+    let limbs = c.as_limbs().map(|l| format!("{l:#018x}ULL"));
+    format!("{{{{{}}}}}", limbs.join(", "))
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Strict and reserved keywords up to Rust 2024
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// `s` as a Rust method name, keywords become raw identifiers. `None` if it
+/// is not an identifier or a keyword that cannot be raw.
+fn rust_identifier(s: &str) -> Option<String> {
+    if !is_identifier(s) || matches!(s, "_" | "crate" | "self" | "Self" | "super") {
+        None
+    } else if RUST_KEYWORDS.contains(&s) {
+        Some(format!("r#{s}"))
+    } else {
+        Some(s.to_string())
+    }
+}
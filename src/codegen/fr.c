@@ -0,0 +1,179 @@
+/* Portable 4x64-limb Montgomery arithmetic. Expects fr_t and the constants
+ * FR_P, FR_R2, FR_ONE and FR_INV to be defined for the prime. Elements are
+ * kept fully reduced, so equal values have equal limbs. */
+
+#if defined(__SIZEOF_INT128__)
+__extension__ typedef unsigned __int128 fr_u128;
+
+static inline uint64_t mac(uint64_t a, uint64_t b, uint64_t c, uint64_t *carry) {
+    fr_u128 t = (fr_u128)b * c + a + *carry;
+    *carry = (uint64_t)(t >> 64);
+    return (uint64_t)t;
+}
+#else
+static inline uint64_t mac(uint64_t a, uint64_t b, uint64_t c, uint64_t *carry) {
+    uint64_t bl = b & 0xffffffff, bh = b >> 32, cl = c & 0xffffffff, ch = c >> 32;
+    uint64_t ll = bl * cl, lh = bl * ch, hl = bh * cl, hh = bh * ch;
+    uint64_t mid = (ll >> 32) + (lh & 0xffffffff) + (hl & 0xffffffff);
+    uint64_t lo = (ll & 0xffffffff) | (mid << 32);
+    uint64_t hi = hh + (lh >> 32) + (hl >> 32) + (mid >> 32);
+    lo += a;
+    hi += lo < a;
+    lo += *carry;
+    hi += lo < *carry;
+    *carry = hi;
+    return lo;
+}
+#endif
+
+static inline uint64_t adc(uint64_t a, uint64_t b, uint64_t *carry) {
+    uint64_t t = a + b;
+    uint64_t r = t + *carry;
+    *carry = (t < a) | (r < t);
+    return r;
+}
+
+static inline uint64_t sbb(uint64_t a, uint64_t b, uint64_t *borrow) {
+    uint64_t t = a - b;
+    uint64_t r = t - *borrow;
+    *borrow = (a < b) | (t < *borrow);
+    return r;
+}
+
+/* r = s - p if s + hi * 2^256 >= p, else s */
+static inline void fr_reduce(fr_t *r, const uint64_t s[4], uint64_t hi) {
+    uint64_t t[4], borrow = 0;
+    for (int i = 0; i < 4; i++) t[i] = sbb(s[i], FR_P.l[i], &borrow);
+    int keep = !hi && borrow;
+    for (int i = 0; i < 4; i++) r->l[i] = keep ? s[i] : t[i];
+}
+
+static inline int fr_is_zero(const fr_t *a) {
+    return (a->l[0] | a->l[1] | a->l[2] | a->l[3]) == 0;
+}
+
+static inline void fr_add(fr_t *r, const fr_t *a, const fr_t *b) {
+    uint64_t s[4], carry = 0;
+    for (int i = 0; i < 4; i++) s[i] = adc(a->l[i], b->l[i], &carry);
+    fr_reduce(r, s, carry);
+}
+
+static inline void fr_sub(fr_t *r, const fr_t *a, const fr_t *b) {
+    uint64_t s[4], borrow = 0, carry = 0;
+    for (int i = 0; i < 4; i++) s[i] = sbb(a->l[i], b->l[i], &borrow);
+    uint64_t mask = 0 - borrow;
+    for (int i = 0; i < 4; i++) r->l[i] = adc(s[i], FR_P.l[i] & mask, &carry);
+}
+
+static inline void fr_neg(fr_t *r, const fr_t *a) {
+    fr_t zero = {{0, 0, 0, 0}};
+    fr_sub(r, &zero, a);
+}
+
+/* Coarsely integrated operand scanning Montgomery multiplication */
+static inline void fr_mul(fr_t *r, const fr_t *a, const fr_t *b) {
+    uint64_t t[6] = {0, 0, 0, 0, 0, 0};
+    for (int i = 0; i < 4; i++) {
+        uint64_t carry = 0, c = 0;
+        for (int j = 0; j < 4; j++) t[j] = mac(t[j], a->l[j], b->l[i], &carry);
+        t[4] = adc(t[4], carry, &c);
+        t[5] = c;
+
+        uint64_t m = t[0] * FR_INV;
+        carry = 0;
+        mac(t[0], m, FR_P.l[0], &carry);
+        for (int j = 1; j < 4; j++) t[j - 1] = mac(t[j], m, FR_P.l[j], &carry);
+        c = 0;
+        t[3] = adc(t[4], carry, &c);
+        t[4] = t[5] + c;
+    }
+    fr_reduce(r, t, t[4]);
+}
+
+static inline void fr_square(fr_t *r, const fr_t *a) {
+    fr_mul(r, a, a);
+}
+
+static inline void fr_pow5(fr_t *r, const fr_t *a) {
+    fr_t t;
+    fr_mul(&t, a, a);
+    fr_mul(&t, &t, &t);
+    fr_mul(r, &t, a);
+}
+
+static inline void fr_mul_add(fr_t *r, const fr_t *a, const fr_t *b, const fr_t *c) {
+    fr_t t;
+    fr_mul(&t, a, b);
+    fr_add(r, &t, c);
+}
+
+/* r = a^(p - 2), the inverse of a nonzero element */
+static inline void fr_inv(fr_t *r, const fr_t *a) {
+    uint64_t e[4], borrow = 0;
+    for (int i = 0; i < 4; i++) e[i] = sbb(FR_P.l[i], i == 0 ? 2 : 0, &borrow);
+    fr_t x = *a, acc = FR_ONE;
+    for (int i = 255; i >= 0; i--) {
+        fr_mul(&acc, &acc, &acc);
+        if ((e[i / 64] >> (i % 64)) & 1) fr_mul(&acc, &acc, &x);
+    }
+    *r = acc;
+}
+
+static inline int fr_div(fr_t *r, const fr_t *a, const fr_t *b) {
+    if (fr_is_zero(b)) return WITNESS_ERR_DIV_ZERO;
+    fr_t t;
+    fr_inv(&t, b);
+    fr_mul(r, a, &t);
+    return WITNESS_OK;
+}
+
+static inline void fr_from_mont(uint64_t r[4], const fr_t *a) {
+    fr_t one = {{1, 0, 0, 0}}, t;
+    fr_mul(&t, a, &one);
+    memcpy(r, t.l, sizeof t.l);
+}
+
+static inline void fr_to_mont(fr_t *r, const uint64_t a[4]) {
+    fr_t t;
+    memcpy(t.l, a, sizeof t.l);
+    fr_mul(r, &t, &FR_R2);
+}
+
+static inline void fr_from_bytes(fr_t *r, const uint8_t *bytes) {
+    uint64_t a[4] = {0, 0, 0, 0};
+    for (int i = 0; i < 32; i++) a[i / 8] |= (uint64_t)bytes[i] << (8 * (i % 8));
+    fr_to_mont(r, a);
+}
+
+static inline void fr_to_bytes(uint8_t *bytes, const fr_t *a) {
+    uint64_t t[4];
+    fr_from_mont(t, a);
+    for (int i = 0; i < 32; i++) bytes[i] = (uint8_t)(t[i / 8] >> (8 * (i % 8)));
+}
+
+/* Integer quotient and remainder of the canonical representatives */
+static inline int fr_divmod(fr_t *q, fr_t *rem, const fr_t *a, const fr_t *b) {
+    if (fr_is_zero(b)) return WITNESS_ERR_DIV_ZERO;
+    uint64_t n[4], d[4], qt[4] = {0, 0, 0, 0}, rt[4] = {0, 0, 0, 0};
+    fr_from_mont(n, a);
+    fr_from_mont(d, b);
+    for (int i = 255; i >= 0; i--) {
+        uint64_t top = rt[3] >> 63;
+        for (int j = 3; j > 0; j--) rt[j] = (rt[j] << 1) | (rt[j - 1] >> 63);
+        rt[0] = (rt[0] << 1) | ((n[i / 64] >> (i % 64)) & 1);
+        uint64_t t[4], borrow = 0;
+        for (int j = 0; j < 4; j++) t[j] = sbb(rt[j], d[j], &borrow);
+        if (top || !borrow) {
+            memcpy(rt, t, sizeof t);
+            qt[i / 64] |= (uint64_t)1 << (i % 64);
+        }
+    }
+    if (q) fr_to_mont(q, qt);
+    if (rem) fr_to_mont(rem, rt);
+    return WITNESS_OK;
+}
+
+static inline void fr_eq(fr_t *r, const fr_t *a, const fr_t *b) {
+    fr_t zero = {{0, 0, 0, 0}};
+    *r = memcmp(a->l, b->l, sizeof a->l) == 0 ? FR_ONE : zero;
+}
@@ -0,0 +1,270 @@
+//! Parser for the `constants.dat` file that `circom --c` generates.
+//!
+//! The file has no header, the number of entries of each section is compiled
+//! into the generated C++ code. Sections follow each other without padding,
+//! all integers are little endian:
+//!
+//! | section           | entry                                                   |
+//! |-------------------|---------------------------------------------------------|
+//! | input hash map    | `u64` hash, `u64` signal id, `u64` signal size          |
+//! | witness to signal | `u64` signal id                                         |
+//! | constants         | `i32` short value, `u32` type, `8 * limbs` bytes        |
+//! | IO signal map     | `u32` template ids, then the fields of each template    |
+//! | bus field map     | `u32` bus ids, then the fields of each bus              |
+//!
+//! The fields of a template or bus are a `u32` count followed by
+//! [`IOFieldDef`]s as `u32` offset, `u32` dimension count, the `u32` lengths,
+//! `u32` size and `u32` bus id. The bus field map exists since circom 2.2.
+//!
+//! The parser does not depend on the generated code, so it is available to
+//! tooling without the `build-witness` feature.
+
+use alloc::{string::String, vec, vec::Vec};
+
+use ruint::aliases::U256;
+
+use crate::{
+    error::{ensure, err, Error},
+    prime::Prime,
+    HashSignalInfo,
+};
+
+/// Number of entries of each section, from the generated
+/// `get_size_of_*` functions
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SectionCounts {
+    pub input_hash_map: usize,
+    pub witness: usize,
+    pub constants: usize,
+    pub io_map: usize,
+    pub bus_field_map: usize,
+    /// Total number of signals, from `get_total_signal_no`. Signal ids of the
+    /// input hash map and the witness must be below it.
+    pub signals: usize,
+}
+
+/// A signal or bus field of a template, as seen from its parent
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IOFieldDef {
+    /// Offset of the field in the signals of the template
+    pub offset: usize,
+    /// Dimensions, empty for a single value
+    pub lengths: Vec<usize>,
+    /// Number of signals of one element, 1 unless it is a bus
+    pub size: u32,
+    /// The bus type of the field in the bus field map, if `size` is not 1
+    pub bus_id: u32,
+}
+
+/// The sections of a `constants.dat` file
+#[derive(Debug, Default, Clone)]
+pub struct DatFile {
+    pub input_hash_map: Vec<HashSignalInfo>,
+    /// Signal of each witness element
+    pub witness_to_signal: Vec<usize>,
+    /// Canonical values of the circuit constants
+    pub constants: Vec<U256>,
+    /// IO fields indexed by template id, empty for templates without an entry
+    pub io_signals: Vec<Vec<IOFieldDef>>,
+    /// Fields indexed by bus id
+    pub bus_fields: Vec<Vec<IOFieldDef>>,
+}
+
+/// Bounds checked little endian reader
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    section: &'static str,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| {
+                err!(
+                    "constants.dat is truncated in the {} at offset {}, {} bytes needed and {} left",
+                    self.section,
+                    self.offset,
+                    len,
+                    self.bytes.len() - self.offset
+                )
+            })?;
+        let bytes = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_i32(&mut self) -> Result<i32, Error> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, Error> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Reads a `u64` that is used as an index or size
+    fn read_usize(&mut self) -> Result<usize, Error> {
+        let offset = self.offset;
+        let value = self.read_u64()?;
+        usize::try_from(value).map_err(|_| {
+            err!("value {value} at offset {offset} of constants.dat does not fit in usize")
+        })
+    }
+}
+
+/// Number of 64 bit limbs of the field elements in the generated C++ code
+pub fn element_limbs(prime: Prime) -> usize {
+    prime.modulus().bit_len().div_ceil(64)
+}
+
+impl DatFile {
+    /// Parses `bytes` generated for `prime`. Bytes after the last section are
+    /// ignored.
+    pub fn parse(bytes: &[u8], counts: SectionCounts, prime: Prime) -> Result<DatFile, Error> {
+        let mut reader = Reader {
+            bytes,
+            offset: 0,
+            section: "input hash map",
+        };
+
+        let mut input_hash_map = Vec::with_capacity(counts.input_hash_map.min(bytes.len() / 24));
+        for _ in 0..counts.input_hash_map {
+            let offset = reader.offset;
+            let info = HashSignalInfo {
+                hash: reader.read_u64()?,
+                signalid: reader.read_u64()?,
+                signalsize: reader.read_u64()?,
+                name: String::new(),
+                shape: vec![],
+            };
+            // Empty slots of the hash map have no signals.
+            ensure!(
+                info.signalsize == 0
+                    || info
+                        .signalid
+                        .checked_add(info.signalsize)
+                        .is_some_and(|end| end <= counts.signals as u64),
+                "input at offset {offset} of constants.dat is out of range of the {} signals",
+                counts.signals
+            );
+            input_hash_map.push(info);
+        }
+
+        reader.section = "witness to signal list";
+        let mut witness_to_signal = Vec::with_capacity(counts.witness.min(bytes.len() / 8));
+        for _ in 0..counts.witness {
+            let offset = reader.offset;
+            let signal = reader.read_usize()?;
+            ensure!(
+                signal < counts.signals,
+                "signal {signal} at offset {offset} of constants.dat is out of range of the {} signals",
+                counts.signals
+            );
+            witness_to_signal.push(signal);
+        }
+
+        reader.section = "constants";
+        let constants = parse_constants(&mut reader, counts.constants, prime)?;
+
+        reader.section = "IO signal map";
+        let io_signals = parse_field_map(&mut reader, counts.io_map)?;
+
+        reader.section = "bus field map";
+        let bus_fields = parse_field_map(&mut reader, counts.bus_field_map)?;
+
+        Ok(DatFile {
+            input_hash_map,
+            witness_to_signal,
+            constants,
+            io_signals,
+            bus_fields,
+        })
+    }
+}
+
+fn parse_constants(reader: &mut Reader, count: usize, prime: Prime) -> Result<Vec<U256>, Error> {
+    if count == 0 {
+        return Ok(vec![]);
+    }
+
+    // Long constants are in Montgomery form with R = 2^(64 * limbs).
+    let m = prime.modulus();
+    let limbs = element_limbs(prime);
+    let r_inv = U256::from(2)
+        .pow_mod(U256::from(64 * limbs), m)
+        .inv_mod(m)
+        .unwrap();
+
+    let mut constants = Vec::with_capacity(count.min(reader.bytes.len() / (8 + 8 * limbs)));
+    for _ in 0..count {
+        let short = reader.read_i32()?;
+        let typ = reader.read_u32()?;
+        let offset = reader.offset;
+        let long = reader.take(8 * limbs)?;
+
+        let value = if typ & 0x80000000 == 0 {
+            if short < 0 {
+                m - U256::from(short.unsigned_abs())
+            } else {
+                U256::from(short as u32)
+            }
+        } else {
+            let raw = U256::from_le_slice(long);
+            ensure!(
+                raw < m,
+                "constant at offset {offset} of constants.dat is not reduced"
+            );
+            raw.mul_mod(r_inv, m)
+        };
+        constants.push(value);
+    }
+    Ok(constants)
+}
+
+/// Parses the fields of `count` templates or buses, indexed by their id
+fn parse_field_map(reader: &mut Reader, count: usize) -> Result<Vec<Vec<IOFieldDef>>, Error> {
+    if count == 0 {
+        return Ok(vec![]);
+    }
+
+    let mut ids = Vec::with_capacity(count.min(reader.bytes.len() / 4));
+    for _ in 0..count {
+        ids.push(reader.read_u32()? as usize);
+    }
+
+    let max_id = ids.iter().copied().max().unwrap_or(0);
+    ensure!(
+        max_id < reader.bytes.len(),
+        "id {max_id} in the {} of constants.dat is out of range",
+        reader.section
+    );
+    let mut map = vec![Vec::new(); max_id + 1];
+
+    for id in ids {
+        let fields = reader.read_u32()? as usize;
+        let mut defs = Vec::with_capacity(fields.min(reader.bytes.len() / 16));
+        for _ in 0..fields {
+            let offset = reader.read_u32()? as usize;
+            let dimensions = reader.read_u32()? as usize;
+            let mut lengths = Vec::with_capacity(dimensions.min(reader.bytes.len() / 4));
+            for _ in 0..dimensions {
+                lengths.push(reader.read_u32()? as usize);
+            }
+            defs.push(IOFieldDef {
+                offset,
+                lengths,
+                size: reader.read_u32()?,
+                bus_id: reader.read_u32()?,
+            });
+        }
+        map[id] = defs;
+    }
+    Ok(map)
+}
@@ -0,0 +1,60 @@
+//! Error type of the runtime, available without `std`.
+//!
+//! [`Error`] implements `core::error::Error`, so it converts into
+//! `eyre::Report` and other error types with `?`.
+
+use alloc::string::{String, ToString};
+use core::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    message: String,
+}
+
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
+impl Error {
+    pub fn msg(message: impl fmt::Display) -> Self {
+        Error {
+            message: message.to_string(),
+        }
+    }
+
+    /// Prepends `context` to the message
+    pub fn context(self, context: impl fmt::Display) -> Self {
+        Error::msg(format_args!("{context}: {}", self.message))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// Creates an [`Error`] from a format string
+macro_rules! err {
+    ($($arg:tt)*) => {
+        $crate::error::Error::msg(::core::format_args!($($arg)*))
+    };
+}
+
+/// Returns early with an [`Error`]
+macro_rules! bail {
+    ($($arg:tt)*) => {
+        return ::core::result::Result::Err($crate::error::err!($($arg)*))
+    };
+}
+
+/// Returns early with an [`Error`] if the condition does not hold
+macro_rules! ensure {
+    ($cond:expr, $($arg:tt)*) => {
+        if !$cond {
+            $crate::error::bail!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use {bail, ensure, err};
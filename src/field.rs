@@ -1,46 +1,124 @@
 use crate::{
     graph::{Node, Operation},
-    M,
+    nodes::Nodes,
+    prime::Prime,
 };
 use rand::Rng;
 use ruint::aliases::U256;
-use std::{ptr, sync::Mutex};
+use std::{cell::RefCell, ptr, sync::LazyLock};
 
-pub const INV: u64 = 14042775128853446655;
+/// The prime passed to circom by the build script
+pub static PRIME: LazyLock<Prime> =
+    LazyLock::new(|| env!("WITNESS_PRIME").parse().expect("unsupported prime"));
 
-static NODES: Mutex<Vec<Node>> = Mutex::new(Vec::new());
-static VALUES: Mutex<Vec<U256>> = Mutex::new(Vec::new());
-static CONSTANT: Mutex<Vec<bool>> = Mutex::new(Vec::new());
+/// Records the graph while the generated circuit runs.
+///
+/// The field hooks called from C++ have no context parameter, so they write
+/// into the builder installed on the current thread by [`GraphBuilder::record`].
+/// Builds on different threads are independent of each other.
+#[derive(Debug, Default)]
+pub struct GraphBuilder {
+    prime: Prime,
+    nodes: Nodes,
+    values: Vec<U256>,
+    constant: Vec<bool>,
+}
+
+thread_local! {
+    static BUILDER: RefCell<Option<GraphBuilder>> = const { RefCell::new(None) };
+}
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct FrElement(pub usize);
 
-#[allow(warnings)]
-pub fn print_eval() {
-    let nodes = NODES.lock().unwrap();
-    let values = VALUES.lock().unwrap();
-    let constant = CONSTANT.lock().unwrap();
-
-    let mut constants = 0_usize;
-    for (i, node) in nodes.iter().enumerate() {
-        print!("{}: {:?}", i, node);
-        if constant[i] {
-            constants += 1;
-            println!(" = {}", values[i]);
-        } else {
-            println!();
-        }
+/// Runs `f` with the builder of the current thread
+fn with_builder<T>(f: impl FnOnce(&mut GraphBuilder) -> T) -> T {
+    BUILDER.with_borrow_mut(|builder| {
+        f(builder
+            .as_mut()
+            .expect("field operation outside of GraphBuilder::record"))
+    })
+}
+
+/// Takes the builder back from the current thread, also when unwinding
+struct Restore<'a>(&'a mut Option<GraphBuilder>);
+
+impl Drop for Restore<'_> {
+    fn drop(&mut self) {
+        *self.0 = BUILDER.take();
     }
-    eprintln!(
-        "{} nodes of which {} constant and {} dynamic",
-        nodes.len(),
-        constants,
-        nodes.len() - constants
-    );
 }
 
-pub fn get_graph() -> Vec<Node> {
-    NODES.lock().unwrap().clone()
+impl GraphBuilder {
+    pub fn new(prime: Prime) -> Self {
+        GraphBuilder {
+            prime,
+            ..Default::default()
+        }
+    }
+
+    /// Runs `f` with this builder receiving the field operations of the
+    /// current thread
+    pub fn record<T>(&mut self, f: impl FnOnce() -> T) -> T {
+        let mut slot = Some(std::mem::take(self));
+        BUILDER.with_borrow_mut(|builder| {
+            assert!(builder.is_none(), "already recording a graph");
+            *builder = slot.take();
+        });
+        let result = {
+            let _restore = Restore(&mut slot);
+            f()
+        };
+        *self = slot.expect("graph builder lost");
+        result
+    }
+
+    pub fn into_nodes(self) -> Nodes {
+        self.nodes
+    }
+
+    #[allow(warnings)]
+    pub fn print_eval(&self) {
+        let mut constants = 0_usize;
+        for (i, node) in self.nodes.iter().enumerate() {
+            print!("{}: {:?}", i, node);
+            if self.constant[i] {
+                constants += 1;
+                println!(" = {}", self.values[i]);
+            } else {
+                println!();
+            }
+        }
+        eprintln!(
+            "{} nodes of which {} constant and {} dynamic",
+            self.nodes.len(),
+            constants,
+            self.nodes.len() - constants
+        );
+    }
+
+    fn push(&mut self, node: Node, value: U256, constant: bool) -> usize {
+        assert_eq!(self.nodes.len(), self.values.len());
+        assert_eq!(self.nodes.len(), self.constant.len());
+        self.values.push(value);
+        self.constant.push(constant);
+        self.nodes.push(node)
+    }
+
+    fn binop(&mut self, op: Operation, a: usize, b: usize) -> usize {
+        assert!(a < self.nodes.len());
+        assert!(b < self.nodes.len());
+        let value = op.eval_mod(self.values[a], self.values[b], self.prime.modulus());
+        let constant = self.constant[a] && self.constant[b];
+        self.push(Node::Op(op, a, b), value, constant)
+    }
+
+    /// Value of a node that must be constant
+    fn constant_value(&self, a: usize) -> U256 {
+        assert!(a < self.nodes.len());
+        assert!(self.constant[a]);
+        self.values[a]
+    }
 }
 
 pub fn undefined(i: usize) -> FrElement {
@@ -48,51 +126,16 @@ pub fn undefined(i: usize) -> FrElement {
 }
 
 pub fn constant(c: U256) -> FrElement {
-    let mut nodes = NODES.lock().unwrap();
-    let mut values = VALUES.lock().unwrap();
-    let mut constant = CONSTANT.lock().unwrap();
-    assert_eq!(nodes.len(), values.len());
-    assert_eq!(nodes.len(), constant.len());
-
-    nodes.push(Node::Constant(c));
-    values.push(c);
-    constant.push(true);
-
-    FrElement(nodes.len() - 1)
+    FrElement(with_builder(|b| b.push(Node::Constant(c), c, true)))
 }
 
 pub fn input(i: usize, value: U256) -> FrElement {
-    let mut nodes = NODES.lock().unwrap();
-    let mut values = VALUES.lock().unwrap();
-    let mut constant = CONSTANT.lock().unwrap();
-    assert_eq!(nodes.len(), values.len());
-    assert_eq!(nodes.len(), constant.len());
-
-    nodes.push(Node::Input(i));
-    values.push(value);
-    constant.push(false);
-
-    FrElement(nodes.len() - 1)
+    FrElement(with_builder(|b| b.push(Node::Input(i), value, false)))
 }
 
 fn binop(op: Operation, to: *mut FrElement, a: *const FrElement, b: *const FrElement) {
-    let mut nodes = NODES.lock().unwrap();
-    let mut values = VALUES.lock().unwrap();
-    let mut constant = CONSTANT.lock().unwrap();
-    assert_eq!(nodes.len(), values.len());
-    assert_eq!(nodes.len(), constant.len());
-
     let (a, b, to) = unsafe { ((*a).0, (*b).0, &mut (*to).0) };
-    assert!(a < nodes.len());
-    assert!(b < nodes.len());
-    nodes.push(Node::Op(op, a, b));
-    *to = nodes.len() - 1;
-
-    let (va, vb) = (values[a], values[b]);
-    values.push(op.eval(va, vb));
-
-    let (ca, cb) = (constant[a], constant[b]);
-    constant.push(ca && cb);
+    *to = with_builder(|builder| builder.binop(op, a, b));
 }
 
 #[allow(warnings)]
@@ -156,16 +199,8 @@ pub fn generate_position_array(
 
 #[allow(warnings)]
 pub unsafe fn Fr_toInt(a: *const FrElement) -> u64 {
-    let nodes = NODES.lock().unwrap();
-    let values = VALUES.lock().unwrap();
-    let constant = CONSTANT.lock().unwrap();
-    assert_eq!(nodes.len(), values.len());
-    assert_eq!(nodes.len(), constant.len());
-
     let a = unsafe { (*a).0 };
-    assert!(a < nodes.len());
-    assert!(constant[a]);
-    values[a].try_into().unwrap()
+    with_builder(|b| b.constant_value(a)).try_into().unwrap()
 }
 
 #[allow(warnings)]
@@ -175,16 +210,8 @@ pub unsafe fn print(a: *const FrElement) {
 
 #[allow(warnings)]
 pub fn Fr_isTrue(a: *mut FrElement) -> bool {
-    let nodes = NODES.lock().unwrap();
-    let values = VALUES.lock().unwrap();
-    let constant = CONSTANT.lock().unwrap();
-    assert_eq!(nodes.len(), values.len());
-    assert_eq!(nodes.len(), constant.len());
-
     let a = unsafe { (*a).0 };
-    assert!(a < nodes.len());
-    assert!(constant[a]);
-    values[a] != U256::ZERO
+    with_builder(|b| b.constant_value(a)) != U256::ZERO
 }
 
 #[allow(warnings)]
@@ -274,19 +301,10 @@ pub unsafe fn Fr_idiv(to: *mut FrElement, a: *const FrElement, b: *const FrEleme
 
 #[allow(warnings)]
 pub unsafe fn bbf(component_name: String, lvarcall: &Vec<FrElement>, destination: *mut FrElement) {
-    let mut nodes = NODES.lock().unwrap();
-    let mut values = VALUES.lock().unwrap();
-    let mut constant = CONSTANT.lock().unwrap();
-    assert_eq!(nodes.len(), values.len());
-    assert_eq!(nodes.len(), constant.len());
-
     let params = lvarcall.iter().map(|x| x.0).collect();
-    nodes.push(Node::BBF(component_name, params));
     let destination = unsafe { &mut (*destination).0 };
-    *destination = nodes.len() - 1;
-
-    let mut rng = rand::thread_rng();
-    values.push(rng.gen::<U256>() % M);
-
-    constant.push(false);
+    *destination = with_builder(|b| {
+        let value = rand::thread_rng().gen::<U256>() % b.prime.modulus();
+        b.push(Node::BBF(component_name, params), value, false)
+    });
 }
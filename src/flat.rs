@@ -0,0 +1,524 @@
+//! Flat graph encoding that is evaluated in place.
+//!
+//! Unlike the postcard based [`format`](crate::format), this encoding needs no
+//! deserialization step: a [`FlatGraph`] borrows the encoded bytes, which can
+//! come from `include_bytes!` or a memory-mapped file, and reads the nodes
+//! directly from them. All integers are little endian and every section starts
+//! at an 8 byte aligned offset.
+//!
+//! | section   | record size | content                                  |
+//! |-----------|-------------|------------------------------------------|
+//! | header    | 48          | magic `CWRF`, version and section counts |
+//! | nodes     | 16          | kind, operation and three `u32` operands |
+//! | constants | 32          | constant values                          |
+//! | params    | 4           | black box function params, input shapes  |
+//! | names     | 4           | end offsets into the name bytes          |
+//! | name data | 1           | UTF-8 black box function and input names |
+//! | signals   | 4           | output node of every witness element     |
+//! | inputs    | 24          | input hash map                           |
+//!
+//! A node record is `[kind: u8, op: u8, 0: u16, a: u32, b: u32, c: u32]`:
+//!
+//! | kind | node                | operands                                 |
+//! |------|---------------------|------------------------------------------|
+//! | 0    | `Input`             | `a` input index                          |
+//! | 1    | `Constant`          | `a` constant index                       |
+//! | 2    | `MontConstant`      | `a` constant index, Montgomery form      |
+//! | 3    | `Op`                | `op` operation, `a` and `b` node indices |
+//! | 4    | `BBF`               | `a` name index, `b..b + c` params        |
+//!
+//! The prime follows the section counts as a `u32` [`Prime`] discriminant
+//! since version 2, version 1 graphs are BN254. Since version 3 the last names
+//! are the names of the inputs, one for each input record.
+//!
+//! Version 4 adds the size of the inputs buffer and the index of the first
+//! input shape in the params as `u32`s after the prime. The shapes follow the
+//! black box function params, each as a dimension count followed by the
+//! dimensions. The buffer size of earlier versions is derived from the input
+//! nodes and records.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use ark_bn254::Fr;
+use ruint::aliases::U256;
+
+use crate::{
+    error::{bail, ensure, err, Error},
+    format::derive_inputs_size,
+    get_inputs_buffer,
+    graph::{strip_suffix_number, Node, Operation},
+    input_infos,
+    prime::{GraphField, Prime},
+    write_input, BlackBoxFunction, Graph, HashMap, HashSignalInfo, InputInfo,
+};
+
+pub const MAGIC: [u8; 4] = *b"CWRF";
+
+/// The version written by [`encode`].
+pub const VERSION: u32 = 4;
+
+const HEADER_SIZE: usize = 48;
+const NODE_SIZE: usize = 16;
+const CONSTANT_SIZE: usize = 32;
+const INPUT_SIZE: usize = 24;
+
+const KIND_INPUT: u8 = 0;
+const KIND_CONSTANT: u8 = 1;
+const KIND_MONT_CONSTANT: u8 = 2;
+const KIND_OP: u8 = 3;
+const KIND_BBF: u8 = 4;
+
+/// Serializes the graph into the flat encoding, fails if a count or index
+/// exceeds `u32`
+pub fn encode(graph: &Graph) -> Result<Vec<u8>, Error> {
+    let mut nodes = Vec::with_capacity(graph.nodes.len() * NODE_SIZE);
+    let mut constants = Vec::new();
+    let mut params = Vec::new();
+    let mut names = Vec::new();
+    let mut name_data = Vec::new();
+
+    for node in graph.nodes.iter() {
+        let (kind, op, a, b, c) = match node {
+            Node::Input(i) => (KIND_INPUT, 0, i, 0, 0),
+            Node::Constant(c) => {
+                constants.extend_from_slice(&c.to_le_bytes::<32>());
+                (KIND_CONSTANT, 0, constants.len() / CONSTANT_SIZE - 1, 0, 0)
+            }
+            Node::MontConstant(c) => {
+                constants.extend_from_slice(&c.to_le_bytes::<32>());
+                (
+                    KIND_MONT_CONSTANT,
+                    0,
+                    constants.len() / CONSTANT_SIZE - 1,
+                    0,
+                    0,
+                )
+            }
+            Node::Op(op, a, b) => (KIND_OP, op as u8, a, b, 0),
+            Node::BBF(name, args) => {
+                name_data.extend_from_slice(name.as_bytes());
+                names.extend_from_slice(&to_u32(name_data.len())?.to_le_bytes());
+                let start = params.len() / 4;
+                for &arg in args.iter() {
+                    params.extend_from_slice(&to_u32(arg)?.to_le_bytes());
+                }
+                (KIND_BBF, 0, names.len() / 4 - 1, start, args.len())
+            }
+        };
+        nodes.extend_from_slice(&[kind, op, 0, 0]);
+        nodes.extend_from_slice(&to_u32(a)?.to_le_bytes());
+        nodes.extend_from_slice(&to_u32(b)?.to_le_bytes());
+        nodes.extend_from_slice(&to_u32(c)?.to_le_bytes());
+    }
+
+    let mut signals = Vec::with_capacity(graph.signals.len() * 4);
+    for &signal in graph.signals.iter() {
+        signals.extend_from_slice(&to_u32(signal)?.to_le_bytes());
+    }
+
+    let shapes = params.len() / 4;
+    let mut inputs = Vec::with_capacity(graph.input_mapping.len() * INPUT_SIZE);
+    for info in graph.input_mapping.iter() {
+        params.extend_from_slice(&to_u32(info.shape.len())?.to_le_bytes());
+        for &length in info.shape.iter() {
+            params.extend_from_slice(&to_u32(length)?.to_le_bytes());
+        }
+        inputs.extend_from_slice(&info.hash.to_le_bytes());
+        inputs.extend_from_slice(&info.signalid.to_le_bytes());
+        inputs.extend_from_slice(&info.signalsize.to_le_bytes());
+        name_data.extend_from_slice(info.name.as_bytes());
+        names.extend_from_slice(&to_u32(name_data.len())?.to_le_bytes());
+    }
+
+    let counts = [
+        graph.nodes.len(),
+        constants.len() / CONSTANT_SIZE,
+        params.len() / 4,
+        names.len() / 4,
+        name_data.len(),
+        graph.signals.len(),
+        graph.input_mapping.len(),
+    ];
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    for count in counts {
+        bytes.extend_from_slice(&to_u32(count)?.to_le_bytes());
+    }
+    bytes.extend_from_slice(&(graph.prime as u32).to_le_bytes());
+    bytes.extend_from_slice(&to_u32(graph.inputs_size)?.to_le_bytes());
+    bytes.extend_from_slice(&to_u32(shapes)?.to_le_bytes());
+    bytes.resize(HEADER_SIZE, 0);
+    for section in [nodes, constants, params, names, name_data, signals, inputs] {
+        bytes.extend_from_slice(&section);
+        bytes.resize(align(bytes.len()), 0);
+    }
+    Ok(bytes)
+}
+
+fn to_u32(value: usize) -> Result<u32, Error> {
+    u32::try_from(value)
+        .map_err(|_| err!("graph too large for the flat encoding: {value} exceeds u32"))
+}
+
+fn align(offset: usize) -> usize {
+    offset.next_multiple_of(8)
+}
+
+/// A graph in the flat encoding, borrowed from the underlying bytes
+#[derive(Debug, Clone, Copy)]
+pub struct FlatGraph<'a> {
+    prime: Prime,
+    nodes: &'a [u8],
+    constants: &'a [u8],
+    params: &'a [u8],
+    names: &'a [u8],
+    name_data: &'a [u8],
+    /// Index of the first input name, the number of names before version 3
+    input_names: usize,
+    /// Index of the first input shape in the params, `None` before version 4
+    input_shapes: Option<usize>,
+    signals: &'a [u8],
+    inputs: &'a [u8],
+    inputs_size: usize,
+}
+
+impl<'a> FlatGraph<'a> {
+    /// Validates the encoded graph without copying it
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
+        ensure!(bytes.starts_with(&MAGIC), "not a flat graph");
+        ensure!(
+            bytes.len() >= HEADER_SIZE,
+            "truncated flat graph header: {} of {HEADER_SIZE} bytes",
+            bytes.len()
+        );
+        let version = read_u32(bytes, 1);
+        if version == 0 || version > VERSION {
+            bail!(
+                "flat graph version {version} is not supported, this build reads versions 1 to {VERSION}"
+            );
+        }
+
+        let sizes = [NODE_SIZE, CONSTANT_SIZE, 4, 4, 1, 4, INPUT_SIZE];
+        let mut sections = [&bytes[..0]; 7];
+        let mut offset = HEADER_SIZE;
+        for (i, size) in sizes.into_iter().enumerate() {
+            let section = (read_u32(bytes, 2 + i) as usize)
+                .checked_mul(size)
+                .and_then(|len| bytes.get(offset..offset.checked_add(len)?))
+                .ok_or_else(|| err!("truncated flat graph: section {i} out of bounds"))?;
+            sections[i] = section;
+            offset = align(offset + section.len());
+        }
+        let [nodes, constants, params, names, name_data, signals, inputs] = sections;
+        let prime = match version {
+            1 => Prime::Bn128,
+            _ => u8::try_from(read_u32(bytes, 9))
+                .ok()
+                .and_then(|p| Prime::try_from(p).ok())
+                .ok_or_else(|| err!("unknown prime {}", read_u32(bytes, 9)))?,
+        };
+
+        let input_names = match version {
+            1 | 2 => names.len() / 4,
+            _ => (names.len() / 4)
+                .checked_sub(inputs.len() / INPUT_SIZE)
+                .ok_or_else(|| err!("missing input names"))?,
+        };
+
+        let input_shapes = match version {
+            1..=3 => None,
+            _ => Some(read_u32(bytes, 11) as usize),
+        };
+
+        let mut graph = FlatGraph {
+            prime,
+            nodes,
+            constants,
+            params,
+            names,
+            name_data,
+            input_names,
+            input_shapes,
+            signals,
+            inputs,
+            inputs_size: read_u32(bytes, 10) as usize,
+        };
+        graph.validate()?;
+        if input_shapes.is_none() {
+            let input_nodes = (0..graph.len())
+                .map(|i| graph.record(i))
+                .filter(|&(kind, ..)| kind == KIND_INPUT)
+                .map(|(_, _, a, _, _)| a);
+            graph.inputs_size =
+                derive_inputs_size(input_nodes, &graph.input_mapping().collect::<Vec<_>>());
+        }
+        Ok(graph)
+    }
+
+    /// Checks all references so that evaluation cannot go out of bounds.
+    fn validate(&self) -> Result<(), Error> {
+        let mut end = 0;
+        for i in 0..self.names.len() / 4 {
+            let next = read_u32(self.names, i) as usize;
+            ensure!(
+                end <= next && next <= self.name_data.len(),
+                "invalid name offset {next} for name {i}"
+            );
+            core::str::from_utf8(&self.name_data[end..next])
+                .map_err(|e| err!("node name {i}: {e}"))?;
+            end = next;
+        }
+
+        let params = self.params.len() / 4;
+        let bbf_params = self.input_shapes.unwrap_or(params);
+        ensure!(bbf_params <= params, "input shapes out of bounds");
+        if self.input_shapes.is_some() {
+            let mut offset = bbf_params;
+            for i in 0..self.inputs.len() / INPUT_SIZE {
+                ensure!(offset < params, "input {i}: shape out of bounds");
+                let len = read_u32(self.params, offset) as usize;
+                ensure!(len < params - offset, "input {i}: shape out of bounds");
+                offset += 1 + len;
+            }
+            ensure!(
+                self.inputs_size
+                    >= derive_inputs_size([], &self.input_mapping().collect::<Vec<_>>()),
+                "inputs buffer of {} elements is too small for the inputs",
+                self.inputs_size
+            );
+        }
+
+        for i in 0..self.len() {
+            let (kind, op, a, b, c) = self.record(i);
+            match kind {
+                KIND_INPUT => (),
+                KIND_CONSTANT => ensure!(
+                    a < self.constants.len() / CONSTANT_SIZE,
+                    "node {i}: constant {a} out of bounds"
+                ),
+                KIND_MONT_CONSTANT => {
+                    ensure!(
+                        a < self.constants.len() / CONSTANT_SIZE,
+                        "node {i}: constant {a} out of bounds"
+                    );
+                    ensure!(
+                        self.constant(a) < self.prime.modulus(),
+                        "node {i}: constant {a} not reduced"
+                    );
+                }
+                KIND_OP => {
+                    Operation::try_from(op)
+                        .map_err(|op| err!("node {i}: unknown operation {op}"))?;
+                    ensure!(a < i && b < i, "node {i}: operands must be backwards");
+                }
+                KIND_BBF => {
+                    ensure!(
+                        a < self.input_names,
+                        "node {i}: black box function name {a} out of bounds"
+                    );
+                    ensure!(
+                        b.checked_add(c).is_some_and(|end| end <= bbf_params),
+                        "node {i}: black box function params out of bounds"
+                    );
+                    for j in b..b + c {
+                        ensure!(
+                            (read_u32(self.params, j) as usize) < i,
+                            "node {i}: params must be backwards"
+                        );
+                    }
+                }
+                kind => bail!("node {i}: unknown node kind {kind}"),
+            }
+        }
+
+        for i in 0..self.signals.len() / 4 {
+            ensure!(
+                (read_u32(self.signals, i) as usize) < self.len(),
+                "signal {i} out of bounds"
+            );
+        }
+        Ok(())
+    }
+
+    /// The prime of the field the graph is evaluated in
+    pub fn prime(&self) -> Prime {
+        self.prime
+    }
+
+    /// Number of nodes
+    pub fn len(&self) -> usize {
+        self.nodes.len() / NODE_SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Decodes a single node
+    pub fn node(&self, i: usize) -> Node {
+        let (kind, op, a, b, c) = self.record(i);
+        match kind {
+            KIND_INPUT => Node::Input(a),
+            KIND_CONSTANT => Node::Constant(self.constant(a)),
+            KIND_MONT_CONSTANT => Node::MontConstant(self.constant(a)),
+            KIND_OP => Node::Op(Operation::try_from(op).unwrap(), a, b),
+            _ => Node::BBF(
+                self.name(a).to_string(),
+                (b..b + c)
+                    .map(|j| read_u32(self.params, j) as usize)
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Output node of every witness element
+    pub fn signals(&self) -> impl Iterator<Item = usize> + 'a {
+        let signals = self.signals;
+        (0..signals.len() / 4).map(move |i| read_u32(signals, i) as usize)
+    }
+
+    pub fn input_mapping(&self) -> impl Iterator<Item = HashSignalInfo> + 'a {
+        let graph = *self;
+        let mut shape_offset = graph.input_shapes;
+        (0..graph.inputs.len() / INPUT_SIZE).map(move |i| {
+            let shape = match &mut shape_offset {
+                Some(offset) => {
+                    let start = *offset + 1;
+                    *offset = start + read_u32(graph.params, start - 1) as usize;
+                    (start..*offset)
+                        .map(|j| read_u32(graph.params, j) as usize)
+                        .collect()
+                }
+                None => Vec::new(),
+            };
+            HashSignalInfo {
+                hash: read_u64(graph.inputs, 3 * i),
+                signalid: read_u64(graph.inputs, 3 * i + 1),
+                signalsize: read_u64(graph.inputs, 3 * i + 2),
+                name: if graph.input_names < graph.names.len() / 4 {
+                    graph.name(graph.input_names + i).to_string()
+                } else {
+                    String::new()
+                },
+                shape,
+            }
+        })
+    }
+
+    /// The inputs of the graph in buffer order, see [`Graph::inputs`]
+    pub fn inputs(&self) -> Vec<InputInfo> {
+        input_infos(&self.input_mapping().collect::<Vec<_>>())
+    }
+
+    /// Number of elements of the inputs buffer, see [`crate::get_inputs_size`]
+    pub fn inputs_size(&self) -> usize {
+        self.inputs_size
+    }
+
+    /// Calculates the witness from the given inputs
+    pub fn calculate_witness(
+        &self,
+        input_list: HashMap<String, Vec<U256>>,
+        bbfs: Option<&HashMap<String, BlackBoxFunction>>,
+    ) -> Result<Vec<U256>, Error> {
+        let mut inputs_buffer = get_inputs_buffer(self.inputs_size());
+        for (key, value) in input_list.iter() {
+            let info = HashSignalInfo::find(self.input_mapping(), key)?;
+            let (offset, length) = (info.signalid as usize, info.signalsize as usize);
+            write_input(key, offset, length, value, &mut inputs_buffer)?;
+        }
+        self.evaluate(&inputs_buffer, bbfs)
+    }
+
+    /// Evaluates the graph and returns the values of all signals
+    pub fn evaluate(
+        &self,
+        inputs: &[U256],
+        bbfs: Option<&HashMap<String, BlackBoxFunction>>,
+    ) -> Result<Vec<U256>, Error> {
+        self.evaluate_in::<Fr>(inputs, bbfs)
+    }
+
+    /// Evaluates a graph built for the prime of `F`
+    pub fn evaluate_in<F: GraphField>(
+        &self,
+        inputs: &[U256],
+        bbfs: Option<&HashMap<String, BlackBoxFunction<F>>>,
+    ) -> Result<Vec<U256>, Error> {
+        ensure!(
+            Prime::of::<F>() == Some(self.prime),
+            "the graph is built for {}, it cannot be evaluated in a different field",
+            self.prime
+        );
+        let mut values = Vec::with_capacity(self.len());
+        for i in 0..self.len() {
+            let (kind, op, a, b, c) = self.record(i);
+            let value = match kind {
+                KIND_INPUT => F::from_u256(inputs.get(a).copied().unwrap_or(U256::MAX)),
+                KIND_CONSTANT => F::from_u256(self.constant(a)),
+                KIND_MONT_CONSTANT => F::from_montgomery(self.constant(a)),
+                KIND_OP => {
+                    // Validated in `from_bytes`.
+                    let op = Operation::ALL[op as usize];
+                    op.eval_fr(values[a], values[b])
+                }
+                _ => {
+                    let Some(bbfs) = bbfs else {
+                        bail!("no black box functions provided");
+                    };
+                    let params = (b..b + c)
+                        .map(|j| values[read_u32(self.params, j) as usize])
+                        .collect::<Vec<_>>();
+                    let name = strip_suffix_number(self.name(a).to_string());
+                    if let Some(bbf) = bbfs.get(&name) {
+                        bbf(&params)
+                    } else {
+                        bail!("black box function {:?} not found", name);
+                    }
+                }
+            };
+            values.push(value);
+        }
+
+        // Convert from Montgomery form and return the outputs.
+        Ok(self.signals().map(|i| values[i].into()).collect())
+    }
+
+    fn record(&self, i: usize) -> (u8, u8, usize, usize, usize) {
+        let record = &self.nodes[i * NODE_SIZE..(i + 1) * NODE_SIZE];
+        (
+            record[0],
+            record[1],
+            read_u32(record, 1) as usize,
+            read_u32(record, 2) as usize,
+            read_u32(record, 3) as usize,
+        )
+    }
+
+    fn constant(&self, i: usize) -> U256 {
+        U256::from_le_slice(&self.constants[i * CONSTANT_SIZE..(i + 1) * CONSTANT_SIZE])
+    }
+
+    fn name(&self, i: usize) -> &'a str {
+        let start = match i {
+            0 => 0,
+            i => read_u32(self.names, i - 1) as usize,
+        };
+        let end = read_u32(self.names, i) as usize;
+        // Validated in `from_bytes`.
+        core::str::from_utf8(&self.name_data[start..end]).unwrap()
+    }
+}
+
+fn read_u32(bytes: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes(bytes[4 * i..4 * i + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], i: usize) -> u64 {
+    u64::from_le_bytes(bytes[8 * i..8 * i + 8].try_into().unwrap())
+}
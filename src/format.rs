@@ -0,0 +1,188 @@
+//! Versioned container format for serialized graphs.
+//!
+//! A graph file starts with a fixed header followed by the postcard encoded
+//! payload:
+//!
+//! | offset | size | content                              |
+//! |--------|------|--------------------------------------|
+//! | 0      | 4    | magic bytes `CWRG`                   |
+//! | 4      | 4    | format version (little endian)       |
+//! | 8      | 8    | payload length (little endian)       |
+//! | 16     | 32   | SHA-256 of the payload               |
+//! | 48     | ..   | payload                              |
+//!
+//! The payload holds the prime, the [`Metadata`], the nodes with Montgomery
+//! constants in their raw form, the signals, the input hash map with the
+//! names and shapes of the inputs and the size of the inputs buffer.
+//!
+//! Files without the magic bytes are decoded as the unversioned format that
+//! predates the header. These are BN254 graphs with canonical Montgomery
+//! constants and without names and shapes, the size of their inputs buffer is
+//! derived from the input nodes and the input hash map.
+
+use alloc::{string::String, vec::Vec};
+
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::{bail, ensure, err, Error},
+    graph::Node,
+    nodes::{Nodes, Opcode},
+    prime::{Bn128Fr, GraphField, Prime},
+    Graph, HashSignalInfo, Metadata,
+};
+
+pub const MAGIC: [u8; 4] = *b"CWRG";
+
+/// The version written by [`encode`].
+pub const VERSION: u32 = 1;
+
+const HEADER_SIZE: usize = 48;
+
+type Payload = (
+    Prime,
+    Metadata,
+    Nodes,
+    Vec<usize>,
+    Vec<HashSignalInfo>,
+    usize,
+);
+
+type LegacyPayload = (Nodes, Vec<usize>, Vec<LegacyInput>);
+
+/// Input hash map entry of the unversioned format, `(hash, signalid, signalsize)`
+type LegacyInput = (u64, u64, u64);
+
+/// Size of the inputs buffer of graphs that don't record it, large enough
+/// for every input node and input signal
+pub(crate) fn derive_inputs_size<'a>(
+    input_nodes: impl IntoIterator<Item = usize>,
+    input_mapping: impl IntoIterator<Item = &'a HashSignalInfo>,
+) -> usize {
+    let nodes = input_nodes.into_iter().map(|i| i + 1);
+    let signals = input_mapping
+        .into_iter()
+        .map(|info| info.signalid.saturating_add(info.signalsize) as usize);
+    nodes.chain(signals).max().unwrap_or(0).max(1)
+}
+
+/// Indices of the input nodes
+fn input_nodes(nodes: &Nodes) -> impl Iterator<Item = usize> + '_ {
+    (0..nodes.len())
+        .filter(|&i| matches!(nodes.opcode(i), Opcode::Input))
+        .map(|i| nodes.operands(i).0)
+}
+
+/// Serializes the graph into the versioned container format
+pub fn encode(graph: &Graph) -> Vec<u8> {
+    let payload = postcard::to_allocvec(&(
+        graph.prime,
+        &graph.metadata,
+        &graph.nodes,
+        &graph.signals,
+        &graph.input_mapping,
+        graph.inputs_size,
+    ))
+    .unwrap();
+
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + payload.len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&Sha256::digest(&payload));
+    bytes.extend_from_slice(&payload);
+    bytes
+}
+
+/// Deserializes a graph from either the versioned or the unversioned format
+pub fn decode(bytes: &[u8]) -> Result<Graph, Error> {
+    if !bytes.starts_with(&MAGIC) {
+        return decode_legacy(bytes).map_err(|e| e.context("failed to decode unversioned graph"));
+    }
+    ensure!(
+        bytes.len() >= HEADER_SIZE,
+        "truncated graph header: {} of {HEADER_SIZE} bytes",
+        bytes.len()
+    );
+
+    let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    let length = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+    let hash = &bytes[16..HEADER_SIZE];
+    let payload = &bytes[HEADER_SIZE..];
+
+    if version != VERSION {
+        bail!(
+            "graph format version {version} is not supported, this build reads version {VERSION}"
+        );
+    }
+    ensure!(
+        length == payload.len() as u64,
+        "graph payload length mismatch: header says {length} bytes, found {}",
+        payload.len()
+    );
+    ensure!(
+        Sha256::digest(payload).as_slice() == hash,
+        "graph checksum mismatch, the file is corrupted"
+    );
+
+    decode_payload(payload)
+        .map_err(|e| e.context(format_args!("failed to decode graph version {version}")))
+}
+
+fn decode_payload(bytes: &[u8]) -> Result<Graph, Error> {
+    let ((prime, metadata, nodes, signals, input_mapping, inputs_size), rest): (Payload, _) =
+        postcard::take_from_bytes(bytes).map_err(|e| err!("{e}"))?;
+    ensure!(rest.is_empty(), "{} trailing bytes after graph", rest.len());
+    ensure!(
+        inputs_size >= derive_inputs_size([], &input_mapping),
+        "inputs buffer of {inputs_size} elements is too small for the inputs"
+    );
+
+    Ok(Graph {
+        prime,
+        metadata,
+        nodes,
+        signals,
+        input_mapping,
+        inputs_size,
+    })
+}
+
+/// Decodes a BN254 payload with canonical Montgomery constants
+fn decode_legacy(bytes: &[u8]) -> Result<Graph, Error> {
+    let ((mut nodes, signals, input_mapping), rest): (LegacyPayload, _) =
+        postcard::take_from_bytes(bytes).map_err(|e| err!("{e}"))?;
+    ensure!(rest.is_empty(), "{} trailing bytes after graph", rest.len());
+
+    for i in 0..nodes.len() {
+        if let Opcode::MontConstant = nodes.opcode(i) {
+            let c = nodes.constant(i);
+            ensure!(
+                c < Prime::Bn128.modulus(),
+                "constant of node {i} is not reduced"
+            );
+            nodes.set(i, Node::MontConstant(Bn128Fr::from_u256(c).montgomery()));
+        }
+    }
+    nodes.shrink_to_fit();
+
+    let input_mapping: Vec<_> = input_mapping
+        .into_iter()
+        .map(|(hash, signalid, signalsize)| HashSignalInfo {
+            hash,
+            signalid,
+            signalsize,
+            name: String::new(),
+            shape: Vec::new(),
+        })
+        .collect();
+    let inputs_size = derive_inputs_size(input_nodes(&nodes), &input_mapping);
+    Ok(Graph {
+        prime: Prime::Bn128,
+        metadata: Metadata::default(),
+        nodes,
+        signals,
+        input_mapping,
+        inputs_size,
+    })
+}
@@ -1,12 +1,18 @@
 #![allow(non_snake_case)]
 
 use crate::field::{self, *};
-use crate::HashSignalInfo;
-use crate::{graph, M};
-use byteorder::{LittleEndian, ReadBytesExt};
+use crate::{
+    codegen,
+    dat::{DatFile, IOFieldDef, SectionCounts},
+    fnv1a, format, graph,
+    prime::with_field,
+    Graph, HashSignalInfo, Metadata,
+};
 use ffi::InputOutputList;
-use ruint::{aliases::U256, uint};
-use std::{io::Read, time::Instant};
+use ruint::uint;
+use std::{fs, path::Path, time::Instant};
+
+pub use crate::field::GraphBuilder;
 
 #[cxx::bridge]
 mod ffi {
@@ -43,6 +49,7 @@ mod ffi {
         componentMemory: Vec<Circom_Component>,
         circuitConstants: Vec<FrElement>,
         templateInsId2IOSignalInfoList: Vec<InputOutputList>,
+        busInsId2FieldInfo: Vec<InputOutputList>,
         listOfTemplateMessages: Vec<String>,
     }
 
@@ -101,180 +108,275 @@ mod ffi {
     unsafe extern "C++" {
         include!("witness/include/witness.h");
 
-        unsafe fn run(ctx: *mut Circom_CalcWit);
-        fn get_size_of_io_map() -> u32;
-        fn get_total_signal_no() -> u32;
-        fn get_main_input_signal_no() -> u32;
-        fn get_main_input_signal_start() -> u32;
-        fn get_number_of_components() -> u32;
-        fn get_size_of_constants() -> u32;
-        fn get_size_of_input_hashmap() -> u32;
-        fn get_size_of_witness() -> u32;
+        unsafe fn run(circuit: usize, ctx: *mut Circom_CalcWit);
+        fn get_size_of_io_map(circuit: usize) -> u32;
+        fn get_total_signal_no(circuit: usize) -> u32;
+        fn get_main_input_signal_no(circuit: usize) -> u32;
+        fn get_main_input_signal_start(circuit: usize) -> u32;
+        fn get_number_of_components(circuit: usize) -> u32;
+        fn get_size_of_constants(circuit: usize) -> u32;
+        fn get_size_of_input_hashmap(circuit: usize) -> u32;
+        fn get_size_of_witness(circuit: usize) -> u32;
+        fn get_size_of_bus_field_map(circuit: usize) -> u32;
     }
 }
 
-const DAT_BYTES: &[u8] = include_bytes!("constants.dat");
-
-pub fn get_input_hash_map() -> Vec<HashSignalInfo> {
-    let mut bytes = &DAT_BYTES[..(ffi::get_size_of_input_hashmap() as usize) * 24];
-    let mut input_hash_map =
-        vec![HashSignalInfo::default(); ffi::get_size_of_input_hashmap() as usize];
-    for i in 0..ffi::get_size_of_input_hashmap() as usize {
-        let hash = bytes.read_u64::<LittleEndian>().unwrap();
-        let signalid = bytes.read_u64::<LittleEndian>().unwrap();
-        let signalsize = bytes.read_u64::<LittleEndian>().unwrap();
-        input_hash_map[i] = HashSignalInfo {
-            hash,
-            signalid,
-            signalsize,
-        };
-    }
-    input_hash_map
+/// A circuit of `WITNESS_CPP` as generated by the build script
+struct CircuitData {
+    name: &'static str,
+    /// Contents of `constants.dat`
+    dat: &'static [u8],
+    circom_version: Option<&'static str>,
+    /// First signal, name and shape of every signal array of the main
+    /// component
+    main_signals: &'static [(usize, &'static str, &'static [usize])],
 }
 
-pub fn get_witness_to_signal() -> Vec<usize> {
-    let mut bytes = &DAT_BYTES[(ffi::get_size_of_input_hashmap() as usize) * 24
-        ..(ffi::get_size_of_input_hashmap() as usize) * 24
-            + (ffi::get_size_of_witness() as usize) * 8];
-    let mut signal_list = Vec::with_capacity(ffi::get_size_of_witness() as usize);
-    for _ in 0..ffi::get_size_of_witness() as usize {
-        signal_list.push(bytes.read_u64::<LittleEndian>().unwrap() as usize);
-    }
-    signal_list
+const CIRCUITS: &[CircuitData] = &include!(concat!(env!("OUT_DIR"), "/circuits.rs"));
+
+/// A circuit compiled into the crate, identified by its file stem
+#[derive(Debug, Clone, Copy)]
+pub struct Circuit {
+    index: usize,
 }
 
-pub fn get_constants() -> Vec<FrElement> {
-    if ffi::get_size_of_constants() == 0 {
-        return vec![];
+impl Circuit {
+    /// All circuits, in the order of `WITNESS_CPP`
+    pub fn all() -> impl Iterator<Item = Circuit> {
+        (0..CIRCUITS.len()).map(|index| Circuit { index })
+    }
+
+    /// The circuit with the identifier `name`
+    pub fn get(name: &str) -> eyre::Result<Circuit> {
+        Circuit::all()
+            .find(|c| c.name() == name)
+            .ok_or_else(|| eyre::eyre!("unknown circuit {name:?}"))
+    }
+
+    pub fn name(self) -> &'static str {
+        CIRCUITS[self.index].name
     }
 
-    // skip the first part
-    let mut bytes = &DAT_BYTES[(ffi::get_size_of_input_hashmap() as usize) * 24
-        + (ffi::get_size_of_witness() as usize) * 8..];
-    let mut constants = vec![field::constant(U256::from(0)); ffi::get_size_of_constants() as usize];
-    for i in 0..ffi::get_size_of_constants() as usize {
-        let sv = bytes.read_i32::<LittleEndian>().unwrap() as i32;
-        let typ = bytes.read_u32::<LittleEndian>().unwrap() as u32;
-
-        let mut buf = [0; 32];
-        bytes.read_exact(&mut buf).unwrap();
-
-        if typ & 0x80000000 == 0 {
-            let c = if sv < 0 {
-                M - U256::from(sv.abs() as u32)
-            } else {
-                U256::from(sv as u32)
-            };
-            constants[i] = field::constant(c);
-        } else {
-            constants[i] =
-                field::constant(U256::from_le_bytes(buf).mul_redc(uint!(1_U256), M, INV));
+    /// Version of circom that generated the circuit, if known
+    pub fn circom_version(self) -> Option<&'static str> {
+        CIRCUITS[self.index].circom_version
+    }
+
+    /// Parses the `constants.dat` of the circuit
+    pub fn dat_file(self) -> eyre::Result<DatFile> {
+        let counts = SectionCounts {
+            input_hash_map: ffi::get_size_of_input_hashmap(self.index) as usize,
+            witness: ffi::get_size_of_witness(self.index) as usize,
+            constants: ffi::get_size_of_constants(self.index) as usize,
+            io_map: ffi::get_size_of_io_map(self.index) as usize,
+            bus_field_map: ffi::get_size_of_bus_field_map(self.index) as usize,
+            signals: ffi::get_total_signal_no(self.index) as usize,
+        };
+        Ok(DatFile::parse(CIRCUITS[self.index].dat, counts, *PRIME)?)
+    }
+
+    /// The input hash map of the dat file with the names and shapes of the
+    /// inputs from the symbol file. Fails if names collide or the files disagree.
+    pub fn input_mapping(self, dat: &DatFile) -> eyre::Result<Vec<HashSignalInfo>> {
+        let start = ffi::get_main_input_signal_start(self.index) as usize;
+        let end = start + ffi::get_main_input_signal_no(self.index) as usize;
+
+        let inputs: Vec<_> = CIRCUITS[self.index]
+            .main_signals
+            .iter()
+            .filter(|(first, _, _)| (start..end).contains(first))
+            .collect();
+        for (i, &&(_, name, _)) in inputs.iter().enumerate() {
+            if let Some((_, other, _)) =
+                inputs[..i].iter().find(|(_, n, _)| fnv1a(n) == fnv1a(name))
+            {
+                eyre::bail!("inputs {other:?} and {name:?} have the same hash");
+            }
+        }
+
+        // Empty slots of the hash map have no signals.
+        let mut mapping: Vec<HashSignalInfo> = dat
+            .input_hash_map
+            .iter()
+            .filter(|info| info.signalsize != 0)
+            .cloned()
+            .collect();
+        for info in &mut mapping {
+            let &&(first, name, shape) = inputs
+                .iter()
+                .find(|(_, name, _)| fnv1a(name) == info.hash)
+                .ok_or_else(|| eyre::eyre!("no input has the hash {:#x}", info.hash))?;
+            eyre::ensure!(
+                first as u64 == info.signalid,
+                "input {name:?} starts at signal {first}, the input hash map says {}",
+                info.signalid
+            );
+            eyre::ensure!(
+                shape.iter().product::<usize>() as u64 == info.signalsize,
+                "input {name:?} has the shape {shape:?}, the input hash map says {} signals",
+                info.signalsize
+            );
+            info.name = name.to_string();
+            info.shape = shape.to_vec();
+        }
+        if let Some((_, name, _)) = inputs
+            .iter()
+            .find(|(_, name, _)| !mapping.iter().any(|info| info.name == *name))
+        {
+            eyre::bail!("input {name:?} is missing from the input hash map");
         }
+        Ok(mapping)
     }
+}
+
+/// Converts a field map of the dat file to the bridged type of the generated
+/// code
+fn field_lists(map: &[Vec<IOFieldDef>]) -> Vec<InputOutputList> {
+    map.iter()
+        .map(|defs| InputOutputList {
+            defs: defs
+                .iter()
+                .map(|def| ffi::IOFieldDef {
+                    code: 0,
+                    offset: def.offset,
+                    lengths: def.lengths.clone(),
+                    size: def.size,
+                    busId: def.bus_id,
+                })
+                .collect(),
+        })
+        .collect()
+}
 
-    return constants;
+/// A graph built from the linked circuit by [`build_graph`]
+pub struct BuiltGraph {
+    pub graph: Graph,
 }
 
-pub fn get_iosignals() -> Vec<InputOutputList> {
-    if ffi::get_size_of_io_map() == 0 {
-        return vec![];
+impl BuiltGraph {
+    /// Writes the graph in the versioned [`format`]
+    pub fn write(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        let bytes = format::encode(&self.graph);
+        eprintln!("Graph size: {} bytes", bytes.len());
+        fs::write(path, bytes)?;
+        Ok(())
     }
 
-    // skip the first part
-    let mut bytes = &DAT_BYTES[(ffi::get_size_of_input_hashmap() as usize) * 24
-        + (ffi::get_size_of_witness() as usize) * 8
-        + (ffi::get_size_of_constants() as usize * 40)..];
-    let io_size = ffi::get_size_of_io_map() as usize;
-    let hashmap_size = ffi::get_size_of_input_hashmap() as usize;
-    let mut indices = vec![0usize; io_size];
-
-    (0..io_size).for_each(|i| {
-        let t32 = bytes.read_u32::<LittleEndian>().unwrap() as usize;
-        indices[i] = t32;
-    });
+    /// Writes the graph as Rust source, see [`codegen::rust`]
+    pub fn write_rust(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        fs::write(path, codegen::rust(&self.graph)?)?;
+        Ok(())
+    }
 
-    let max_index = indices.iter().max().unwrap();
-    let mut map: Vec<InputOutputList> = vec![InputOutputList::default(); *max_index + 1];
+    /// Writes the graph as a C library, `out/witness` writes `out/witness.h`
+    /// and `out/witness.c`. See [`codegen::c`].
+    pub fn write_c(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("witness");
+        let library = codegen::c(&self.graph, name)?;
+        fs::write(path.with_file_name(format!("{name}.h")), library.header)?;
+        fs::write(path.with_file_name(format!("{name}.c")), library.source)?;
+        Ok(())
+    }
+}
 
-    (0..io_size).for_each(|i| {
-        let l32 = bytes.read_u32::<LittleEndian>().unwrap() as usize;
-        let mut io_list: InputOutputList = InputOutputList { defs: vec![] };
+/// Runs the cpp witness generator of `circuit` and returns the graph
+/// optimized with `options`
+pub fn build_graph(circuit: &str, options: &graph::OptimizeOptions) -> eyre::Result<BuiltGraph> {
+    let circuit = Circuit::get(circuit)?;
+    let dat = circuit.dat_file()?;
+    let input_mapping = circuit.input_mapping(&dat)?;
+    let inputs_size = ffi::get_main_input_signal_start(circuit.index) as usize
+        + ffi::get_main_input_signal_no(circuit.index) as usize;
+    let prime = *PRIME;
+    let mut builder = GraphBuilder::new(prime);
+    let mut signals = builder.record(|| {
+        let mut signal_values = vec![];
+        for i in 0..ffi::get_total_signal_no(circuit.index) as usize {
+            signal_values.push(field::undefined(i));
+        }
+        signal_values[0] = field::constant(uint!(1_U256));
 
-        (0..l32).for_each(|_j| {
-            let offset = bytes.read_u32::<LittleEndian>().unwrap() as usize;
-            let len = bytes.read_u32::<LittleEndian>().unwrap() as usize;
+        let main_input_start = ffi::get_main_input_signal_start(circuit.index) as usize;
+        let main_input_len = ffi::get_main_input_signal_no(circuit.index) as usize;
 
-            let mut lengths = vec![0usize; len];
+        for i in main_input_start..main_input_start + main_input_len {
+            signal_values[i] = field::input(i, uint!(0_U256));
+        }
 
-            (0..len).for_each(|k| {
-                lengths[k] = bytes.read_u32::<LittleEndian>().unwrap() as usize;
-            });
+        let mut ctx = ffi::Circom_CalcWit {
+            signalValues: signal_values,
+            componentMemory: vec![
+                ffi::Circom_Component::default();
+                ffi::get_number_of_components(circuit.index) as usize
+            ],
+            circuitConstants: dat.constants.iter().map(|&c| field::constant(c)).collect(),
+            templateInsId2IOSignalInfoList: field_lists(&dat.io_signals),
+            busInsId2FieldInfo: field_lists(&dat.bus_fields),
+            listOfTemplateMessages: vec![],
+        };
 
-            let size = bytes.read_u32::<LittleEndian>().unwrap();
-            let busId = bytes.read_u32::<LittleEndian>().unwrap();
+        // measure time
+        let now = Instant::now();
+        unsafe {
+            ffi::run(circuit.index, &mut ctx as *mut _);
+        }
+        eprintln!("Calculation took: {:?}", now.elapsed());
 
-            io_list.defs.push(ffi::IOFieldDef {
-                code: 0,
-                offset,
-                lengths,
-                size,
-                busId,
-            });
-        });
-        map[indices[i]] = io_list;
+        dat.witness_to_signal
+            .iter()
+            .map(|&i| ctx.signalValues[i].0)
+            .collect::<Vec<_>>()
     });
-    map
+    let mut nodes = builder.into_nodes();
+    eprintln!("Graph with {} nodes", nodes.len());
+
+    with_field!(prime, F => graph::optimize_with::<F>(&mut nodes, &mut signals, *options));
+
+    Ok(BuiltGraph {
+        graph: Graph {
+            prime,
+            metadata: Metadata {
+                circom_version: circuit.circom_version().map(String::from),
+            },
+            nodes,
+            signals,
+            input_mapping,
+            inputs_size,
+        },
+    })
 }
 
-/// Run cpp witness generator and optimize graph
-pub fn build_witness() -> eyre::Result<()> {
-    let mut signal_values = vec![];
-    for i in 0..ffi::get_total_signal_no() as usize {
-        signal_values.push(field::undefined(i));
+/// Run cpp witness generator of `circuit`, optimize graph and write it to
+/// `<circuit>.bin`
+pub fn build_witness(circuit: &str) -> eyre::Result<()> {
+    // `WITNESS_OPTIMIZE_ROUNDS` sets the number of random evaluations the
+    // probabilistic passes require to agree.
+    let mut options = graph::OptimizeOptions::default();
+    if let Ok(rounds) = std::env::var("WITNESS_OPTIMIZE_ROUNDS") {
+        options.rounds = rounds
+            .parse()
+            .ok()
+            .filter(|&rounds| rounds > 0)
+            .ok_or_else(|| eyre::eyre!("invalid WITNESS_OPTIMIZE_ROUNDS {rounds:?}"))?;
     }
-    signal_values[0] = field::constant(uint!(1_U256));
+    let built = build_graph(circuit, &options)?;
+    built.write(format!("{circuit}.bin"))?;
 
-    let main_input_start = ffi::get_main_input_signal_start() as usize;
-    let main_input_len = ffi::get_main_input_signal_no() as usize;
-
-    for i in main_input_start..main_input_start + main_input_len {
-        signal_values[i] = field::input(i, uint!(0_U256));
+    // Optionally emit the graph as Rust source. `{circuit}` in the paths below
+    // is replaced by the circuit name.
+    if let Ok(path) = std::env::var("WITNESS_RS") {
+        built.write_rust(path.replace("{circuit}", circuit))?;
     }
 
-    let mut ctx = ffi::Circom_CalcWit {
-        signalValues: signal_values,
-        componentMemory: vec![
-            ffi::Circom_Component::default();
-            ffi::get_number_of_components() as usize
-        ],
-        circuitConstants: get_constants(),
-        templateInsId2IOSignalInfoList: get_iosignals(),
-        listOfTemplateMessages: vec![],
-    };
-
-    // measure time
-    let now = Instant::now();
-    unsafe {
-        ffi::run(&mut ctx as *mut _);
+    // Optionally emit the graph as a C library, `WITNESS_C=out/witness`
+    // writes `out/witness.h` and `out/witness.c`.
+    if let Ok(path) = std::env::var("WITNESS_C") {
+        built.write_c(path.replace("{circuit}", circuit))?;
     }
-    eprintln!("Calculation took: {:?}", now.elapsed());
-
-    let signal_values = get_witness_to_signal();
-    let mut signals = signal_values
-        .into_iter()
-        .map(|i| ctx.signalValues[i].0)
-        .collect::<Vec<_>>();
-    let mut nodes = field::get_graph();
-    eprintln!("Graph with {} nodes", nodes.len());
-
-    // Optimize graph
-    graph::optimize(&mut nodes, &mut signals);
-
-    // Store graph to file.
-    let input_map = get_input_hash_map();
-    let bytes = postcard::to_stdvec(&(&nodes, &signals, &input_map)).unwrap();
-    eprintln!("Graph size: {} bytes", bytes.len());
-    std::fs::write("graph.bin", bytes).unwrap();
 
     Ok(())
 }
@@ -1,36 +1,49 @@
-use std::{cmp::Ordering, collections::HashMap, ops::Shr};
-
-use crate::{BlackBoxFunction, M};
-use ark_bn254::Fr;
-use eyre::bail;
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{cmp::Ordering, ops::Shr};
+
+use crate::{
+    error::{bail, Error},
+    nodes::{Nodes, Opcode},
+    prime::GraphField,
+    BlackBoxFunction, HashMap, M,
+};
+use ark_ff::PrimeField;
 use num_bigint::BigUint;
-use rand::Rng;
 use ruint::aliases::U256;
 use ruint::uint;
 use serde::{Deserialize, Serialize};
 
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+#[cfg(feature = "std")]
+mod optimize;
+
+#[cfg(feature = "std")]
+pub use optimize::{
+    common_subexpressions, constants, graph_seed, montgomery_form, optimize, optimize_with,
+    propagate, simplify, tree_shake, value_numbering, OptimizeOptions, SimplifyStats,
+};
 
-fn ark_se<S, A: CanonicalSerialize>(a: &A, s: S) -> Result<S::Ok, S::Error>
+fn raw_se<S>(a: &U256, s: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
 {
-    let mut bytes = vec![];
-    a.serialize_with_mode(&mut bytes, Compress::Yes)
-        .map_err(serde::ser::Error::custom)?;
-    s.serialize_bytes(&bytes)
+    s.serialize_bytes(&a.to_le_bytes::<32>())
 }
 
-fn ark_de<'de, D, A: CanonicalDeserialize>(data: D) -> Result<A, D::Error>
+fn raw_de<'de, D>(data: D) -> Result<U256, D::Error>
 where
     D: serde::de::Deserializer<'de>,
 {
     let s: Vec<u8> = serde::de::Deserialize::deserialize(data)?;
-    let a = A::deserialize_with_mode(s.as_slice(), Compress::Yes, Validate::Yes);
-    a.map_err(serde::de::Error::custom)
+    U256::try_from_le_slice(&s)
+        .ok_or_else(|| serde::de::Error::invalid_length(s.len(), &"32 bytes"))
 }
 
 #[derive(Hash, PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+#[repr(u8)]
 pub enum Operation {
     Mul,
     MMul,
@@ -59,57 +72,73 @@ pub enum Operation {
 pub enum Node {
     Input(usize),
     Constant(U256),
-    #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
-    MontConstant(Fr),
+    /// Constant in the Montgomery form of the graph's field
+    #[serde(serialize_with = "raw_se", deserialize_with = "raw_de")]
+    MontConstant(U256),
     Op(Operation, usize, usize),
     BBF(String, Vec<usize>),
 }
 
-fn cmp_balanced(a: U256, b: U256) -> Ordering {
-    match (a > M.shr(1), b > M.shr(1)) {
+fn cmp_balanced(a: U256, b: U256, m: U256) -> Ordering {
+    match (a > m.shr(1), b > m.shr(1)) {
         (false, true) => Ordering::Greater,
         (true, false) => Ordering::Less,
         (false, false) => a.cmp(&b),
         (true, true) => {
             // both negative: compare reversed
-            let ma = M - a;
-            let mb = M - b;
+            let ma = m - a;
+            let mb = m - b;
             mb.cmp(&ma)
         }
     }
 }
 
 impl Operation {
+    /// All operations, indexed by their `u8` discriminant
+    pub const ALL: [Operation; 21] = {
+        use Operation::*;
+        [
+            Mul, MMul, Add, Sub, Eq, Neq, Lt, Gt, Leq, Geq, Lor, Shl, Shr, Band, Neg, Inv, Div,
+            Mod, Pow, Land, IDiv,
+        ]
+    };
+
+    /// Evaluates the operation in BN254
     pub fn eval(&self, a: U256, b: U256) -> U256 {
-        let a = a % M;
-        let b = b % M;
+        self.eval_mod(a, b, M)
+    }
+
+    /// Evaluates the operation modulo the prime `m`
+    pub fn eval_mod(&self, a: U256, b: U256, m: U256) -> U256 {
+        let a = a % m;
+        let b = b % m;
         use Operation::*;
         match self {
-            Add => a.add_mod(b, M),
-            Sub => a.add_mod(M - b, M),
-            Mul => a.mul_mod(b, M),
+            Add => a.add_mod(b, m),
+            Sub => a.add_mod(m - b, m),
+            Mul => a.mul_mod(b, m),
             Eq => U256::from(a == b),
             Neq => U256::from(a != b),
-            Lt => U256::from(cmp_balanced(a, b).is_lt()),
-            Gt => U256::from(cmp_balanced(a, b).is_gt()),
-            Leq => U256::from(cmp_balanced(a, b).is_le()),
-            Geq => U256::from(cmp_balanced(a, b).is_ge()),
+            Lt => U256::from(cmp_balanced(a, b, m).is_lt()),
+            Gt => U256::from(cmp_balanced(a, b, m).is_gt()),
+            Leq => U256::from(cmp_balanced(a, b, m).is_le()),
+            Geq => U256::from(cmp_balanced(a, b, m).is_ge()),
             Lor => U256::from(a != U256::ZERO || b != U256::ZERO),
-            Shl => compute_shl(a, b),
-            Shr => compute_shr(a, b),
-            Band => a.bitand(b) % M,
+            Shl => compute_shl(a, b, m),
+            Shr => compute_shr(a, b, m),
+            Band => a.bitand(b) % m,
             Land => U256::from(a != U256::ZERO && b != U256::ZERO),
-            Neg => (M - a) % M,
-            Inv => a.inv_mod(M).unwrap(),
-            Div => a.mul_mod(b.inv_mod(M).unwrap(), M),
+            Neg => (m - a) % m,
+            Inv => a.inv_mod(m).unwrap(),
+            Div => a.mul_mod(b.inv_mod(m).unwrap(), m),
             Mod => a.reduce_mod(b),
-            Pow => a.pow_mod(b, M),
+            Pow => a.pow_mod(b, m),
             IDiv => a / b,
             _ => unimplemented!("operator {:?} not implemented", self),
         }
     }
 
-    pub fn eval_fr(&self, a: Fr, b: Fr) -> Fr {
+    pub fn eval_fr<F: PrimeField>(&self, a: F, b: F) -> F {
         use Operation::*;
         match self {
             Add => a + b,
@@ -118,54 +147,52 @@ impl Operation {
             Eq => (a == b).into(),
             Neg => -a,
             Div => a / b,
+            // The results are below `a`, so they are reduced. `F::from` does
+            // not support fields with fewer bits than limbs like Goldilocks.
             IDiv => {
                 let a: BigUint = a.into();
                 let b: BigUint = b.into();
-                Fr::from(a / b)
+                F::from_bigint((a / b).try_into().unwrap()).unwrap()
             }
             Mod => {
                 let a: BigUint = a.into();
                 let b: BigUint = b.into();
-                Fr::from(a % b)
+                F::from_bigint((a % b).try_into().unwrap()).unwrap()
             }
             _ => unimplemented!("operator {:?} not implemented for Montgomery", self),
         }
     }
 }
 
-fn compute_shl(a: U256, b: U256) -> U256 {
+impl TryFrom<u8> for Operation {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Operation::ALL.get(value as usize).copied().ok_or(value)
+    }
+}
+
+fn compute_shl(a: U256, b: U256, m: U256) -> U256 {
     assert!(b < uint!(256));
     let s = b.as_limbs()[0] as usize;
-    let mask = (U256::ONE << 254) - U256::ONE;
-    ((a << s) & mask) % M
+    let mask = U256::MAX >> (256 - m.bit_len());
+    ((a << s) & mask) % m
 }
 
-fn compute_shr(a: U256, b: U256) -> U256 {
+fn compute_shr(a: U256, b: U256, m: U256) -> U256 {
     assert!(b < uint!(256));
     let s = b.as_limbs()[0] as usize;
-    (a >> s) % M
+    (a >> s) % m
 }
 
 /// All references must be backwards.
-fn assert_valid(nodes: &[Node]) {
-    for (i, node) in nodes.iter().enumerate() {
-        if let Node::Op(_, a, b) = node {
-            assert!(*a < i);
-            assert!(*b < i);
-        }
+pub(crate) fn assert_valid(nodes: &Nodes) {
+    for i in 0..nodes.len() {
+        nodes.for_each_dependency(i, |j| assert!(j < i));
     }
 }
 
-pub fn optimize(nodes: &mut Vec<Node>, outputs: &mut [usize]) {
-    tree_shake(nodes, outputs);
-    propagate(nodes);
-    value_numbering(nodes, outputs);
-    constants(nodes);
-    tree_shake(nodes, outputs);
-    montgomery_form(nodes);
-}
-
-fn strip_suffix_number(s: String) -> String {
+pub(crate) fn strip_suffix_number(s: String) -> String {
     if let Some(pos) = s.rfind('_') {
         let (prefix, suffix) = s.split_at(pos);
         if suffix[1..].chars().all(|c| c.is_ascii_digit()) {
@@ -175,42 +202,18 @@ fn strip_suffix_number(s: String) -> String {
     s
 }
 
-pub fn evaluate(
-    nodes: &[Node],
+pub fn evaluate<F: GraphField>(
+    nodes: &Nodes,
     inputs: &[U256],
     outputs: &[usize],
-    bbfs: Option<&HashMap<String, BlackBoxFunction>>,
-) -> eyre::Result<Vec<U256>> {
+    bbfs: Option<&HashMap<String, BlackBoxFunction<F>>>,
+) -> Result<Vec<U256>, Error> {
     assert_valid(nodes);
 
     // Evaluate the graph.
     let mut values = Vec::with_capacity(nodes.len());
-    for node in nodes.iter() {
-        let value = match node {
-            Node::Constant(c) => Fr::new(c.into()),
-            Node::MontConstant(c) => *c,
-            Node::Input(i) => {
-                if *i < inputs.len() {
-                    Fr::new(inputs[*i].into())
-                } else {
-                    Fr::new(U256::MAX.into())
-                }
-            }
-            Node::Op(op, a, b) => op.eval_fr(values[*a], values[*b]),
-            Node::BBF(name, params) => {
-                if let Some(bbfs) = bbfs {
-                    let params = params.iter().map(|i| values[*i]).collect::<Vec<_>>();
-                    let name = strip_suffix_number(name.clone());
-                    if let Some(bbf) = bbfs.get(&name) {
-                        bbf(&params)
-                    } else {
-                        bail!("black box function {:?} not found", name);
-                    }
-                } else {
-                    bail!("no black box functions provided");
-                }
-            }
-        };
+    for i in 0..nodes.len() {
+        let value = evaluate_node(nodes, i, &values, inputs, bbfs)?;
         values.push(value);
     }
 
@@ -223,206 +226,46 @@ pub fn evaluate(
     Ok(out)
 }
 
-/// Constant propagation
-pub fn propagate(nodes: &mut [Node]) {
-    assert_valid(nodes);
-    let mut constants = 0_usize;
-    for i in 0..nodes.len() {
-        if let Node::Op(op, a, b) = nodes[i] {
-            if let (Node::Constant(va), Node::Constant(vb)) = (nodes[a].clone(), nodes[b].clone()) {
-                nodes[i] = Node::Constant(op.eval(va, vb));
-                constants += 1;
-            } else if a == b {
-                // Not constant but equal
-                use Operation::*;
-                if let Some(c) = match op {
-                    Eq | Leq | Geq => Some(true),
-                    Neq | Lt | Gt => Some(false),
-                    _ => None,
-                } {
-                    nodes[i] = Node::Constant(U256::from(c));
-                    constants += 1;
-                }
-            }
-        }
-    }
-
-    eprintln!("Propagated {constants} constants");
-}
-
-/// Remove unused nodes
-pub fn tree_shake(nodes: &mut Vec<Node>, outputs: &mut [usize]) {
-    assert_valid(nodes);
-
-    // Mark all nodes that are used.
-    let mut used = vec![false; nodes.len()];
-    for &i in outputs.iter() {
-        used[i] = true;
-    }
-
-    // Work backwards from end as all references are backwards.
-    for i in (0..nodes.len()).rev() {
-        if used[i] {
-            if let Node::Op(_, a, b) = nodes[i] {
-                used[a] = true;
-                used[b] = true;
-            }
-            if let Node::BBF(_, params) = &nodes[i] {
-                for &param in params.iter() {
-                    used[param] = true;
-                }
-            }
-        }
-    }
-
-    // Remove unused nodes
-    let n = nodes.len();
-    let mut retain = used.iter();
-    nodes.retain(|_| *retain.next().unwrap());
-    let removed = n - nodes.len();
-
-    // Renumber references.
-    let mut renumber = vec![None; n];
-    let mut index = 0;
-    for (i, &used) in used.iter().enumerate() {
-        if used {
-            renumber[i] = Some(index);
-            index += 1;
-        }
-    }
-    assert_eq!(index, nodes.len());
-    for (&used, renumber) in used.iter().zip(renumber.iter()) {
-        assert_eq!(used, renumber.is_some());
-    }
-
-    // Renumber references.
-    for node in nodes.iter_mut() {
-        if let Node::Op(_, a, b) = node {
-            *a = renumber[*a].unwrap();
-            *b = renumber[*b].unwrap();
-        }
-        if let Node::BBF(_, params) = node {
-            for param in params.iter_mut() {
-                *param = renumber[*param].unwrap();
-            }
-        }
-    }
-    for output in outputs.iter_mut() {
-        *output = renumber[*output].unwrap();
-    }
-
-    eprintln!("Removed {removed} unused nodes");
-}
-
-/// Randomly evaluate the graph
-fn random_eval(nodes: &mut [Node]) -> Vec<U256> {
-    let mut rng = rand::thread_rng();
-    let mut values = Vec::with_capacity(nodes.len());
-    let mut inputs = HashMap::new();
-    let mut prfs = HashMap::new();
-    for node in nodes.iter() {
-        use Operation::*;
-        let value = match node {
-            Node::BBF(_, _) => rng.gen::<U256>() % M,
-            // Constants evaluate to themselves
-            Node::Constant(c) => *c,
-
-            Node::MontConstant(_) => unimplemented!("should not be used"),
-
-            // Algebraic Ops are evaluated directly
-            // Since the field is large, by Swartz-Zippel if
-            // two values are the same then they are likely algebraically equal.
-            Node::Op(op @ (Add | Sub | Mul | Neg), a, b) => op.eval(values[*a], values[*b]),
-
-            // Input and non-algebraic ops are random functions
-            // TODO: https://github.com/recmo/uint/issues/95 and use .gen_range(..M)
-            Node::Input(i) => *inputs.entry(*i).or_insert_with(|| rng.gen::<U256>() % M),
-            Node::Op(op, a, b) => *prfs
-                .entry((*op, values[*a], values[*b]))
-                .or_insert_with(|| rng.gen::<U256>() % M),
-        };
-        values.push(value);
-    }
-    values
-}
-
-/// Value numbering
-pub fn value_numbering(nodes: &mut [Node], outputs: &mut [usize]) {
-    assert_valid(nodes);
-
-    // Evaluate the graph in random field elements.
-    let values = random_eval(nodes);
-
-    // Find all nodes with the same value.
-    let mut value_map = HashMap::new();
-    for (i, &value) in values.iter().enumerate() {
-        value_map.entry(value).or_insert_with(Vec::new).push(i);
-    }
-
-    // For nodes that are the same, pick the first index.
-    let mut renumber = Vec::with_capacity(nodes.len());
-    for value in values {
-        renumber.push(value_map[&value][0]);
-    }
-
-    // Renumber references.
-    for node in nodes.iter_mut() {
-        if let Node::Op(_, a, b) = node {
-            *a = renumber[*a];
-            *b = renumber[*b];
-        }
-
-        if let Node::BBF(_, params) = node {
-            for p in params.iter_mut() {
-                *p = renumber[*p];
+/// Evaluates node `i`, `values` must hold the values of all its dependencies
+#[inline]
+pub(crate) fn evaluate_node<F: GraphField>(
+    nodes: &Nodes,
+    i: usize,
+    values: &[F],
+    inputs: &[U256],
+    bbfs: Option<&HashMap<String, BlackBoxFunction<F>>>,
+) -> Result<F, Error> {
+    Ok(match nodes.opcode(i) {
+        Opcode::Constant => F::from_u256(nodes.constant(i)),
+        Opcode::MontConstant => nodes.mont_constant(i),
+        Opcode::Input => {
+            let (i, _) = nodes.operands(i);
+            if i < inputs.len() {
+                F::from_u256(inputs[i])
+            } else {
+                F::from_u256(U256::MAX)
             }
         }
-    }
-    for output in outputs.iter_mut() {
-        *output = renumber[*output];
-    }
-
-    eprintln!("Global value numbering applied");
-}
-
-/// Probabilistic constant determination
-pub fn constants(nodes: &mut [Node]) {
-    assert_valid(nodes);
-
-    // Evaluate the graph in random field elements.
-    let values_a = random_eval(nodes);
-    let values_b = random_eval(nodes);
-
-    // Find all nodes with the same value.
-    let mut constants = 0;
-    for i in 0..nodes.len() {
-        if let Node::Constant(_) = nodes[i] {
-            continue;
-        }
-        if values_a[i] == values_b[i] {
-            nodes[i] = Node::Constant(values_a[i]);
-            constants += 1;
+        Opcode::Op(op) => {
+            let (a, b) = nodes.operands(i);
+            op.eval_fr(values[a], values[b])
         }
-    }
-    eprintln!("Found {constants} constants");
-}
-
-/// Convert to Montgomery form
-pub fn montgomery_form(nodes: &mut [Node]) {
-    for node in nodes.iter_mut() {
-        use Node::*;
-        use Operation::*;
-        match node {
-            Constant(c) => *node = MontConstant(Fr::new((*c).into())),
-            MontConstant(..) => (),
-            Input(..) => (),
-            Op(Add | Sub | Mul | Neg | Div | Mod | IDiv, ..) => (),
-            Op(op, ..) => {
-                println!("Operator {:?} not implemented for Montgomery form", op);
-                unimplemented!("Operators Montgomery form")
+        Opcode::BBF => {
+            if let Some(bbfs) = bbfs {
+                let params = nodes
+                    .params(i)
+                    .iter()
+                    .map(|&p| values[p as usize])
+                    .collect::<Vec<_>>();
+                let name = strip_suffix_number(nodes.name(i).to_string());
+                if let Some(bbf) = bbfs.get(&name) {
+                    bbf(&params)
+                } else {
+                    bail!("black box function {:?} not found", name);
+                }
+            } else {
+                bail!("no black box functions provided");
             }
-            BBF(..) => (),
         }
-    }
-    eprintln!("Converted to Montgomery form");
+    })
 }
@@ -0,0 +1,586 @@
+//! Graph optimization passes, available with `std`.
+
+use std::collections::HashMap;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use ruint::aliases::U256;
+use sha2::{Digest, Sha256};
+
+use super::{assert_valid, strip_suffix_number, Node, Operation};
+use crate::{
+    nodes::{Nodes, Opcode},
+    prime::GraphField,
+};
+
+/// Values that fit in this many bits, or whose negation does, are in a small
+/// range. Random evaluations agree by chance too often there, so merges and
+/// constants with such values in all rounds need an exact check.
+const SMALL_RANGE_BITS: usize = 64;
+
+/// Settings of the probabilistic passes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptimizeOptions {
+    /// Seed of the random evaluations, the [`graph_seed`] of the
+    /// unoptimized graph if `None`
+    pub seed: Option<u64>,
+    /// Number of independent random evaluations that must all agree before
+    /// nodes are merged or made constant, 0 counts as 1
+    pub rounds: usize,
+}
+
+impl Default for OptimizeOptions {
+    fn default() -> Self {
+        OptimizeOptions {
+            seed: None,
+            rounds: 2,
+        }
+    }
+}
+
+/// Runs all passes for a graph in the field `F` with the default options
+pub fn optimize<F: GraphField>(nodes: &mut Nodes, outputs: &mut [usize]) {
+    optimize_with::<F>(nodes, outputs, OptimizeOptions::default());
+}
+
+/// Runs all passes, the same graph and options always give the same result
+pub fn optimize_with<F: GraphField>(
+    nodes: &mut Nodes,
+    outputs: &mut [usize],
+    options: OptimizeOptions,
+) {
+    let seed = options.seed.unwrap_or_else(|| graph_seed(nodes, outputs));
+    eprintln!(
+        "Optimizing with seed {seed:#018x} and {} rounds",
+        options.rounds
+    );
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    tree_shake(nodes, outputs);
+    loop {
+        propagate::<F>(nodes);
+        let stats = simplify::<F>(nodes, outputs);
+        tree_shake(nodes, outputs);
+        if stats.total() == 0 {
+            break;
+        }
+    }
+    common_subexpressions(nodes, outputs);
+    value_numbering::<F>(nodes, outputs, &mut rng, options.rounds);
+    constants::<F>(nodes, &mut rng, options.rounds);
+    tree_shake(nodes, outputs);
+    montgomery_form::<F>(nodes);
+    nodes.shrink_to_fit();
+}
+
+/// Seed from the SHA-256 of the encoded nodes and outputs
+pub fn graph_seed(nodes: &Nodes, outputs: &[usize]) -> u64 {
+    let bytes = postcard::to_allocvec(&(nodes, outputs)).unwrap();
+    u64::from_le_bytes(Sha256::digest(bytes)[..8].try_into().unwrap())
+}
+
+/// Constant propagation
+pub fn propagate<F: GraphField>(nodes: &mut Nodes) {
+    assert_valid(nodes);
+    let m = F::modulus();
+    let mut constants = 0_usize;
+    for i in 0..nodes.len() {
+        if let Opcode::Op(op) = nodes.opcode(i) {
+            let (a, b) = nodes.operands(i);
+            if let (Opcode::Constant, Opcode::Constant) = (nodes.opcode(a), nodes.opcode(b)) {
+                let (va, vb) = (nodes.constant(a), nodes.constant(b));
+                nodes.set(i, Node::Constant(op.eval_mod(va, vb, m)));
+                constants += 1;
+            } else if a == b {
+                // Not constant but equal
+                use Operation::*;
+                if let Some(c) = match op {
+                    Eq | Leq | Geq => Some(true),
+                    Neq | Lt | Gt => Some(false),
+                    _ => None,
+                } {
+                    nodes.set(i, Node::Constant(U256::from(c)));
+                    constants += 1;
+                }
+            }
+        }
+    }
+
+    eprintln!("Propagated {constants} constants");
+}
+
+/// Result of a simplification rule
+enum Rewrite {
+    /// The value of an earlier node
+    Same(usize),
+    Constant(U256),
+    /// An operation on a node and a new constant
+    WithConstant(Operation, usize, U256),
+}
+
+/// A simplification of `a op b` in the field of the modulus, the operands
+/// are nodes of the simplified graph
+type Rule = fn(&Nodes, Operation, usize, usize, U256) -> Option<Rewrite>;
+
+/// Algebraic identities of [`simplify`], the first matching rule applies
+const RULES: &[(&str, Rule)] = &[
+    ("x * 1", mul_one),
+    ("x * 0", mul_zero),
+    ("x + 0", add_zero),
+    ("x - 0", sub_zero),
+    ("x - x", sub_self),
+    ("-(-x)", double_neg),
+    ("x / c", div_constant),
+    ("x - c", sub_constant),
+];
+
+/// Value of node `i` if it is a constant
+fn constant_value(nodes: &Nodes, i: usize) -> Option<U256> {
+    matches!(nodes.opcode(i), Opcode::Constant).then(|| nodes.constant(i))
+}
+
+/// The operand other than the constant `c` of a commutative operation
+fn other_than(nodes: &Nodes, a: usize, b: usize, c: U256) -> Option<usize> {
+    if constant_value(nodes, b) == Some(c) {
+        Some(a)
+    } else if constant_value(nodes, a) == Some(c) {
+        Some(b)
+    } else {
+        None
+    }
+}
+
+fn mul_one(nodes: &Nodes, op: Operation, a: usize, b: usize, _: U256) -> Option<Rewrite> {
+    if op != Operation::Mul {
+        return None;
+    }
+    other_than(nodes, a, b, U256::from(1)).map(Rewrite::Same)
+}
+
+fn mul_zero(nodes: &Nodes, op: Operation, a: usize, b: usize, _: U256) -> Option<Rewrite> {
+    if op != Operation::Mul {
+        return None;
+    }
+    other_than(nodes, a, b, U256::ZERO).map(|_| Rewrite::Constant(U256::ZERO))
+}
+
+fn add_zero(nodes: &Nodes, op: Operation, a: usize, b: usize, _: U256) -> Option<Rewrite> {
+    if op != Operation::Add {
+        return None;
+    }
+    other_than(nodes, a, b, U256::ZERO).map(Rewrite::Same)
+}
+
+fn sub_zero(nodes: &Nodes, op: Operation, a: usize, b: usize, _: U256) -> Option<Rewrite> {
+    (op == Operation::Sub && constant_value(nodes, b) == Some(U256::ZERO))
+        .then_some(Rewrite::Same(a))
+}
+
+fn sub_self(_: &Nodes, op: Operation, a: usize, b: usize, _: U256) -> Option<Rewrite> {
+    (op == Operation::Sub && a == b).then_some(Rewrite::Constant(U256::ZERO))
+}
+
+fn double_neg(nodes: &Nodes, op: Operation, a: usize, _: usize, _: U256) -> Option<Rewrite> {
+    if op != Operation::Neg || nodes.opcode(a) != Opcode::Op(Operation::Neg) {
+        return None;
+    }
+    Some(Rewrite::Same(nodes.operands(a).0))
+}
+
+fn div_constant(nodes: &Nodes, op: Operation, a: usize, b: usize, m: U256) -> Option<Rewrite> {
+    if op != Operation::Div {
+        return None;
+    }
+    let inverse = constant_value(nodes, b)?.inv_mod(m)?;
+    Some(Rewrite::WithConstant(Operation::Mul, a, inverse))
+}
+
+fn sub_constant(nodes: &Nodes, op: Operation, a: usize, b: usize, m: U256) -> Option<Rewrite> {
+    if op != Operation::Sub {
+        return None;
+    }
+    let c = constant_value(nodes, b)?;
+    Some(Rewrite::WithConstant(Operation::Add, a, (m - c) % m))
+}
+
+/// Number of nodes rewritten by each rule of [`simplify`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SimplifyStats {
+    /// `x * 1 = x`
+    pub mul_one: usize,
+    /// `x * 0 = 0`
+    pub mul_zero: usize,
+    /// `x + 0 = x`
+    pub add_zero: usize,
+    /// `x - 0 = x`
+    pub sub_zero: usize,
+    /// `x - x = 0`
+    pub sub_self: usize,
+    /// `-(-x) = x`
+    pub double_neg: usize,
+    /// `x / c = x * c⁻¹`
+    pub div_constant: usize,
+    /// `x - c = x + (-c)`
+    pub sub_constant: usize,
+}
+
+impl SimplifyStats {
+    /// Number of simplified nodes
+    pub fn total(&self) -> usize {
+        self.mul_one
+            + self.mul_zero
+            + self.add_zero
+            + self.sub_zero
+            + self.sub_self
+            + self.double_neg
+            + self.div_constant
+            + self.sub_constant
+    }
+}
+
+/// Algebraic simplification with the [`RULES`], returns how often each rule
+/// applied
+pub fn simplify<F: GraphField>(nodes: &mut Nodes, outputs: &mut [usize]) -> SimplifyStats {
+    assert_valid(nodes);
+    let m = F::modulus();
+
+    // Rebuild the graph, rewrites may add constants before the node.
+    let mut hits = [0_usize; RULES.len()];
+    let mut simplified = Nodes::with_capacity(nodes.len());
+    let mut renumber = Vec::with_capacity(nodes.len());
+    for node in nodes.iter() {
+        let node = match node {
+            Node::Op(op, a, b) => Node::Op(op, renumber[a], renumber[b]),
+            Node::BBF(name, params) => {
+                Node::BBF(name, params.into_iter().map(|p| renumber[p]).collect())
+            }
+            node => node,
+        };
+        let rewrite = match node {
+            Node::Op(op, a, b) => RULES
+                .iter()
+                .enumerate()
+                .find_map(|(rule, (_, apply))| Some((rule, apply(&simplified, op, a, b, m)?))),
+            _ => None,
+        };
+        let index = match rewrite {
+            None => simplified.push(node),
+            Some((rule, rewrite)) => {
+                hits[rule] += 1;
+                match rewrite {
+                    Rewrite::Same(j) => j,
+                    Rewrite::Constant(c) => simplified.push(Node::Constant(c)),
+                    Rewrite::WithConstant(op, a, c) => {
+                        let c = simplified.push(Node::Constant(c));
+                        simplified.push(Node::Op(op, a, c))
+                    }
+                }
+            }
+        };
+        renumber.push(index);
+    }
+    *nodes = simplified;
+    for output in outputs.iter_mut() {
+        *output = renumber[*output];
+    }
+
+    let total: usize = hits.iter().sum();
+    let counts: Vec<_> = RULES
+        .iter()
+        .zip(hits)
+        .filter(|&(_, hits)| hits > 0)
+        .map(|((name, _), hits)| format!("{name}: {hits}"))
+        .collect();
+    eprintln!("Simplified {total} nodes ({})", counts.join(", "));
+
+    // In the order of the RULES.
+    let [mul_one, mul_zero, add_zero, sub_zero, sub_self, double_neg, div_constant, sub_constant] =
+        hits;
+    SimplifyStats {
+        mul_one,
+        mul_zero,
+        add_zero,
+        sub_zero,
+        sub_self,
+        double_neg,
+        div_constant,
+        sub_constant,
+    }
+}
+
+/// Remove unused nodes
+pub fn tree_shake(nodes: &mut Nodes, outputs: &mut [usize]) {
+    assert_valid(nodes);
+
+    // Mark all nodes that are used.
+    let mut used = vec![false; nodes.len()];
+    for &i in outputs.iter() {
+        used[i] = true;
+    }
+
+    // Work backwards from end as all references are backwards.
+    for i in (0..nodes.len()).rev() {
+        if used[i] {
+            nodes.for_each_dependency(i, |j| used[j] = true);
+        }
+    }
+
+    // Remove unused nodes
+    let n = nodes.len();
+    nodes.retain(&used);
+    let removed = n - nodes.len();
+
+    // Renumber references.
+    let mut renumber = vec![None; n];
+    let mut index = 0;
+    for (i, &used) in used.iter().enumerate() {
+        if used {
+            renumber[i] = Some(index);
+            index += 1;
+        }
+    }
+    assert_eq!(index, nodes.len());
+    for (&used, renumber) in used.iter().zip(renumber.iter()) {
+        assert_eq!(used, renumber.is_some());
+    }
+
+    // Renumber references.
+    nodes.renumber(|i| renumber[i].unwrap());
+    for output in outputs.iter_mut() {
+        *output = renumber[*output].unwrap();
+    }
+
+    eprintln!("Removed {removed} unused nodes");
+}
+
+/// Randomly evaluate the graph
+fn random_eval<F: GraphField>(nodes: &Nodes, rng: &mut impl Rng) -> Vec<U256> {
+    let m = F::modulus();
+    let mut values = Vec::with_capacity(nodes.len());
+    let mut inputs = HashMap::new();
+    let mut prfs = HashMap::new();
+    for i in 0..nodes.len() {
+        use Operation::*;
+        let (a, b) = nodes.operands(i);
+        let value = match nodes.opcode(i) {
+            Opcode::BBF => rng.gen::<U256>() % m,
+            // Constants evaluate to themselves
+            Opcode::Constant => nodes.constant(i),
+
+            Opcode::MontConstant => unimplemented!("should not be used"),
+
+            // Algebraic Ops are evaluated directly
+            // Since the field is large, by Swartz-Zippel if
+            // two values are the same then they are likely algebraically equal.
+            Opcode::Op(op @ (Add | Sub | Mul | Neg)) => op.eval_mod(values[a], values[b], m),
+
+            // Input and non-algebraic ops are random functions
+            // TODO: https://github.com/recmo/uint/issues/95 and use .gen_range(..M)
+            Opcode::Input => *inputs.entry(a).or_insert_with(|| rng.gen::<U256>() % m),
+            Opcode::Op(op) => *prfs
+                .entry((op, values[a], values[b]))
+                .or_insert_with(|| rng.gen::<U256>() % m),
+        };
+        values.push(value);
+    }
+    values
+}
+
+/// Whether `value` is in the small range, like booleans, Legendre symbols and
+/// every value of a 64 bit field
+fn is_small_range<F: GraphField>(value: U256) -> bool {
+    let negated = F::modulus() - value;
+    value.bit_len() <= SMALL_RANGE_BITS || negated.bit_len() <= SMALL_RANGE_BITS
+}
+
+/// Whether nodes `i` and `j` are the same operation on the same operands,
+/// given the merges of earlier nodes in `renumber`
+fn same_structure(nodes: &Nodes, renumber: &[usize], i: usize, j: usize) -> bool {
+    structure_key(nodes, renumber, i) == structure_key(nodes, renumber, j)
+}
+
+/// Operation of a node on its renumbered operands, equal keys mean equal
+/// values
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Input(usize),
+    Constant(Opcode, U256),
+    Op(Operation, usize, usize),
+    /// Black box function, by the name it is looked up with, and its params
+    Call(String, Vec<usize>),
+}
+
+/// Key of node `i` given the merges of earlier nodes in `renumber`, with the
+/// operands of commutative operations ordered. Black box functions are pure,
+/// calls with the same arguments are equal.
+fn structure_key(nodes: &Nodes, renumber: &[usize], i: usize) -> Key {
+    use Operation::*;
+    let (a, b) = nodes.operands(i);
+    match nodes.opcode(i) {
+        Opcode::Input => Key::Input(a),
+        opcode @ (Opcode::Constant | Opcode::MontConstant) => {
+            Key::Constant(opcode, nodes.constant(i))
+        }
+        Opcode::Op(op) => {
+            let (a, b) = (renumber[a], renumber[b]);
+            let (a, b) = match op {
+                Add | Mul | Eq | Neq | Band | Land | Lor => (a.min(b), a.max(b)),
+                _ => (a, b),
+            };
+            Key::Op(op, a, b)
+        }
+        Opcode::BBF => Key::Call(
+            strip_suffix_number(nodes.name(i).to_string()),
+            nodes
+                .params(i)
+                .iter()
+                .map(|&p| renumber[p as usize])
+                .collect(),
+        ),
+    }
+}
+
+/// Exact common subexpression elimination, merges nodes with the same
+/// [`Key`] into the first of them, returns the number of merged nodes
+pub fn common_subexpressions(nodes: &mut Nodes, outputs: &mut [usize]) -> usize {
+    assert_valid(nodes);
+
+    let mut first = HashMap::new();
+    let mut renumber = Vec::with_capacity(nodes.len());
+    let mut eliminated = 0_usize;
+    for i in 0..nodes.len() {
+        let j = *first.entry(structure_key(nodes, &renumber, i)).or_insert(i);
+        eliminated += usize::from(j != i);
+        renumber.push(j);
+    }
+
+    // Renumber references.
+    nodes.renumber(|i| renumber[i]);
+    for output in outputs.iter_mut() {
+        *output = renumber[*output];
+    }
+
+    eprintln!("Eliminated {eliminated} common subexpressions");
+    eliminated
+}
+
+/// Value numbering, merges nodes with the same values in all `rounds`, at
+/// least one
+pub fn value_numbering<F: GraphField>(
+    nodes: &mut Nodes,
+    outputs: &mut [usize],
+    rng: &mut impl Rng,
+    rounds: usize,
+) {
+    assert_valid(nodes);
+    let rounds = rounds.max(1);
+
+    // Evaluate the graph in random field elements.
+    let evaluations: Vec<_> = (0..rounds).map(|_| random_eval::<F>(nodes, rng)).collect();
+
+    // Merge every node into the first node with the same values. If all
+    // values are in the small range, only into a node with the same structure.
+    let mut classes: HashMap<Vec<U256>, Vec<usize>> = HashMap::new();
+    let mut renumber = Vec::with_capacity(nodes.len());
+    let mut rejected = 0_usize;
+    for i in 0..nodes.len() {
+        let values: Vec<U256> = evaluations.iter().map(|values| values[i]).collect();
+        let small = values.iter().all(|&value| is_small_range::<F>(value));
+        let class = classes.entry(values).or_default();
+        let representative = if small {
+            class
+                .iter()
+                .copied()
+                .find(|&j| same_structure(nodes, &renumber, i, j))
+        } else {
+            class.first().copied()
+        };
+        match representative {
+            Some(j) => renumber.push(j),
+            None => {
+                rejected += usize::from(!class.is_empty());
+                class.push(i);
+                renumber.push(i);
+            }
+        }
+    }
+
+    // Renumber references.
+    nodes.renumber(|i| renumber[i]);
+    for output in outputs.iter_mut() {
+        *output = renumber[*output];
+    }
+
+    eprintln!("Global value numbering applied, {rejected} small-range merges rejected");
+}
+
+/// Value of node `i` if it is an operation on constants
+fn exact_constant<F: GraphField>(nodes: &Nodes, i: usize) -> Option<U256> {
+    use Operation::*;
+    let Opcode::Op(op) = nodes.opcode(i) else {
+        return None;
+    };
+    let (a, b) = nodes.operands(i);
+    let (Opcode::Constant, Opcode::Constant) = (nodes.opcode(a), nodes.opcode(b)) else {
+        return None;
+    };
+    let (a, b) = (nodes.constant(a), nodes.constant(b));
+    let fails = match op {
+        Inv => a.is_zero(),
+        Div | Mod | IDiv => b.is_zero(),
+        _ => false,
+    };
+    (!fails).then(|| op.eval_mod(a, b, F::modulus()))
+}
+
+/// Probabilistic constant determination, a node is constant if it has the
+/// same value in `rounds + 1` random evaluations, `rounds` is at least one
+pub fn constants<F: GraphField>(nodes: &mut Nodes, rng: &mut impl Rng, rounds: usize) {
+    assert_valid(nodes);
+    let rounds = rounds.max(1);
+
+    // Evaluate the graph in random field elements.
+    let evaluations: Vec<_> = (0..=rounds).map(|_| random_eval::<F>(nodes, rng)).collect();
+
+    // Find all nodes with the same value. A value in the small range must
+    // follow exactly from constant operands.
+    let mut constants = 0;
+    let mut rejected = 0;
+    for i in 0..nodes.len() {
+        if let Opcode::Constant = nodes.opcode(i) {
+            continue;
+        }
+        let value = evaluations[0][i];
+        if evaluations[1..].iter().any(|values| values[i] != value) {
+            continue;
+        }
+        if is_small_range::<F>(value) && exact_constant::<F>(nodes, i) != Some(value) {
+            rejected += 1;
+            continue;
+        }
+        nodes.set(i, Node::Constant(value));
+        constants += 1;
+    }
+    eprintln!("Found {constants} constants, {rejected} small-range constants rejected");
+}
+
+/// Convert to Montgomery form
+pub fn montgomery_form<F: GraphField>(nodes: &mut Nodes) {
+    for i in 0..nodes.len() {
+        use Operation::*;
+        match nodes.opcode(i) {
+            Opcode::Constant => {
+                let c = F::from_u256(nodes.constant(i)).montgomery();
+                nodes.set(i, Node::MontConstant(c));
+            }
+            Opcode::MontConstant => (),
+            Opcode::Input => (),
+            Opcode::Op(Add | Sub | Mul | Neg | Div | Mod | IDiv) => (),
+            Opcode::Op(op) => {
+                println!("Operator {:?} not implemented for Montgomery form", op);
+                unimplemented!("Operators Montgomery form")
+            }
+            Opcode::BBF => (),
+        }
+    }
+    eprintln!("Converted to Montgomery form");
+}
@@ -0,0 +1,411 @@
+//! Native code generation for bytecode programs with Cranelift.
+//!
+//! [`JitGraph::compile`] translates every [`Instruction`] of a
+//! [`Program`](crate::bytecode::Program) into a call of a Montgomery
+//! arithmetic routine on the register file, which removes the dispatch
+//! overhead of the interpreter. The instructions are split into chunks that
+//! are compiled as separate functions to keep compile times linear for large
+//! graphs. Like the bytecode, compiled programs evaluate in BN254, since
+//! [`lower`](crate::bytecode::lower) rejects graphs over other primes.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use ark_bn254::Fr;
+use ark_ff::{AdditiveGroup, Field};
+use cranelift_codegen::ir::{types, AbiParam, InstBuilder, Signature, Type, Value};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, FuncId, Linkage, Module};
+use eyre::{bail, eyre};
+use ruint::aliases::U256;
+
+use crate::{
+    bytecode::{Instruction, Program},
+    graph::Operation,
+    BlackBoxFunction, HashMap,
+};
+
+/// Instructions per compiled function
+const CHUNK_SIZE: usize = 4096;
+
+const FR_SIZE: i64 = std::mem::size_of::<Fr>() as i64;
+
+/// `fn(values, inputs, constants, out, context) -> status`
+type ChunkFn = unsafe extern "C" fn(*mut Fr, *const Fr, *const Fr, *mut Fr, *mut Context) -> i32;
+
+/// State shared with the callbacks of a running evaluation
+struct Context<'a> {
+    program: &'a Program,
+    bbfs: Option<&'a HashMap<String, BlackBoxFunction>>,
+    error: Option<eyre::Report>,
+}
+
+pub struct JitGraph {
+    program: Program,
+    inputs: usize,
+    chunks: Vec<ChunkFn>,
+    module: Option<JITModule>,
+}
+
+// SAFETY: The module is only accessed through `&mut self` when dropping, the
+// compiled functions are immutable code that only touch their arguments.
+unsafe impl Sync for JitGraph {}
+
+impl Drop for JitGraph {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            // SAFETY: The function pointers are dropped together with the module.
+            unsafe { module.free_memory() };
+        }
+    }
+}
+
+unsafe extern "C" fn fr_copy(dst: *mut Fr, a: *const Fr) {
+    *dst = *a;
+}
+
+unsafe extern "C" fn fr_add(dst: *mut Fr, a: *const Fr, b: *const Fr) {
+    *dst = *a + *b;
+}
+
+unsafe extern "C" fn fr_sub(dst: *mut Fr, a: *const Fr, b: *const Fr) {
+    *dst = *a - *b;
+}
+
+unsafe extern "C" fn fr_mul(dst: *mut Fr, a: *const Fr, b: *const Fr) {
+    *dst = *a * *b;
+}
+
+unsafe extern "C" fn fr_neg(dst: *mut Fr, a: *const Fr) {
+    *dst = -*a;
+}
+
+unsafe extern "C" fn fr_square(dst: *mut Fr, a: *const Fr) {
+    *dst = (*a).square();
+}
+
+unsafe extern "C" fn fr_pow5(dst: *mut Fr, a: *const Fr) {
+    let x = *a;
+    *dst = x.square().square() * x;
+}
+
+unsafe extern "C" fn fr_mul_add(dst: *mut Fr, a: *const Fr, b: *const Fr, c: *const Fr) {
+    *dst = *a * *b + *c;
+}
+
+unsafe extern "C" fn fr_op(
+    context: *mut Context,
+    op: u32,
+    dst: *mut Fr,
+    a: *const Fr,
+    b: *const Fr,
+) -> i32 {
+    let (a, b) = (*a, *b);
+    match catch_unwind(|| Operation::ALL[op as usize].eval_fr(a, b)) {
+        Ok(value) => {
+            *dst = value;
+            0
+        }
+        Err(_) => {
+            let op = Operation::ALL[op as usize];
+            (*context).error = Some(eyre!("operation {op:?} failed"));
+            1
+        }
+    }
+}
+
+unsafe extern "C" fn fr_bbf(
+    context: *mut Context,
+    call: u32,
+    values: *const Fr,
+    dst: *mut Fr,
+) -> i32 {
+    let context = &mut *context;
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        context
+            .program
+            .call_bbf(call, context.bbfs, |r| unsafe { *values.add(r as usize) })
+    }));
+    match result {
+        Ok(Ok(value)) => {
+            *dst = value;
+            0
+        }
+        Ok(Err(error)) => {
+            context.error = Some(error.into());
+            1
+        }
+        Err(_) => {
+            context.error = Some(eyre!("black box function panicked"));
+            1
+        }
+    }
+}
+
+/// Imported Montgomery routines
+struct Routines {
+    copy: FuncId,
+    add: FuncId,
+    sub: FuncId,
+    mul: FuncId,
+    neg: FuncId,
+    square: FuncId,
+    pow5: FuncId,
+    mul_add: FuncId,
+    op: FuncId,
+    bbf: FuncId,
+}
+
+impl JitGraph {
+    /// Compiles the program to native code for the host
+    pub fn compile(program: &Program) -> eyre::Result<Self> {
+        let mut builder = JITBuilder::new(default_libcall_names())?;
+        let symbols: [(&str, *const u8); 10] = [
+            ("cwr_fr_copy", fr_copy as *const u8),
+            ("cwr_fr_add", fr_add as *const u8),
+            ("cwr_fr_sub", fr_sub as *const u8),
+            ("cwr_fr_mul", fr_mul as *const u8),
+            ("cwr_fr_neg", fr_neg as *const u8),
+            ("cwr_fr_square", fr_square as *const u8),
+            ("cwr_fr_pow5", fr_pow5 as *const u8),
+            ("cwr_fr_mul_add", fr_mul_add as *const u8),
+            ("cwr_fr_op", fr_op as *const u8),
+            ("cwr_fr_bbf", fr_bbf as *const u8),
+        ];
+        builder.symbols(symbols);
+        let mut module = JITModule::new(builder);
+
+        let ptr = module.target_config().pointer_type();
+        let (i32, p) = (types::I32, ptr);
+        let routines = Routines {
+            copy: import(&mut module, "cwr_fr_copy", &[p, p], false)?,
+            add: import(&mut module, "cwr_fr_add", &[p, p, p], false)?,
+            sub: import(&mut module, "cwr_fr_sub", &[p, p, p], false)?,
+            mul: import(&mut module, "cwr_fr_mul", &[p, p, p], false)?,
+            neg: import(&mut module, "cwr_fr_neg", &[p, p], false)?,
+            square: import(&mut module, "cwr_fr_square", &[p, p], false)?,
+            pow5: import(&mut module, "cwr_fr_pow5", &[p, p], false)?,
+            mul_add: import(&mut module, "cwr_fr_mul_add", &[p, p, p, p], false)?,
+            op: import(&mut module, "cwr_fr_op", &[p, i32, p, p, p], true)?,
+            bbf: import(&mut module, "cwr_fr_bbf", &[p, i32, p, p], true)?,
+        };
+
+        let chunk_signature = signature(&module, &[p; 5], true);
+        let mut context = module.make_context();
+        let mut builder_context = FunctionBuilderContext::new();
+        let mut ids = vec![];
+        for (chunk, instructions) in program.instructions.chunks(CHUNK_SIZE).enumerate() {
+            let id = module.declare_function(
+                &format!("chunk_{chunk}"),
+                Linkage::Local,
+                &chunk_signature,
+            )?;
+            context.func.signature = chunk_signature.clone();
+            let start = chunk * CHUNK_SIZE;
+            translate(
+                &mut module,
+                FunctionBuilder::new(&mut context.func, &mut builder_context),
+                &routines,
+                program,
+                start,
+                instructions,
+            );
+            module.define_function(id, &mut context)?;
+            module.clear_context(&mut context);
+            ids.push(id);
+        }
+        module.finalize_definitions()?;
+
+        let chunks = ids
+            .into_iter()
+            .map(|id| {
+                // SAFETY: The function was compiled with the signature of `ChunkFn`.
+                unsafe {
+                    std::mem::transmute::<*const u8, ChunkFn>(module.get_finalized_function(id))
+                }
+            })
+            .collect::<Vec<_>>();
+        let inputs = program
+            .instructions
+            .iter()
+            .map(|instruction| match *instruction {
+                Instruction::Input(i) => i as usize + 1,
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0);
+
+        eprintln!(
+            "Compiled {} instructions in {} functions",
+            program.instructions.len(),
+            chunks.len()
+        );
+        Ok(JitGraph {
+            program: program.clone(),
+            inputs,
+            chunks,
+            module: Some(module),
+        })
+    }
+
+    /// Evaluates the compiled program, giving the same result as
+    /// [`Program::evaluate`]
+    pub fn evaluate(
+        &self,
+        inputs: &[U256],
+        bbfs: Option<&HashMap<String, BlackBoxFunction>>,
+    ) -> eyre::Result<Vec<U256>> {
+        let program = &self.program;
+        let inputs = (0..self.inputs)
+            .map(|i| Fr::new(inputs.get(i).copied().unwrap_or(U256::MAX).into()))
+            .collect::<Vec<_>>();
+        let mut values = vec![Fr::ZERO; program.registers];
+        values[..program.preload.len()].copy_from_slice(&program.preload);
+        let mut out = vec![Fr::ZERO; program.outputs.len()];
+        for output in program.outputs.iter().filter(|output| output.step == 0) {
+            out[output.position as usize] = values[output.register as usize];
+        }
+
+        let mut context = Context {
+            program,
+            bbfs,
+            error: None,
+        };
+        for chunk in self.chunks.iter() {
+            // SAFETY: The buffers have the sizes the program was compiled for.
+            let status = unsafe {
+                chunk(
+                    values.as_mut_ptr(),
+                    inputs.as_ptr(),
+                    program.constants.as_ptr(),
+                    out.as_mut_ptr(),
+                    &mut context,
+                )
+            };
+            if status != 0 {
+                match context.error.take() {
+                    Some(error) => return Err(error),
+                    None => bail!("evaluation failed with status {status}"),
+                }
+            }
+        }
+
+        // Convert from Montgomery form and return the outputs.
+        Ok(out.into_iter().map(Into::into).collect())
+    }
+}
+
+fn signature(module: &JITModule, params: &[Type], status: bool) -> Signature {
+    let mut signature = module.make_signature();
+    signature
+        .params
+        .extend(params.iter().map(|&t| AbiParam::new(t)));
+    if status {
+        signature.returns.push(AbiParam::new(types::I32));
+    }
+    signature
+}
+
+/// Declares an imported routine, returning an `i32` status if `status` is set
+fn import(
+    module: &mut JITModule,
+    name: &str,
+    params: &[Type],
+    status: bool,
+) -> eyre::Result<FuncId> {
+    let signature = signature(module, params, status);
+    Ok(module.declare_function(name, Linkage::Import, &signature)?)
+}
+
+/// Emits the instructions `start..start + instructions.len()` into `builder`.
+fn translate(
+    module: &mut JITModule,
+    mut builder: FunctionBuilder,
+    routines: &Routines,
+    program: &Program,
+    start: usize,
+    instructions: &[Instruction],
+) {
+    let entry = builder.create_block();
+    let exit = builder.create_block();
+    builder.append_block_params_for_function_params(entry);
+    builder.append_block_param(exit, types::I32);
+    builder.switch_to_block(entry);
+    let [values, inputs, constants, out, context] = builder.block_params(entry)[..] else {
+        unreachable!()
+    };
+
+    let funcs = [
+        routines.copy,
+        routines.add,
+        routines.sub,
+        routines.mul,
+        routines.neg,
+        routines.square,
+        routines.pow5,
+        routines.mul_add,
+        routines.op,
+        routines.bbf,
+    ]
+    .map(|id| module.declare_func_in_func(id, builder.func));
+    let [copy, add, sub, mul, neg, square, pow5, mul_add, op, bbf] = funcs;
+
+    let mut outputs = program.outputs.iter().peekable();
+    while outputs.next_if(|o| (o.step as usize) <= start).is_some() {}
+
+    for (k, instruction) in instructions.iter().enumerate() {
+        let k = start + k;
+        let mut at = |base: Value, i: u32| builder.ins().iadd_imm(base, i as i64 * FR_SIZE);
+        let dst = at(values, program.destinations[k]);
+        let (func, args, status) = match *instruction {
+            Instruction::Input(i) => (copy, vec![dst, at(inputs, i)], false),
+            Instruction::Add(a, b) => (add, vec![dst, at(values, a), at(values, b)], false),
+            Instruction::Sub(a, b) => (sub, vec![dst, at(values, a), at(values, b)], false),
+            Instruction::Mul(a, b) => (mul, vec![dst, at(values, a), at(values, b)], false),
+            Instruction::Neg(a) => (neg, vec![dst, at(values, a)], false),
+            Instruction::AddConst(a, c) => (add, vec![dst, at(values, a), at(constants, c)], false),
+            Instruction::MulConst(a, c) => (mul, vec![dst, at(values, a), at(constants, c)], false),
+            Instruction::MulAdd(a, b, c) => {
+                let args = vec![dst, at(values, a), at(values, b), at(values, c)];
+                (mul_add, args, false)
+            }
+            Instruction::Square(a) => (square, vec![dst, at(values, a)], false),
+            Instruction::Pow5(a) => (pow5, vec![dst, at(values, a)], false),
+            Instruction::Op(operation, a, b) => {
+                let (a, b) = (at(values, a), at(values, b));
+                let operation = builder.ins().iconst(types::I32, operation as i64);
+                (op, vec![context, operation, dst, a, b], true)
+            }
+            Instruction::BBF(i) => {
+                let call = builder.ins().iconst(types::I32, i as i64);
+                (bbf, vec![context, call, values, dst], true)
+            }
+        };
+        let call = builder.ins().call(func, &args);
+        if status {
+            let status = builder.inst_results(call)[0];
+            let next = builder.create_block();
+            builder.ins().brif(status, exit, &[status], next, &[]);
+            builder.switch_to_block(next);
+        }
+
+        // Copy out the outputs computed by this instruction.
+        while let Some(output) = outputs.next_if(|o| o.step as usize == k + 1) {
+            let target = builder
+                .ins()
+                .iadd_imm(out, output.position as i64 * FR_SIZE);
+            let source = builder
+                .ins()
+                .iadd_imm(values, output.register as i64 * FR_SIZE);
+            builder.ins().call(copy, &[target, source]);
+        }
+    }
+    let ok = builder.ins().iconst(types::I32, 0);
+    builder.ins().jump(exit, &[ok]);
+
+    builder.switch_to_block(exit);
+    let status = builder.block_params(exit)[0];
+    builder.ins().return_(&[status]);
+    builder.seal_all_blocks();
+    builder.finalize();
+}
@@ -1,4 +1,27 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod bytecode;
+pub mod dat;
+pub mod error;
+pub mod flat;
+pub mod format;
 pub mod graph;
+pub mod nodes;
+pub mod prime;
+
+#[cfg(feature = "std")]
+pub mod codegen;
+
+#[cfg(feature = "capi")]
+pub mod capi;
+
+#[cfg(feature = "jit")]
+pub mod jit;
+
+#[cfg(feature = "parallel")]
+pub mod parallel;
 
 #[cfg(feature = "build-witness")]
 pub mod generate;
@@ -6,15 +29,27 @@ pub mod generate;
 #[cfg(feature = "build-witness")]
 mod field;
 
-use std::{collections::HashMap, sync::Arc};
+use alloc::{
+    borrow::Borrow, collections::BTreeMap, format, string::String, sync::Arc, vec, vec::Vec,
+};
 
 use ark_bn254::Fr;
 use ruint::{aliases::U256, uint};
 use serde::{Deserialize, Serialize};
 
-use crate::graph::Node;
+use crate::{
+    error::{bail, ensure, err},
+    nodes::Nodes,
+    prime::{GraphField, Prime},
+};
+
+pub use crate::error::Error;
+
+/// Map type of the public interface. It is `hashbrown` with and without
+/// `std`, so the API does not change when another crate enables the feature.
+pub use hashbrown::HashMap;
 
-pub type BlackBoxFunction = Arc<dyn Fn(&[Fr]) -> Fr + Send + Sync + 'static>;
+pub type BlackBoxFunction<F = Fr> = Arc<dyn Fn(&[F]) -> F + Send + Sync + 'static>;
 
 pub const M: U256 =
     uint!(21888242871839275222246405745257275088548364400416034343698204186575808495617_U256);
@@ -24,15 +59,99 @@ pub struct HashSignalInfo {
     pub hash: u64,
     pub signalid: u64,
     pub signalsize: u64,
+    /// Name of the input, empty in graphs built before names were recorded
+    pub name: String,
+    /// Dimensions of the input, empty for a single signal and in graphs
+    /// built before shapes were recorded
+    pub shape: Vec<usize>,
+}
+
+impl HashSignalInfo {
+    /// Finds the entry of the input `name` by its hash and checks the
+    /// recorded name, so that a colliding name never resolves to another
+    /// input
+    pub fn find<I: Borrow<HashSignalInfo>>(
+        entries: impl IntoIterator<Item = I>,
+        name: &str,
+    ) -> Result<I, Error> {
+        let hash = fnv1a(name);
+        let entry = entries
+            .into_iter()
+            .find(|entry| entry.borrow().hash == hash)
+            .ok_or_else(|| err!("unknown input {name:?}"))?;
+        let recorded = &entry.borrow().name;
+        ensure!(
+            recorded.is_empty() || recorded == name,
+            "unknown input {name:?}, it has the hash of input {recorded:?}"
+        );
+        Ok(entry)
+    }
+}
+
+/// Information about how a graph was built
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metadata {
+    /// Version of the circom compiler that generated the circuit, if known
+    pub circom_version: Option<String>,
 }
 
 pub struct Graph {
-    pub nodes: Vec<Node>,
+    /// The prime of the field the graph is evaluated in
+    pub prime: Prime,
+    pub metadata: Metadata,
+    pub nodes: Nodes,
     pub signals: Vec<usize>,
     pub input_mapping: Vec<HashSignalInfo>,
+    /// Length of the inputs buffer, the constant 1 at index 0 up to the last
+    /// input signal
+    pub inputs_size: usize,
+}
+
+/// An input that a graph expects, see [`Graph::inputs`]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputInfo {
+    /// Qualified name, `p.x` for the field `x` of a bus input `p`
+    pub name: String,
+    /// Position of the first element in the inputs buffer
+    pub offset: usize,
+    /// Number of elements
+    pub length: usize,
+    /// Dimensions, empty for a single value. Arrays of graphs built before
+    /// shapes were recorded have a single dimension.
+    pub shape: Vec<usize>,
+}
+
+impl From<&HashSignalInfo> for InputInfo {
+    fn from(info: &HashSignalInfo) -> Self {
+        let length = info.signalsize as usize;
+        let shape = if info.shape.is_empty() && length != 1 {
+            vec![length]
+        } else {
+            info.shape.clone()
+        };
+        InputInfo {
+            name: info.name.clone(),
+            offset: info.signalid as usize,
+            length,
+            shape,
+        }
+    }
+}
+
+impl Graph {
+    /// The inputs of the graph in buffer order
+    pub fn inputs(&self) -> Vec<InputInfo> {
+        input_infos(&self.input_mapping)
+    }
+}
+
+pub(crate) fn input_infos(input_mapping: &[HashSignalInfo]) -> Vec<InputInfo> {
+    let mut inputs: Vec<InputInfo> = input_mapping.iter().map(InputInfo::from).collect();
+    inputs.sort_by_key(|input| input.offset);
+    inputs
 }
 
-fn fnv1a(s: &str) -> u64 {
+pub(crate) fn fnv1a(s: &str) -> u64 {
     let mut hash: u64 = 0xCBF29CE484222325;
     for c in s.bytes() {
         hash ^= c as u64;
@@ -41,33 +160,14 @@ fn fnv1a(s: &str) -> u64 {
     hash
 }
 
-/// Loads the graph from bytes
-pub fn init_graph(graph_bytes: &[u8]) -> eyre::Result<Graph> {
-    let (nodes, signals, input_mapping): (Vec<Node>, Vec<usize>, Vec<HashSignalInfo>) =
-        postcard::from_bytes(graph_bytes)?;
-
-    Ok(Graph {
-        nodes,
-        signals,
-        input_mapping,
-    })
+/// Loads the graph from bytes, see [`format`] for the accepted encodings
+pub fn init_graph(graph_bytes: &[u8]) -> Result<Graph, Error> {
+    format::decode(graph_bytes)
 }
 
-/// Calculates the number of needed inputs
+/// Number of elements of the inputs buffer
 pub fn get_inputs_size(graph: &Graph) -> usize {
-    let mut start = false;
-    let mut max_index = 0usize;
-    for node in graph.nodes.iter() {
-        if let Node::Input(i) = node {
-            if *i > max_index {
-                max_index = *i;
-            }
-            start = true
-        } else if start {
-            break;
-        }
-    }
-    max_index + 1
+    graph.inputs_size
 }
 
 /// Allocates inputs vec with position 0 set to 1
@@ -77,33 +177,136 @@ pub fn get_inputs_buffer(size: usize) -> Vec<U256> {
     inputs
 }
 
-/// Calculates the position of the given signal in the inputs buffer
-pub fn get_input_mapping(input_list: &Vec<String>, graph: &Graph) -> HashMap<String, usize> {
+/// A value of the input JSON, which nests objects for bus inputs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum InputValue {
+    Number(U256),
+    Array(Vec<InputValue>),
+    /// The fields of a bus
+    Bus(BTreeMap<String, InputValue>),
+}
+
+/// Flattens nested inputs into the qualified names of the input hash map.
+///
+/// Like circom, the fields of a bus `p` become `p.x`, and arrays of buses
+/// are indexed as `p[0].x`. Arrays of numbers stay under the name of the
+/// signal in row-major order.
+pub fn flatten_inputs(
+    inputs: impl IntoIterator<Item = (String, InputValue)>,
+) -> Result<HashMap<String, Vec<U256>>, Error> {
+    let mut flat = HashMap::new();
+    for (name, value) in inputs {
+        flatten_input(name, value, &mut flat)?;
+    }
+    Ok(flat)
+}
+
+fn flatten_input(
+    name: String,
+    value: InputValue,
+    flat: &mut HashMap<String, Vec<U256>>,
+) -> Result<(), Error> {
+    match value {
+        InputValue::Bus(fields) => {
+            for (field, value) in fields {
+                flatten_input(format!("{name}.{field}"), value, flat)?;
+            }
+        }
+        InputValue::Array(values) if contains_bus(&values) => {
+            for (i, value) in values.into_iter().enumerate() {
+                let name = format!("{name}[{i}]");
+                match value {
+                    InputValue::Number(_) => bail!("input {name} mixes numbers and buses"),
+                    value => flatten_input(name, value, flat)?,
+                }
+            }
+        }
+        value => {
+            let mut numbers = Vec::new();
+            push_numbers(value, &mut numbers);
+            flat.insert(name, numbers);
+        }
+    }
+    Ok(())
+}
+
+fn contains_bus(values: &[InputValue]) -> bool {
+    values.iter().any(|value| match value {
+        InputValue::Number(_) => false,
+        InputValue::Array(values) => contains_bus(values),
+        InputValue::Bus(_) => true,
+    })
+}
+
+/// Appends the numbers of a value without buses in row-major order
+fn push_numbers(value: InputValue, numbers: &mut Vec<U256>) {
+    match value {
+        InputValue::Number(n) => numbers.push(n),
+        InputValue::Array(values) => values.into_iter().for_each(|v| push_numbers(v, numbers)),
+        InputValue::Bus(_) => unreachable!("buses are flattened by name"),
+    }
+}
+
+/// Looks up the position and length of the given signals in the inputs buffer
+pub fn get_input_mapping(
+    input_list: &Vec<String>,
+    graph: &Graph,
+) -> Result<HashMap<String, InputInfo>, Error> {
     let mut input_mapping = HashMap::new();
     for key in input_list {
-        let h = fnv1a(key);
-        let pos = graph
-            .input_mapping
-            .iter()
-            .position(|x| x.hash == h)
-            .unwrap();
-        let si = (graph.input_mapping[pos].signalid) as usize;
-        input_mapping.insert(key.to_string(), si);
+        let info = HashSignalInfo::find(&graph.input_mapping, key)?;
+        input_mapping.insert(key.clone(), InputInfo::from(info));
     }
-    input_mapping
+    Ok(input_mapping)
 }
 
 /// Sets all provided inputs given the mapping and inputs buffer
 pub fn populate_inputs(
     input_list: &HashMap<String, Vec<U256>>,
-    input_mapping: &HashMap<String, usize>,
+    input_mapping: &HashMap<String, InputInfo>,
     input_buffer: &mut [U256],
-) {
+) -> Result<(), Error> {
     for (key, value) in input_list {
-        let start = input_mapping[key];
-        let end = start + value.len();
-        input_buffer[start..end].copy_from_slice(value);
+        let info = input_mapping
+            .get(key)
+            .ok_or_else(|| err!("unknown input {key:?}"))?;
+        write_input(key, info.offset, info.length, value, input_buffer)?;
     }
+    Ok(())
+}
+
+/// Copies the `length` values of input `name` to `offset`, so that a value
+/// never ends up in another input
+pub(crate) fn write_input(
+    name: &str,
+    offset: usize,
+    length: usize,
+    value: &[U256],
+    input_buffer: &mut [U256],
+) -> Result<(), Error> {
+    ensure!(
+        value.len() == length,
+        "input {name:?} has {} values, expected {length}",
+        value.len()
+    );
+    let elements = offset
+        .checked_add(length)
+        .and_then(|end| input_buffer.get_mut(offset..end))
+        .ok_or_else(|| err!("input {name:?} is out of bounds of the inputs buffer"))?;
+    elements.copy_from_slice(value);
+    Ok(())
+}
+
+/// Allocates and populates the inputs buffer for the given inputs
+pub fn prepare_inputs(
+    input_list: &HashMap<String, Vec<U256>>,
+    graph: &Graph,
+) -> Result<Vec<U256>, Error> {
+    let mut inputs_buffer = get_inputs_buffer(get_inputs_size(graph));
+    let input_mapping = get_input_mapping(&input_list.keys().cloned().collect(), graph)?;
+    populate_inputs(input_list, &input_mapping, &mut inputs_buffer)?;
+    Ok(inputs_buffer)
 }
 
 /// Calculate witness based on serialized graph and inputs
@@ -111,9 +314,21 @@ pub fn calculate_witness(
     input_list: HashMap<String, Vec<U256>>,
     graph: &Graph,
     bbfs: Option<&HashMap<String, BlackBoxFunction>>,
-) -> eyre::Result<Vec<U256>> {
-    let mut inputs_buffer = get_inputs_buffer(get_inputs_size(graph));
-    let input_mapping = get_input_mapping(&input_list.keys().cloned().collect(), graph);
-    populate_inputs(&input_list, &input_mapping, &mut inputs_buffer);
+) -> Result<Vec<U256>, Error> {
+    calculate_witness_in::<Fr>(input_list, graph, bbfs)
+}
+
+/// Calculates the witness of a graph built for the prime of `F`
+pub fn calculate_witness_in<F: GraphField>(
+    input_list: HashMap<String, Vec<U256>>,
+    graph: &Graph,
+    bbfs: Option<&HashMap<String, BlackBoxFunction<F>>>,
+) -> Result<Vec<U256>, Error> {
+    ensure!(
+        Prime::of::<F>() == Some(graph.prime),
+        "the graph is built for {}, it cannot be evaluated in a different field",
+        graph.prime
+    );
+    let inputs_buffer = prepare_inputs(&input_list, graph)?;
     graph::evaluate(&graph.nodes, &inputs_buffer, &graph.signals, bbfs)
 }
@@ -0,0 +1,325 @@
+//! Compact structure-of-arrays storage for graph nodes.
+//!
+//! Every node takes a one byte [`Opcode`] and two `u32` operands. Constants
+//! live in a shared pool and black box calls in a side table, so the node
+//! arrays stay small and cache friendly even for multi-million node graphs.
+//! [`Node`] values are produced on demand for inspection.
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt;
+
+use ruint::aliases::U256;
+use serde::{
+    de::{Error as _, SeqAccess, Visitor},
+    ser::SerializeSeq,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    graph::{Node, Operation},
+    prime::GraphField,
+    HashMap,
+};
+
+/// Node kind and operation, stored in a single byte.
+///
+/// The operands `a` and `b` of a node are interpreted depending on the opcode:
+/// the input index for [`Opcode::Input`], the constant pool index for
+/// constants, the black box table index for [`Opcode::BBF`] and the node
+/// indices for [`Opcode::Op`].
+#[derive(Hash, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Opcode {
+    Input,
+    Constant,
+    MontConstant,
+    BBF,
+    Op(Operation),
+}
+
+const _: () = assert!(core::mem::size_of::<Opcode>() == 1);
+
+#[derive(Debug, Clone, Default)]
+struct BlackBoxCall {
+    name: String,
+    params: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Nodes {
+    opcodes: Vec<Opcode>,
+    a: Vec<u32>,
+    b: Vec<u32>,
+    constants: Vec<U256>,
+    bbfs: Vec<BlackBoxCall>,
+}
+
+fn index(i: usize) -> u32 {
+    u32::try_from(i).expect("graph exceeds u32 indices")
+}
+
+/// The largest index stored by `node`, [`Nodes::push`] requires it to fit in
+/// `u32`
+fn max_index(node: &Node) -> usize {
+    match node {
+        Node::Input(i) => *i,
+        Node::Op(_, a, b) => *a.max(b),
+        Node::BBF(_, params) => params.iter().copied().max().unwrap_or(0),
+        Node::Constant(_) | Node::MontConstant(_) => 0,
+    }
+}
+
+impl Nodes {
+    pub const fn new() -> Self {
+        Nodes {
+            opcodes: Vec::new(),
+            a: Vec::new(),
+            b: Vec::new(),
+            constants: Vec::new(),
+            bbfs: Vec::new(),
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Nodes {
+            opcodes: Vec::with_capacity(capacity),
+            a: Vec::with_capacity(capacity),
+            b: Vec::with_capacity(capacity),
+            constants: Vec::new(),
+            bbfs: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.opcodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.opcodes.is_empty()
+    }
+
+    /// Appends a node and returns its index
+    pub fn push(&mut self, node: Node) -> usize {
+        let (opcode, a, b) = self.encode(node);
+        self.opcodes.push(opcode);
+        self.a.push(a);
+        self.b.push(b);
+        self.len() - 1
+    }
+
+    /// Replaces the node at index `i`
+    pub fn set(&mut self, i: usize, node: Node) {
+        let (opcode, a, b) = self.encode(node);
+        self.opcodes[i] = opcode;
+        self.a[i] = a;
+        self.b[i] = b;
+    }
+
+    fn encode(&mut self, node: Node) -> (Opcode, u32, u32) {
+        match node {
+            Node::Input(i) => (Opcode::Input, index(i), 0),
+            Node::Constant(c) => (Opcode::Constant, self.push_constant(c), 0),
+            Node::MontConstant(c) => (Opcode::MontConstant, self.push_constant(c), 0),
+            Node::Op(op, a, b) => (Opcode::Op(op), index(a), index(b)),
+            Node::BBF(name, params) => {
+                let params = params.into_iter().map(index).collect();
+                self.bbfs.push(BlackBoxCall { name, params });
+                (Opcode::BBF, index(self.bbfs.len() - 1), 0)
+            }
+        }
+    }
+
+    fn push_constant(&mut self, c: U256) -> u32 {
+        self.constants.push(c);
+        index(self.constants.len() - 1)
+    }
+
+    /// Decodes the node at index `i`
+    pub fn get(&self, i: usize) -> Node {
+        match self.opcodes[i] {
+            Opcode::Input => Node::Input(self.a[i] as usize),
+            Opcode::Constant => Node::Constant(self.constant(i)),
+            Opcode::MontConstant => Node::MontConstant(self.constant(i)),
+            Opcode::Op(op) => Node::Op(op, self.a[i] as usize, self.b[i] as usize),
+            Opcode::BBF => Node::BBF(
+                self.name(i).to_string(),
+                self.params(i).iter().map(|&p| p as usize).collect(),
+            ),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Node> + '_ {
+        (0..self.len()).map(|i| self.get(i))
+    }
+
+    #[inline]
+    pub fn opcode(&self, i: usize) -> Opcode {
+        self.opcodes[i]
+    }
+
+    /// Raw operands of the node at index `i`
+    #[inline]
+    pub fn operands(&self, i: usize) -> (usize, usize) {
+        (self.a[i] as usize, self.b[i] as usize)
+    }
+
+    /// Value of a constant node, in Montgomery form for [`Opcode::MontConstant`]
+    #[inline]
+    pub fn constant(&self, i: usize) -> U256 {
+        self.constants[self.a[i] as usize]
+    }
+
+    #[inline]
+    pub fn mont_constant<F: GraphField>(&self, i: usize) -> F {
+        F::from_montgomery(self.constant(i))
+    }
+
+    /// Name of a black box function node
+    pub fn name(&self, i: usize) -> &str {
+        &self.bbfs[self.a[i] as usize].name
+    }
+
+    /// Parameters of a black box function node
+    pub fn params(&self, i: usize) -> &[u32] {
+        &self.bbfs[self.a[i] as usize].params
+    }
+
+    /// Calls `f` with every node index referenced by node `i`
+    #[inline]
+    pub fn for_each_dependency(&self, i: usize, mut f: impl FnMut(usize)) {
+        match self.opcodes[i] {
+            Opcode::Op(_) => {
+                f(self.a[i] as usize);
+                f(self.b[i] as usize);
+            }
+            Opcode::BBF => self.params(i).iter().for_each(|&p| f(p as usize)),
+            _ => (),
+        }
+    }
+
+    /// Rewrites all node references through `renumber`
+    pub fn renumber(&mut self, renumber: impl Fn(usize) -> usize) {
+        for i in 0..self.len() {
+            match self.opcodes[i] {
+                Opcode::Op(_) => {
+                    self.a[i] = index(renumber(self.a[i] as usize));
+                    self.b[i] = index(renumber(self.b[i] as usize));
+                }
+                Opcode::BBF => {
+                    for p in self.bbfs[self.a[i] as usize].params.iter_mut() {
+                        *p = index(renumber(*p as usize));
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Keeps the nodes marked in `keep` and drops unreferenced constants and
+    /// black box calls. References are not renumbered.
+    pub fn retain(&mut self, keep: &[bool]) {
+        assert_eq!(keep.len(), self.len());
+        let mut constants = Vec::new();
+        let mut constant_map = HashMap::new();
+        let mut bbfs = Vec::new();
+        let mut j = 0;
+        for (i, &keep) in keep.iter().enumerate() {
+            if !keep {
+                continue;
+            }
+            self.opcodes[j] = self.opcodes[i];
+            self.a[j] = match self.opcodes[i] {
+                Opcode::Constant | Opcode::MontConstant => {
+                    let c = self.constant(i);
+                    *constant_map.entry(c).or_insert_with(|| {
+                        constants.push(c);
+                        index(constants.len() - 1)
+                    })
+                }
+                Opcode::BBF => {
+                    bbfs.push(core::mem::take(&mut self.bbfs[self.a[i] as usize]));
+                    index(bbfs.len() - 1)
+                }
+                _ => self.a[i],
+            };
+            self.b[j] = self.b[i];
+            j += 1;
+        }
+        self.opcodes.truncate(j);
+        self.a.truncate(j);
+        self.b.truncate(j);
+        self.constants = constants;
+        self.bbfs = bbfs;
+    }
+
+    /// Drops unreferenced constants and releases excess capacity
+    pub fn shrink_to_fit(&mut self) {
+        self.retain(&vec![true; self.len()]);
+        self.opcodes.shrink_to_fit();
+        self.a.shrink_to_fit();
+        self.b.shrink_to_fit();
+    }
+}
+
+impl From<Vec<Node>> for Nodes {
+    fn from(nodes: Vec<Node>) -> Self {
+        nodes.into_iter().collect()
+    }
+}
+
+impl FromIterator<Node> for Nodes {
+    fn from_iter<T: IntoIterator<Item = Node>>(iter: T) -> Self {
+        let iter = iter.into_iter();
+        let mut nodes = Nodes::with_capacity(iter.size_hint().0);
+        for node in iter {
+            nodes.push(node);
+        }
+        nodes
+    }
+}
+
+/// Serialized as a sequence of [`Node`], the same as `Vec<Node>`.
+impl Serialize for Nodes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for node in self.iter() {
+            seq.serialize_element(&node)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Nodes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NodesVisitor;
+
+        impl<'de> Visitor<'de> for NodesVisitor {
+            type Value = Nodes;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a sequence of nodes")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Nodes, A::Error> {
+                let mut nodes = Nodes::with_capacity(seq.size_hint().unwrap_or(0).min(1 << 20));
+                while let Some(node) = seq.next_element::<Node>()? {
+                    // Pool and table indices are bounded by the number of nodes.
+                    let i = max_index(&node).max(nodes.len());
+                    if u32::try_from(i).is_err() {
+                        return Err(A::Error::custom(format_args!(
+                            "node {}: index {i} exceeds u32",
+                            nodes.len()
+                        )));
+                    }
+                    nodes.push(node);
+                }
+                Ok(nodes)
+            }
+        }
+
+        deserializer.deserialize_seq(NodesVisitor)
+    }
+}
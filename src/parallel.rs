@@ -0,0 +1,152 @@
+//! Parallel evaluation of a single witness.
+//!
+//! All references in a graph point backwards, so every node can be assigned
+//! a level one above the highest level of its dependencies. Nodes within a
+//! level are independent of each other. [`ParallelGraph`] reorders the nodes
+//! by level so that every level is a contiguous range and evaluates the
+//! levels one after another, splitting wide levels into chunks that run on
+//! the rayon thread pool. Consecutive narrow levels are merged into one
+//! sequential step that keeps the order of the graph, so that narrow graphs
+//! keep the locality of the sequential interpreter. Run [`ParallelGraph::evaluate`] inside
+//! `ThreadPool::install` to use a dedicated pool. Only BN254 graphs are
+//! supported.
+
+use ark_bn254::Fr;
+use ark_ff::AdditiveGroup;
+use rayon::prelude::*;
+use ruint::aliases::U256;
+
+use crate::{
+    graph::{assert_valid, evaluate_node, Node},
+    nodes::Nodes,
+    prime::Prime,
+    BlackBoxFunction, Graph, HashMap,
+};
+
+/// Levels with fewer than two chunks of nodes are evaluated sequentially.
+const MIN_CHUNK: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct ParallelGraph {
+    nodes: Nodes,
+    /// Start index of every step, followed by the number of nodes. A step
+    /// is a wide level or a run of narrow levels.
+    steps: Vec<usize>,
+    /// Whether the nodes of each step are evaluated in parallel
+    wide: Vec<bool>,
+    outputs: Vec<usize>,
+}
+
+impl ParallelGraph {
+    /// Orders the nodes of the graph by dependency level
+    pub fn new(graph: &Graph) -> eyre::Result<Self> {
+        let prime = graph.prime;
+        eyre::ensure!(
+            prime == Prime::Bn128,
+            "parallel evaluation is not supported for {prime}"
+        );
+        let (nodes, outputs) = (&graph.nodes, &graph.signals);
+        assert_valid(nodes);
+        let n = nodes.len();
+
+        let mut level = vec![0usize; n];
+        let mut counts = vec![];
+        for i in 0..n {
+            let mut l = 0;
+            nodes.for_each_dependency(i, |j| l = l.max(level[j] + 1));
+            level[i] = l;
+            if l == counts.len() {
+                counts.push(0);
+            }
+            counts[l] += 1;
+        }
+
+        // Merge runs of narrow levels into one step.
+        let mut step_of_level = Vec::with_capacity(counts.len());
+        let mut step_counts: Vec<usize> = vec![];
+        let mut wide = vec![];
+        for count in counts {
+            let is_wide = count >= 2 * MIN_CHUNK;
+            if is_wide || wide.last() != Some(&false) {
+                step_counts.push(0);
+                wide.push(is_wide);
+            }
+            step_of_level.push(step_counts.len() - 1);
+            *step_counts.last_mut().unwrap() += count;
+        }
+
+        // Stable counting sort by step.
+        let mut steps = Vec::with_capacity(step_counts.len() + 1);
+        let mut start = 0;
+        for count in step_counts {
+            steps.push(start);
+            start += count;
+        }
+        steps.push(n);
+        let mut next = steps.clone();
+        let mut renumber = vec![0; n];
+        for i in 0..n {
+            let step = step_of_level[level[i]];
+            renumber[i] = next[step];
+            next[step] += 1;
+        }
+        let mut order = vec![0; n];
+        for i in 0..n {
+            order[renumber[i]] = i;
+        }
+
+        let mut sorted = Nodes::with_capacity(n);
+        for &i in order.iter() {
+            sorted.push(match nodes.get(i) {
+                Node::Op(op, a, b) => Node::Op(op, renumber[a], renumber[b]),
+                Node::BBF(name, params) => {
+                    Node::BBF(name, params.into_iter().map(|p| renumber[p]).collect())
+                }
+                node => node,
+            });
+        }
+
+        eprintln!(
+            "Scheduled {n} nodes in {} levels, {} of them parallel",
+            step_of_level.len(),
+            wide.iter().filter(|&&w| w).count()
+        );
+        Ok(ParallelGraph {
+            nodes: sorted,
+            steps,
+            wide,
+            outputs: outputs.iter().map(|&i| renumber[i]).collect(),
+        })
+    }
+
+    /// Evaluates the graph, giving the same result as [`crate::graph::evaluate`]
+    pub fn evaluate(
+        &self,
+        inputs: &[U256],
+        bbfs: Option<&HashMap<String, BlackBoxFunction>>,
+    ) -> eyre::Result<Vec<U256>> {
+        let mut values = vec![Fr::ZERO; self.nodes.len()];
+        for (step, &wide) in self.steps.windows(2).zip(&self.wide) {
+            let (start, end) = (step[0], step[1]);
+            if !wide {
+                // The nodes of merged levels depend on each other.
+                for i in start..end {
+                    values[i] = evaluate_node(&self.nodes, i, &values, inputs, bbfs)?;
+                }
+                continue;
+            }
+            let (done, current) = values.split_at_mut(start);
+            current[..end - start]
+                .par_iter_mut()
+                .with_min_len(MIN_CHUNK)
+                .enumerate()
+                .try_for_each(|(k, value)| -> eyre::Result<()> {
+                    *value = evaluate_node(&self.nodes, start + k, done, inputs, bbfs)?;
+                    Ok(())
+                })?;
+        }
+
+        // Convert from Montgomery form and return the outputs.
+        Ok(self.outputs.iter().map(|&i| values[i].into()).collect())
+    }
+}
@@ -0,0 +1,227 @@
+//! Prime fields supported by circom.
+//!
+//! A graph records the [`Prime`] it was built for. Evaluation and the
+//! optimizer are generic over [`GraphField`], which is implemented for every
+//! arkworks field with four 64 bit Montgomery limbs, including the types
+//! defined here and `ark_bn254::Fr`.
+
+use core::{fmt, str::FromStr};
+
+use ark_ff::{BigInt, Fp256, MontBackend, MontConfig, MontFp, PrimeField};
+use ruint::{aliases::U256, uint};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{err, Error};
+
+/// A prime accepted by `circom --prime`
+#[derive(Hash, PartialEq, Eq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum Prime {
+    #[default]
+    Bn128,
+    Bls12381,
+    Goldilocks,
+    Grumpkin,
+    Pallas,
+    Vesta,
+    Secq256r1,
+}
+
+impl Prime {
+    /// All primes, indexed by their `u8` discriminant
+    pub const ALL: [Prime; 7] = {
+        use Prime::*;
+        [
+            Bn128, Bls12381, Goldilocks, Grumpkin, Pallas, Vesta, Secq256r1,
+        ]
+    };
+
+    /// Name of the prime on the circom command line
+    pub const fn name(self) -> &'static str {
+        match self {
+            Prime::Bn128 => "bn128",
+            Prime::Bls12381 => "bls12381",
+            Prime::Goldilocks => "goldilocks",
+            Prime::Grumpkin => "grumpkin",
+            Prime::Pallas => "pallas",
+            Prime::Vesta => "vesta",
+            Prime::Secq256r1 => "secq256r1",
+        }
+    }
+
+    pub const fn modulus(self) -> U256 {
+        match self {
+            Prime::Bn128 => crate::M,
+            Prime::Bls12381 => uint!(
+                52435875175126190479447740508185965837690552500527637822603658699938581184513_U256
+            ),
+            Prime::Goldilocks => uint!(18446744069414584321_U256),
+            Prime::Grumpkin => uint!(
+                21888242871839275222246405745257275088696311157297823662689037894645226208583_U256
+            ),
+            Prime::Pallas => uint!(
+                28948022309329048855892746252171976963363056481941560715954676764349967630337_U256
+            ),
+            Prime::Vesta => uint!(
+                28948022309329048855892746252171976963363056481941647379679742748393362948097_U256
+            ),
+            Prime::Secq256r1 => uint!(
+                115792089210356248762697446949407573530086143415290314195533631308867097853951_U256
+            ),
+        }
+    }
+
+    /// The prime of the field `F`, if it is supported
+    pub fn of<F: GraphField>() -> Option<Prime> {
+        Prime::ALL.into_iter().find(|p| p.modulus() == F::modulus())
+    }
+
+    /// Converts the canonical value `c` to the Montgomery form of this field
+    pub fn to_montgomery(self, c: U256) -> U256 {
+        with_field!(self, F => F::from_u256(c).montgomery())
+    }
+}
+
+impl fmt::Display for Prime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Prime {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Prime::ALL
+            .into_iter()
+            .find(|p| p.name() == s)
+            .ok_or_else(|| err!("unknown prime {s:?}"))
+    }
+}
+
+impl TryFrom<u8> for Prime {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Prime::ALL.get(value as usize).copied().ok_or(value)
+    }
+}
+
+/// A field that graphs can be evaluated in
+pub trait GraphField: PrimeField + Into<U256> {
+    fn modulus() -> U256;
+
+    /// The element for `value`, inputs that are not reduced are taken as is
+    /// like `Fp::new` does.
+    fn from_u256(value: U256) -> Self;
+
+    /// The element with the Montgomery representation `raw`
+    fn from_montgomery(raw: U256) -> Self;
+
+    /// The Montgomery representation of the element
+    fn montgomery(self) -> U256;
+}
+
+impl<P: MontConfig<4>> GraphField for Fp256<MontBackend<P, 4>> {
+    #[inline]
+    fn modulus() -> U256 {
+        U256::from_limbs(P::MODULUS.0)
+    }
+
+    #[inline]
+    fn from_u256(value: U256) -> Self {
+        Self::new(BigInt(value.into_limbs()))
+    }
+
+    #[inline]
+    fn from_montgomery(raw: U256) -> Self {
+        Self::new_unchecked(BigInt(raw.into_limbs()))
+    }
+
+    #[inline]
+    fn montgomery(self) -> U256 {
+        U256::from_limbs(self.0 .0)
+    }
+}
+
+/// Evaluates `$body` with the type `$F` bound to the field of `$prime`
+macro_rules! with_field {
+    ($prime:expr, $F:ident => $body:expr) => {{
+        use $crate::prime::*;
+        match $prime {
+            Prime::Bn128 => {
+                type $F = Bn128Fr;
+                $body
+            }
+            Prime::Bls12381 => {
+                type $F = Bls12381Fr;
+                $body
+            }
+            Prime::Goldilocks => {
+                type $F = GoldilocksFr;
+                $body
+            }
+            Prime::Grumpkin => {
+                type $F = GrumpkinFr;
+                $body
+            }
+            Prime::Pallas => {
+                type $F = PallasFr;
+                $body
+            }
+            Prime::Vesta => {
+                type $F = VestaFr;
+                $body
+            }
+            Prime::Secq256r1 => {
+                type $F = Secq256r1Fr;
+                $body
+            }
+        }
+    }};
+}
+
+pub(crate) use with_field;
+
+pub type Bn128Fr = ark_bn254::Fr;
+
+#[derive(MontConfig)]
+#[modulus = "52435875175126190479447740508185965837690552500527637822603658699938581184513"]
+#[generator = "7"]
+pub struct Bls12381Config;
+pub type Bls12381Fr = Fp256<MontBackend<Bls12381Config, 4>>;
+
+/// Goldilocks is implemented by hand, the derive macro would pick a single
+/// limb for the 64 bit modulus.
+pub struct GoldilocksConfig;
+pub type GoldilocksFr = Fp256<MontBackend<GoldilocksConfig, 4>>;
+
+impl MontConfig<4> for GoldilocksConfig {
+    const MODULUS: BigInt<4> = BigInt([0xffffffff00000001, 0, 0, 0]);
+    const GENERATOR: GoldilocksFr = MontFp!("7");
+    const TWO_ADIC_ROOT_OF_UNITY: GoldilocksFr = MontFp!("1753635133440165772");
+}
+
+#[derive(MontConfig)]
+#[modulus = "21888242871839275222246405745257275088696311157297823662689037894645226208583"]
+#[generator = "3"]
+pub struct GrumpkinConfig;
+pub type GrumpkinFr = Fp256<MontBackend<GrumpkinConfig, 4>>;
+
+#[derive(MontConfig)]
+#[modulus = "28948022309329048855892746252171976963363056481941560715954676764349967630337"]
+#[generator = "5"]
+pub struct PallasConfig;
+pub type PallasFr = Fp256<MontBackend<PallasConfig, 4>>;
+
+#[derive(MontConfig)]
+#[modulus = "28948022309329048855892746252171976963363056481941647379679742748393362948097"]
+#[generator = "5"]
+pub struct VestaConfig;
+pub type VestaFr = Fp256<MontBackend<VestaConfig, 4>>;
+
+#[derive(MontConfig)]
+#[modulus = "115792089210356248762697446949407573530086143415290314195533631308867097853951"]
+#[generator = "6"]
+pub struct Secq256r1Config;
+pub type Secq256r1Fr = Fp256<MontBackend<Secq256r1Config, 4>>;
@@ -0,0 +1,152 @@
+use ark_bn254::Fr;
+use circom_witness_rs::{
+    bytecode::{self, Instruction, Program},
+    graph::{self, Node, Operation},
+    nodes::Nodes,
+    prime::Prime,
+    Graph, M,
+};
+use rand::Rng;
+use ruint::aliases::U256;
+
+/// Graph of inputs 1 to 3, the signals are the nodes returned by `build`
+fn three_inputs(build: impl FnOnce(&mut Nodes, [usize; 3]) -> Vec<usize>) -> Graph {
+    let mut nodes = Nodes::new();
+    let inputs = [1, 2, 3].map(|i| nodes.push(Node::Input(i)));
+    let signals = build(&mut nodes, inputs);
+    Graph {
+        prime: Prime::Bn128,
+        metadata: Default::default(),
+        nodes,
+        signals,
+        input_mapping: vec![],
+        inputs_size: 4,
+    }
+}
+
+/// Lowers the graph and checks the program against the graph for random
+/// inputs
+fn lower(graph: &Graph) -> Program {
+    let program = bytecode::lower(graph).unwrap();
+    let mut rng = rand::thread_rng();
+    for _ in 0..8 {
+        let inputs = [0; 4].map(|_| rng.gen::<U256>() % M);
+        let expected = graph::evaluate::<Fr>(&graph.nodes, &inputs, &graph.signals, None).unwrap();
+        assert_eq!(program.evaluate(&inputs, None).unwrap(), expected);
+    }
+    program
+}
+
+fn mul(nodes: &mut Nodes, a: usize, b: usize) -> usize {
+    nodes.push(Node::Op(Operation::Mul, a, b))
+}
+
+#[test]
+fn mul_add() {
+    let graph = three_inputs(|nodes, [x, y, z]| {
+        let product = mul(nodes, x, y);
+        vec![
+            nodes.push(Node::Op(Operation::Add, z, product)),
+            // The product of `y` and `z` is used twice and not fused.
+            {
+                let product = mul(nodes, y, z);
+                let sum = nodes.push(Node::Op(Operation::Add, product, x));
+                mul(nodes, sum, product)
+            },
+        ]
+    });
+    let program = lower(&graph);
+    assert!(matches!(
+        program.instructions()[3..],
+        [
+            Instruction::MulAdd(..),
+            Instruction::Mul(..),
+            Instruction::Add(..),
+            Instruction::Mul(..)
+        ]
+    ));
+}
+
+#[test]
+fn pow5() {
+    let graph = three_inputs(|nodes, [x, ..]| {
+        let x2 = mul(nodes, x, x);
+        let x4 = mul(nodes, x2, x2);
+        vec![mul(nodes, x4, x)]
+    });
+    let program = lower(&graph);
+    assert!(matches!(
+        program.instructions()[3..],
+        [Instruction::Pow5(_)]
+    ));
+}
+
+#[test]
+fn pow5_with_other_uses_of_the_square() {
+    // x^2 is an output too, so it is computed and x^5 is not fused.
+    let graph = three_inputs(|nodes, [x, ..]| {
+        let x2 = mul(nodes, x, x);
+        let x4 = mul(nodes, x2, x2);
+        vec![mul(nodes, x4, x), x2]
+    });
+    let program = lower(&graph);
+    assert!(matches!(
+        program.instructions()[3..],
+        [
+            Instruction::Square(_),
+            Instruction::Square(_),
+            Instruction::Mul(..)
+        ]
+    ));
+
+    // The same for a use by another node.
+    let graph = three_inputs(|nodes, [x, y, _]| {
+        let x2 = mul(nodes, x, x);
+        let x4 = mul(nodes, x2, x2);
+        vec![mul(nodes, x4, x), mul(nodes, x2, y)]
+    });
+    let program = lower(&graph);
+    assert!(!program
+        .instructions()
+        .iter()
+        .any(|i| matches!(i, Instruction::Pow5(_))));
+}
+
+#[test]
+fn sub_constant() {
+    let graph = three_inputs(|nodes, [x, ..]| {
+        let c = nodes.push(Node::Constant(U256::from(5)));
+        vec![nodes.push(Node::Op(Operation::Sub, x, c))]
+    });
+    let program = lower(&graph);
+    assert!(matches!(
+        program.instructions()[3..],
+        [Instruction::AddConst(..)]
+    ));
+}
+
+#[test]
+fn registers_stay_bounded() {
+    // A long MiMC style chain, every value is dead after the next round.
+    let chain = |rounds: usize| {
+        three_inputs(|nodes, [x, k, _]| {
+            let mut acc = x;
+            for round in 0..rounds {
+                let c = nodes.push(Node::Constant(U256::from(round + 1)));
+                let t = nodes.push(Node::Op(Operation::Add, acc, k));
+                let t = nodes.push(Node::Op(Operation::Add, t, c));
+                let t2 = mul(nodes, t, t);
+                let t4 = mul(nodes, t2, t2);
+                acc = mul(nodes, t4, t);
+            }
+            vec![acc]
+        })
+    };
+    let short = lower(&chain(10)).registers();
+    for rounds in [100, 1000] {
+        let graph = chain(rounds);
+        let program = lower(&graph);
+        assert_eq!(program.registers(), short);
+        assert!(program.registers() * 100 < graph.nodes.len());
+    }
+}
@@ -0,0 +1,298 @@
+mod common;
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use ark_bn254::Fr;
+use ark_ff::{BigInt, PrimeField};
+use circom_witness_rs::{
+    codegen,
+    graph::{self, Node, Operation},
+    nodes::Nodes,
+    prime::{
+        Bls12381Fr, GoldilocksFr, GraphField, GrumpkinFr, PallasFr, Prime, Secq256r1Fr, VestaFr,
+    },
+    Graph,
+};
+use common::{bbfs, graph, random_inputs};
+use rand::Rng;
+use ruint::aliases::U256;
+
+/// [`codegen::rust`] of [`graph`], checked by [`rust_is_up_to_date`]
+#[allow(clippy::all)]
+mod generated {
+    include!("fixtures/witness.rs");
+}
+
+/// Black box function `f` of the generated code, returns its first argument
+struct BlackBox;
+
+impl generated::BlackBox for BlackBox {
+    fn f(&self, args: &[Fr]) -> Fr {
+        args[0]
+    }
+}
+
+#[test]
+fn rust_is_up_to_date() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/witness.rs");
+    let source = codegen::rust(&graph()).unwrap();
+    if std::env::var_os("BLESS").is_some() {
+        fs::write(&path, &source).unwrap();
+    } else {
+        let committed = fs::read_to_string(&path).unwrap();
+        assert!(
+            committed == source,
+            "{} is out of date, run the tests with BLESS=1",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn rust_matches_graph_evaluation() {
+    let (graph, bbfs) = (graph(), bbfs());
+    for round in 0..32 {
+        let inputs = random_inputs(round);
+        let expected =
+            graph::evaluate::<Fr>(&graph.nodes, &inputs, &graph.signals, Some(&bbfs)).unwrap();
+        let inputs: Vec<Fr> = inputs
+            .iter()
+            .map(|&i| Fr::from_bigint(BigInt::from(i)).unwrap())
+            .collect();
+        let outputs: Vec<U256> = generated::witness(&inputs, &BlackBox)
+            .into_iter()
+            .map(|v| U256::from_limbs(v.into_bigint().0))
+            .collect();
+        assert_eq!(outputs, expected, "inputs {inputs:?}");
+    }
+}
+
+/// Nodes with input 1 and a call of the black box function `name` on it
+fn call(name: &str) -> Graph {
+    let mut nodes = Nodes::new();
+    let x = nodes.push(Node::Input(1));
+    let call = nodes.push(Node::BBF(name.to_string(), vec![x]));
+    Graph {
+        nodes,
+        signals: vec![call],
+        ..graph()
+    }
+}
+
+#[test]
+fn rust_imports_only_what_is_used() {
+    let source = codegen::rust(&call("f_0")).unwrap();
+    assert!(!source.contains("BigInt"));
+    assert!(!source.contains("BigUint"));
+}
+
+#[test]
+fn rust_escapes_keywords() {
+    let source = codegen::rust(&call("match_0")).unwrap();
+    assert!(source.contains("fn r#match(&self, args: &[Fr]) -> Fr;"));
+    assert!(source.contains("bbfs.r#match(&[v0])"));
+
+    for name in [
+        "self_0", "Self_1", "super_0", "crate_0", "__0", "1f_0", "f-g_0",
+    ] {
+        let error = codegen::rust(&call(name)).unwrap_err();
+        assert!(
+            error.to_string().ends_with("is not a valid identifier"),
+            "{name}: {error}"
+        );
+    }
+}
+
+/// Reads the inputs from stdin and writes the outputs to stdout, the black
+/// box function `f` returns its first argument.
+const DRIVER: &str = r#"
+#include <stdio.h>
+#include <string.h>
+
+#include "witness.h"
+
+void witness_bbf_f(const uint8_t *args, size_t nargs, uint8_t *out) {
+    (void)nargs;
+    memcpy(out, args, 32);
+}
+
+int main(void) {
+    uint8_t inputs[WITNESS_NUM_INPUTS * 32], out[WITNESS_NUM_OUTPUTS * 32];
+    if (fread(inputs, 32, WITNESS_NUM_INPUTS, stdin) != WITNESS_NUM_INPUTS) return 100;
+    int status = witness_calculate(inputs, out);
+    if (status != WITNESS_OK) return status;
+    fwrite(out, 32, WITNESS_NUM_OUTPUTS, stdout);
+    return 0;
+}
+"#;
+
+/// Compiles the generated library with the driver in directory `name`,
+/// `None` without `cc`
+fn compile(graph: &Graph, name: &str) -> Option<PathBuf> {
+    if Command::new("cc").arg("--version").output().is_err() {
+        eprintln!("cc not found, skipping");
+        return None;
+    }
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::create_dir_all(&dir).unwrap();
+    let library = codegen::c(graph, "witness").unwrap();
+    fs::write(dir.join("witness.h"), library.header).unwrap();
+    fs::write(dir.join("witness.c"), library.source).unwrap();
+    fs::write(dir.join("main.c"), DRIVER).unwrap();
+    let driver = dir.join("driver");
+    let status = Command::new("cc")
+        .args(["-std=c99", "-O1", "-Wall", "-Wextra", "-Werror", "-o"])
+        .arg(&driver)
+        .arg(dir.join("main.c"))
+        .arg(dir.join("witness.c"))
+        .status()
+        .unwrap();
+    assert!(status.success(), "compiling the generated C failed");
+    Some(driver)
+}
+
+/// Runs the driver, returns the outputs or the status of `witness_calculate`
+fn run(driver: &Path, inputs: &[U256]) -> Result<Vec<U256>, i32> {
+    let mut child = Command::new(driver)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let bytes: Vec<u8> = inputs.iter().flat_map(U256::to_le_bytes::<32>).collect();
+    child.stdin.take().unwrap().write_all(&bytes).unwrap();
+    let output = child.wait_with_output().unwrap();
+    match output.status.code() {
+        Some(0) => Ok(output
+            .stdout
+            .chunks(32)
+            .map(|chunk| U256::from_le_slice(chunk))
+            .collect()),
+        status => Err(status.unwrap()),
+    }
+}
+
+#[test]
+fn matches_graph_evaluation() {
+    let graph = graph();
+    let Some(driver) = compile(&graph, "codegen_evaluation") else {
+        return;
+    };
+    let bbfs = bbfs();
+    for round in 0..32 {
+        let inputs = random_inputs(round);
+        let expected =
+            graph::evaluate::<Fr>(&graph.nodes, &inputs, &graph.signals, Some(&bbfs)).unwrap();
+        assert_eq!(run(&driver, &inputs), Ok(expected), "inputs {inputs:?}");
+    }
+}
+
+#[test]
+fn division_by_zero() {
+    let graph = graph();
+    let Some(driver) = compile(&graph, "codegen_division_by_zero") else {
+        return;
+    };
+    // WITNESS_ERR_DIV_ZERO
+    let inputs = [U256::from(1), U256::from(5), U256::ZERO];
+    assert_eq!(run(&driver, &inputs), Err(2));
+}
+
+#[test]
+fn rust_rejects_other_primes() {
+    let graph = Graph {
+        prime: Prime::Goldilocks,
+        ..graph()
+    };
+    let error = codegen::rust(&graph).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Rust code generation is not supported for goldilocks"
+    );
+}
+
+/// Every operation of the C backend on inputs 1 and 2 and constants of the
+/// field of `F`, without black box functions
+fn graph_over<F: GraphField>(prime: Prime) -> Graph {
+    use Operation::*;
+    let mut nodes = Nodes::new();
+    let a = nodes.push(Node::Input(1));
+    let b = nodes.push(Node::Input(2));
+    let c = nodes.push(Node::Constant(F::modulus() - U256::from(7)));
+    let k = nodes.push(Node::MontConstant(F::from_u256(U256::from(9)).montgomery()));
+    let mut signals = vec![];
+    for op in [Add, Sub, Mul, Div, IDiv, Mod, Eq, Neg] {
+        signals.push(nodes.push(Node::Op(op, a, b)));
+    }
+    let same = nodes.push(Node::Op(Eq, a, a));
+    let add_const = nodes.push(Node::Op(Add, a, c));
+    let sub_const = nodes.push(Node::Op(Sub, b, c));
+    let mul_const = nodes.push(Node::Op(Mul, b, k));
+    let mul = nodes.push(Node::Op(Mul, add_const, b));
+    let mul_add = nodes.push(Node::Op(Add, mul, sub_const));
+    let a2 = nodes.push(Node::Op(Mul, a, a));
+    let a4 = nodes.push(Node::Op(Mul, a2, a2));
+    let a5 = nodes.push(Node::Op(Mul, a4, a));
+    signals.extend([same, mul_const, mul_add, a5, c, k]);
+    Graph {
+        prime,
+        metadata: Default::default(),
+        nodes,
+        signals,
+        input_mapping: vec![],
+        inputs_size: 3,
+    }
+}
+
+/// Runs the generated C for `prime` against the graph evaluated in `F`
+fn check_prime<F: GraphField>(prime: Prime) {
+    let graph = graph_over::<F>(prime);
+    let Some(driver) = compile(&graph, &format!("codegen_{prime}")) else {
+        return;
+    };
+    let mut rng = rand::thread_rng();
+    for round in 0..16 {
+        let mut random = || match round % 2 {
+            0 => rng.gen::<U256>() % F::modulus(),
+            _ => U256::from(rng.gen_range(1..1000u64)),
+        };
+        let inputs = [U256::from(1), random(), random()];
+        let expected = graph::evaluate::<F>(&graph.nodes, &inputs, &graph.signals, None).unwrap();
+        assert_eq!(run(&driver, &inputs), Ok(expected), "{prime} {inputs:?}");
+    }
+}
+
+#[test]
+fn c_supports_other_primes() {
+    check_prime::<Bls12381Fr>(Prime::Bls12381);
+    check_prime::<GoldilocksFr>(Prime::Goldilocks);
+    check_prime::<GrumpkinFr>(Prime::Grumpkin);
+    check_prime::<PallasFr>(Prime::Pallas);
+    check_prime::<VestaFr>(Prime::Vesta);
+    check_prime::<Secq256r1Fr>(Prime::Secq256r1);
+}
+
+#[test]
+fn c_inputs_follow_the_graph() {
+    // The inputs buffer of the graph, not the largest input that is read.
+    let wide = Graph {
+        inputs_size: 5,
+        ..graph()
+    };
+    let library = codegen::c(&wide, "witness").unwrap();
+    assert!(library.header.contains("#define WITNESS_NUM_INPUTS 5\n"));
+
+    let narrow = Graph {
+        inputs_size: 2,
+        ..graph()
+    };
+    let error = codegen::c(&narrow, "witness").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "input 2 is out of bounds of the 2 inputs"
+    );
+}
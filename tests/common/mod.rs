@@ -0,0 +1,70 @@
+//! Graph shared by the tests of the BN254 backends.
+
+use std::sync::Arc;
+
+use ark_bn254::Fr;
+use circom_witness_rs::{
+    graph::{Node, Operation},
+    nodes::Nodes,
+    prime::Prime,
+    BlackBoxFunction, Graph, HashMap, M,
+};
+use rand::Rng;
+use ruint::aliases::U256;
+
+/// Uses every operation that is implemented in Montgomery form on inputs 1
+/// and 2, a Montgomery constant, the black box function `f` and patterns
+/// for all fused bytecode instructions
+pub fn graph() -> Graph {
+    use Operation::*;
+    let mut nodes = Nodes::new();
+    let a = nodes.push(Node::Input(1));
+    let b = nodes.push(Node::Input(2));
+    let c = nodes.push(Node::Constant(U256::from(7)));
+    let k = nodes.push(Node::MontConstant(U256::from_limbs(Fr::from(9).0 .0)));
+    let div = nodes.push(Node::Op(Div, a, b));
+    let idiv = nodes.push(Node::Op(IDiv, a, b));
+    let rem = nodes.push(Node::Op(Mod, a, b));
+    let neg = nodes.push(Node::Op(Neg, a, a));
+    let eq = nodes.push(Node::Op(Eq, a, b));
+    let same = nodes.push(Node::Op(Eq, a, a));
+    let mul = nodes.push(Node::Op(Mul, a, b));
+    let mul_add = nodes.push(Node::Op(Add, mul, neg));
+    let a2 = nodes.push(Node::Op(Mul, a, a));
+    let a4 = nodes.push(Node::Op(Mul, a2, a2));
+    let a5 = nodes.push(Node::Op(Mul, a4, a));
+    let square = nodes.push(Node::Op(Mul, rem, rem));
+    let add_const = nodes.push(Node::Op(Add, a, c));
+    let mul_const = nodes.push(Node::Op(Mul, b, k));
+    let sub = nodes.push(Node::Op(Sub, div, idiv));
+    let bbf = nodes.push(Node::BBF("f_0".to_string(), vec![add_const, b]));
+    let signals = vec![
+        div, idiv, rem, neg, eq, same, mul_add, a5, square, mul_const, sub, bbf,
+    ];
+    Graph {
+        prime: Prime::Bn128,
+        metadata: Default::default(),
+        nodes,
+        signals,
+        input_mapping: vec![],
+        inputs_size: 3,
+    }
+}
+
+/// Black box function `f` of [`graph`], returns its first argument
+pub fn bbfs() -> HashMap<String, BlackBoxFunction> {
+    let mut bbfs: HashMap<String, BlackBoxFunction> = HashMap::new();
+    bbfs.insert("f".to_string(), Arc::new(|args: &[Fr]| args[0]));
+    bbfs
+}
+
+/// Random inputs of [`graph`], small ones in odd rounds to make
+/// integer division and modulo non-trivial
+pub fn random_inputs(round: usize) -> [U256; 3] {
+    let mut rng = rand::thread_rng();
+    let mut random = || match round % 2 {
+        0 => rng.gen::<U256>() % M,
+        _ => U256::from(rng.gen_range(1..1000u64)),
+    };
+    [U256::from(1), random(), random()]
+}
@@ -0,0 +1,170 @@
+use circom_witness_rs::{
+    dat::{DatFile, IOFieldDef, SectionCounts},
+    prime::Prime,
+    M,
+};
+use ruint::aliases::U256;
+
+/// Builds a `constants.dat` section by section
+#[derive(Default)]
+struct Dat {
+    bytes: Vec<u8>,
+    counts: SectionCounts,
+}
+
+impl Dat {
+    fn u32(&mut self, value: u32) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn u64(&mut self, value: u64) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn input(&mut self, hash: u64, signalid: u64, signalsize: u64) -> &mut Self {
+        self.counts.input_hash_map += 1;
+        self.u64(hash).u64(signalid).u64(signalsize)
+    }
+
+    fn witness(&mut self, signal: u64) -> &mut Self {
+        self.counts.witness += 1;
+        self.u64(signal)
+    }
+
+    fn short(&mut self, value: i32) -> &mut Self {
+        self.counts.constants += 1;
+        self.u32(value as u32).u32(0).u64(0).u64(0).u64(0).u64(0)
+    }
+
+    /// A long constant with the raw limbs `value`
+    fn long(&mut self, value: U256) -> &mut Self {
+        self.counts.constants += 1;
+        self.u32(0).u32(0x80000000);
+        self.bytes.extend_from_slice(&value.to_le_bytes::<32>());
+        self
+    }
+
+    /// An IO signal map with the single template `id` that has one signal
+    /// array of length 2
+    fn io_map(&mut self, id: u32) -> &mut Self {
+        self.counts.io_map += 1;
+        self.u32(id).u32(1).u32(0).u32(1).u32(2).u32(1).u32(0)
+    }
+
+    fn parse(&self) -> Result<DatFile, String> {
+        DatFile::parse(&self.bytes, self.counts, Prime::Bn128).map_err(|e| e.to_string())
+    }
+}
+
+/// Montgomery form of `value` in the generated code for BN254
+fn montgomery(value: u64) -> U256 {
+    let r = U256::from(2).pow_mod(U256::from(256), M);
+    U256::from(value).mul_mod(r, M)
+}
+
+/// One entry in each section with the signals 0 to 3
+fn dat() -> Dat {
+    let mut dat = Dat::default();
+    dat.counts.signals = 4;
+    dat.input(0xABCD, 1, 2)
+        .witness(0)
+        .witness(3)
+        .short(5)
+        .short(-1)
+        .long(montgomery(7))
+        .io_map(3);
+    dat
+}
+
+#[test]
+fn parses_all_sections() {
+    let dat = dat().parse().unwrap();
+    let input = &dat.input_hash_map[0];
+    assert_eq!(
+        (input.hash, input.signalid, input.signalsize),
+        (0xABCD, 1, 2)
+    );
+    assert_eq!(dat.witness_to_signal, [0, 3]);
+    assert_eq!(
+        dat.constants,
+        [U256::from(5), M - U256::from(1), U256::from(7)]
+    );
+    let field = IOFieldDef {
+        offset: 0,
+        lengths: vec![2],
+        size: 1,
+        bus_id: 0,
+    };
+    assert_eq!(dat.io_signals, [vec![], vec![], vec![], vec![field]]);
+    assert!(dat.bus_fields.is_empty());
+}
+
+#[test]
+fn rejects_truncated_sections() {
+    let dat = dat();
+    for len in 0..dat.bytes.len() {
+        let bytes = &dat.bytes[..len];
+        assert!(
+            DatFile::parse(bytes, dat.counts, Prime::Bn128).is_err(),
+            "{len}"
+        );
+    }
+
+    // The long part of the constant is cut off.
+    let error = DatFile::parse(&dat.bytes[..150], dat.counts, Prime::Bn128).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "constants.dat is truncated in the constants at offset 128, 32 bytes needed and 22 left"
+    );
+
+    // Counts larger than the file are truncation, not a huge allocation.
+    let mut dat = Dat::default();
+    dat.counts.witness = usize::MAX;
+    assert_eq!(
+        dat.parse().unwrap_err(),
+        "constants.dat is truncated in the witness to signal list at offset 0, 8 bytes needed and 0 left"
+    );
+}
+
+#[test]
+fn rejects_out_of_range_ids() {
+    let mut dat = Dat::default();
+    dat.io_map(u32::MAX);
+    assert_eq!(
+        dat.parse().unwrap_err(),
+        "id 4294967295 in the IO signal map of constants.dat is out of range"
+    );
+
+    let mut dat = Dat::default();
+    dat.counts.signals = 4;
+    dat.witness(4);
+    assert_eq!(
+        dat.parse().unwrap_err(),
+        "signal 4 at offset 0 of constants.dat is out of range of the 4 signals"
+    );
+
+    let mut dat = Dat::default();
+    dat.counts.signals = 4;
+    dat.input(0xABCD, 3, 2);
+    assert_eq!(
+        dat.parse().unwrap_err(),
+        "input at offset 0 of constants.dat is out of range of the 4 signals"
+    );
+
+    // Empty slots of the input hash map are not signals.
+    let mut dat = Dat::default();
+    dat.input(0, 0, 0);
+    assert!(dat.parse().is_ok());
+}
+
+#[test]
+fn rejects_unreduced_constants() {
+    let mut dat = Dat::default();
+    dat.short(1).long(M);
+    assert_eq!(
+        dat.parse().unwrap_err(),
+        "constant at offset 48 of constants.dat is not reduced"
+    );
+}
@@ -0,0 +1,45 @@
+// Generated by circom-witness-rs, do not edit.
+
+use ark_bn254::Fr;
+use ark_ff::BigInt;
+use num_bigint::BigUint;
+
+pub trait BlackBox {
+    fn f(&self, args: &[Fr]) -> Fr;
+}
+
+const C0: Fr = Fr::new(BigInt([0x0000000000000007, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000]));
+const C1: Fr = Fr::new_unchecked(BigInt([0x8983e9d6efffffd1, 0x9e785ab1a6f45554, 0x2943337e3940c6e5, 0x1d9598e8a7e39857]));
+
+fn idiv(a: Fr, b: Fr) -> Fr {
+    Fr::from(BigUint::from(a) / BigUint::from(b))
+}
+
+fn modulo(a: Fr, b: Fr) -> Fr {
+    Fr::from(BigUint::from(a) % BigUint::from(b))
+}
+
+#[allow(unused_variables)]
+pub fn witness(inputs: &[Fr], bbfs: &impl BlackBox) -> Vec<Fr> {
+    let v0 = inputs[1];
+    let v1 = inputs[2];
+    let v2 = C0;
+    let v3 = C1;
+    let v4 = v0 / v1;
+    let v5 = idiv(v0, v1);
+    let v6 = modulo(v0, v1);
+    let v7 = -v0;
+    let v8 = Fr::from(v0 == v1);
+    let v9 = Fr::from(v0 == v0);
+    let v10 = v0 * v1;
+    let v11 = v10 + v7;
+    let v12 = v0 * v0;
+    let v13 = v12 * v12;
+    let v14 = v13 * v0;
+    let v15 = v6 * v6;
+    let v16 = v0 + v2;
+    let v17 = v1 * v3;
+    let v18 = v4 - v5;
+    let v19 = bbfs.f(&[v16, v1]);
+    vec![v4, v5, v6, v7, v8, v9, v11, v14, v15, v17, v18, v19]
+}
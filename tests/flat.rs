@@ -0,0 +1,188 @@
+mod common;
+
+use ark_bn254::Fr;
+use circom_witness_rs::{
+    flat::{self, FlatGraph},
+    graph, Graph, HashMap, HashSignalInfo,
+};
+use common::{bbfs, random_inputs};
+use ruint::aliases::U256;
+
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xCBF29CE484222325, |hash, c| {
+        (hash ^ c as u64).wrapping_mul(0x100000001B3)
+    })
+}
+
+/// [`common::graph`] with the inputs `a` at 1 and `b` of shape `[1]` at 2
+fn graph() -> Graph {
+    let input = |name: &str, signalid, shape: Vec<usize>| HashSignalInfo {
+        hash: fnv1a(name),
+        signalid,
+        signalsize: 1,
+        name: name.to_string(),
+        shape,
+    };
+    Graph {
+        input_mapping: vec![input("a", 1, vec![]), input("b", 2, vec![1])],
+        ..common::graph()
+    }
+}
+
+/// Byte offset of every section after the header
+fn sections(bytes: &[u8]) -> [usize; 7] {
+    let sizes = [16, 32, 4, 4, 1, 4, 24];
+    let mut offsets = [0; 7];
+    let mut offset = 48;
+    for (i, size) in sizes.into_iter().enumerate() {
+        offsets[i] = offset;
+        offset = (offset + read_u32(bytes, 8 + 4 * i) as usize * size).next_multiple_of(8);
+    }
+    offsets
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn write_u32(bytes: &mut [u8], at: usize, value: u32) {
+    bytes[at..at + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+#[test]
+fn round_trip() {
+    let graph = graph();
+    let bytes = flat::encode(&graph).unwrap();
+    let flat = FlatGraph::from_bytes(&bytes).unwrap();
+    assert_eq!(flat.prime(), graph.prime);
+    assert_eq!(flat.len(), graph.nodes.len());
+    for i in 0..flat.len() {
+        assert_eq!(flat.node(i), graph.nodes.get(i));
+    }
+    assert_eq!(flat.signals().collect::<Vec<_>>(), graph.signals);
+    let fields = |info: &HashSignalInfo| {
+        let HashSignalInfo {
+            hash,
+            signalid,
+            signalsize,
+            name,
+            shape,
+        } = info.clone();
+        (hash, signalid, signalsize, name, shape)
+    };
+    assert!(flat
+        .input_mapping()
+        .map(|info| fields(&info))
+        .eq(graph.input_mapping.iter().map(fields)));
+    assert_eq!(flat.inputs_size(), graph.inputs_size);
+    assert_eq!(flat.inputs(), graph.inputs());
+}
+
+#[test]
+fn matches_graph_evaluation() {
+    let (graph, bbfs) = (graph(), bbfs());
+    let bytes = flat::encode(&graph).unwrap();
+    let flat = FlatGraph::from_bytes(&bytes).unwrap();
+    for round in 0..32 {
+        let inputs = random_inputs(round);
+        let expected =
+            graph::evaluate::<Fr>(&graph.nodes, &inputs, &graph.signals, Some(&bbfs)).unwrap();
+        assert_eq!(flat.evaluate(&inputs, Some(&bbfs)).unwrap(), expected);
+
+        let named = HashMap::from([
+            ("a".to_string(), vec![inputs[1]]),
+            ("b".to_string(), vec![inputs[2]]),
+        ]);
+        assert_eq!(
+            flat.calculate_witness(named, Some(&bbfs)).unwrap(),
+            expected
+        );
+    }
+}
+
+#[test]
+fn rejects_truncated_graphs() {
+    let bytes = flat::encode(&graph()).unwrap();
+    for len in 0..bytes.len() {
+        assert!(FlatGraph::from_bytes(&bytes[..len]).is_err(), "{len}");
+    }
+}
+
+/// Error of decoding the bytes after `corrupt` modified them
+fn decode_error(bytes: &[u8], corrupt: impl FnOnce(&mut Vec<u8>)) -> String {
+    let mut bytes = bytes.to_vec();
+    corrupt(&mut bytes);
+    FlatGraph::from_bytes(&bytes).unwrap_err().to_string()
+}
+
+#[test]
+fn rejects_corrupted_graphs() {
+    let bytes = flat::encode(&graph()).unwrap();
+    let [nodes, _, _, _, _, signals, _] = sections(&bytes);
+    let unsupported = |version| {
+        format!("flat graph version {version} is not supported, this build reads versions 1 to 4")
+    };
+
+    assert_eq!(decode_error(&bytes, |b| b[0] = b'X'), "not a flat graph");
+    assert_eq!(
+        decode_error(&bytes, |b| b.truncate(20)),
+        "truncated flat graph header: 20 of 48 bytes"
+    );
+    assert_eq!(decode_error(&bytes, |b| write_u32(b, 4, 0)), unsupported(0));
+    assert_eq!(
+        decode_error(&bytes, |b| write_u32(b, 4, 99)),
+        unsupported(99)
+    );
+    assert_eq!(
+        decode_error(&bytes, |b| write_u32(b, 8, u32::MAX)),
+        "truncated flat graph: section 0 out of bounds"
+    );
+    assert_eq!(
+        decode_error(&bytes, |b| write_u32(b, 28, u32::MAX)),
+        "truncated flat graph: section 5 out of bounds"
+    );
+    assert_eq!(
+        decode_error(&bytes, |b| write_u32(b, 36, 99)),
+        "unknown prime 99"
+    );
+    assert_eq!(
+        decode_error(&bytes, |b| write_u32(b, 40, 2)),
+        "inputs buffer of 2 elements is too small for the inputs"
+    );
+    assert_eq!(
+        decode_error(&bytes, |b| write_u32(b, 44, u32::MAX)),
+        "input shapes out of bounds"
+    );
+    // The first operand of the division, node 4.
+    assert_eq!(
+        decode_error(&bytes, |b| write_u32(b, nodes + 4 * 16 + 4, 10)),
+        "node 4: operands must be backwards"
+    );
+    assert_eq!(
+        decode_error(&bytes, |b| write_u32(b, signals, 1000)),
+        "signal 0 out of bounds"
+    );
+}
+
+#[test]
+fn rejects_over_long_inputs() {
+    let bytes = flat::encode(&graph()).unwrap();
+    let flat = FlatGraph::from_bytes(&bytes).unwrap();
+    let inputs = HashMap::from([
+        ("a".to_string(), vec![U256::from(5), U256::from(7)]),
+        ("b".to_string(), vec![U256::from(1)]),
+    ]);
+    let error = flat.calculate_witness(inputs, Some(&bbfs())).unwrap_err();
+    assert_eq!(error.to_string(), "input \"a\" has 2 values, expected 1");
+}
+
+#[test]
+fn encode_rejects_large_graphs() {
+    let mut graph = graph();
+    graph.signals.push(1 << 33);
+    let error = flat::encode(&graph).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "graph too large for the flat encoding: 8589934592 exceeds u32"
+    );
+}
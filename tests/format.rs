@@ -0,0 +1,155 @@
+use ark_bn254::Fr;
+use circom_witness_rs::{
+    format,
+    graph::{self, Node, Operation},
+    nodes::Nodes,
+    prime::Prime,
+    Graph, HashSignalInfo, Metadata,
+};
+use ruint::aliases::U256;
+
+/// `x * 9` for the input `x` of shape `[1]` at 1, with 9 in Montgomery form
+fn graph() -> Graph {
+    let mut nodes = Nodes::new();
+    let x = nodes.push(Node::Input(1));
+    let nine = Fr::from(9);
+    let k = nodes.push(Node::MontConstant(U256::from_limbs(nine.0 .0)));
+    let product = nodes.push(Node::Op(Operation::Mul, x, k));
+    Graph {
+        prime: Prime::Bn128,
+        metadata: Metadata {
+            circom_version: Some("2.2.2".to_string()),
+        },
+        nodes,
+        signals: vec![product, x],
+        input_mapping: vec![HashSignalInfo {
+            hash: 0xABCD,
+            signalid: 1,
+            signalsize: 1,
+            name: "x".to_string(),
+            shape: vec![1],
+        }],
+        inputs_size: 2,
+    }
+}
+
+fn evaluate(graph: &Graph) -> Vec<U256> {
+    let inputs = [U256::from(1), U256::from(5)];
+    graph::evaluate::<Fr>(&graph.nodes, &inputs, &graph.signals, None).unwrap()
+}
+
+fn decode_error(bytes: &[u8]) -> String {
+    match format::decode(bytes) {
+        Ok(_) => panic!("decoded a corrupted graph"),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[test]
+fn round_trip() {
+    let graph = graph();
+    let bytes = format::encode(&graph);
+    assert_eq!(bytes[..4], format::MAGIC);
+    let decoded = format::decode(&bytes).unwrap();
+    assert_eq!(decoded.prime, graph.prime);
+    assert_eq!(decoded.metadata, graph.metadata);
+    assert_eq!(
+        decoded.nodes.iter().collect::<Vec<_>>(),
+        graph.nodes.iter().collect::<Vec<_>>()
+    );
+    assert_eq!(decoded.signals, graph.signals);
+    assert_eq!(decoded.inputs(), graph.inputs());
+    assert_eq!(decoded.inputs_size, graph.inputs_size);
+    assert_eq!(evaluate(&decoded), [45, 5].map(U256::from));
+}
+
+#[test]
+fn checksum_mismatch() {
+    let mut bytes = format::encode(&graph());
+    *bytes.last_mut().unwrap() ^= 1;
+    assert_eq!(
+        decode_error(&bytes),
+        "graph checksum mismatch, the file is corrupted"
+    );
+}
+
+#[test]
+fn bad_magic() {
+    // Without the magic bytes the file is taken for an unversioned graph.
+    let mut bytes = format::encode(&graph());
+    bytes[3] = b'X';
+    assert!(decode_error(&bytes).starts_with("failed to decode unversioned graph: "));
+}
+
+#[test]
+fn unsupported_version() {
+    let mut bytes = format::encode(&graph());
+    for version in [0u32, 2, u32::MAX] {
+        bytes[4..8].copy_from_slice(&version.to_le_bytes());
+        assert_eq!(
+            decode_error(&bytes),
+            format!("graph format version {version} is not supported, this build reads version 1")
+        );
+    }
+}
+
+#[test]
+fn truncated() {
+    let bytes = format::encode(&graph());
+    assert_eq!(
+        decode_error(&bytes[..20]),
+        "truncated graph header: 20 of 48 bytes"
+    );
+    let payload = bytes.len() - 48;
+    assert_eq!(
+        decode_error(&bytes[..bytes.len() - 1]),
+        format!(
+            "graph payload length mismatch: header says {payload} bytes, found {}",
+            payload - 1
+        )
+    );
+    for len in 0..bytes.len() {
+        assert!(format::decode(&bytes[..len]).is_err(), "{len}");
+    }
+}
+
+#[test]
+fn legacy_unversioned() {
+    // Before the header, Montgomery constants were stored canonically and
+    // inputs had no names or shapes.
+    let nodes = vec![
+        Node::Input(1),
+        Node::MontConstant(U256::from(9)),
+        Node::Op(Operation::Mul, 0, 1),
+    ];
+    let legacy = (nodes, vec![2usize, 0], vec![(0xABCDu64, 1u64, 1u64)]);
+    let bytes = postcard::to_allocvec(&legacy).unwrap();
+    let decoded = format::decode(&bytes).unwrap();
+    let expected = graph();
+    assert_eq!(decoded.prime, Prime::Bn128);
+    assert_eq!(decoded.metadata, Metadata::default());
+    assert_eq!(
+        decoded.nodes.iter().collect::<Vec<_>>(),
+        expected.nodes.iter().collect::<Vec<_>>()
+    );
+    assert_eq!(decoded.inputs_size, 2);
+    let input = &decoded.input_mapping[0];
+    assert_eq!(
+        (input.hash, input.signalid, input.signalsize),
+        (0xABCD, 1, 1)
+    );
+    assert!(input.name.is_empty() && input.shape.is_empty());
+    assert_eq!(evaluate(&decoded), [45, 5].map(U256::from));
+
+    // Canonical constants must be reduced.
+    let legacy = (
+        vec![Node::MontConstant(U256::MAX)],
+        vec![0usize],
+        Vec::<(u64, u64, u64)>::new(),
+    );
+    let bytes = postcard::to_allocvec(&legacy).unwrap();
+    assert_eq!(
+        decode_error(&bytes),
+        "failed to decode unversioned graph: constant of node 0 is not reduced"
+    );
+}
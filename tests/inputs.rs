@@ -0,0 +1,92 @@
+use circom_witness_rs::{
+    calculate_witness, flat,
+    graph::{Node, Operation},
+    nodes::Nodes,
+    prime::Prime,
+    Graph, HashMap, HashSignalInfo,
+};
+use ruint::aliases::U256;
+
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xCBF29CE484222325, |hash, c| {
+        (hash ^ c as u64).wrapping_mul(0x100000001B3)
+    })
+}
+
+/// `a + b` for the single inputs `a` at 1 and `b` at 2, followed by the
+/// inputs themselves
+fn graph() -> Graph {
+    let mut nodes = Nodes::new();
+    let a = nodes.push(Node::Input(1));
+    let b = nodes.push(Node::Input(2));
+    let sum = nodes.push(Node::Op(Operation::Add, a, b));
+    let input = |name: &str, signalid| HashSignalInfo {
+        hash: fnv1a(name),
+        signalid,
+        signalsize: 1,
+        name: name.to_string(),
+        shape: vec![],
+    };
+    Graph {
+        prime: Prime::Bn128,
+        metadata: Default::default(),
+        nodes,
+        signals: vec![sum, a, b],
+        input_mapping: vec![input("a", 1), input("b", 2)],
+        inputs_size: 3,
+    }
+}
+
+fn inputs(a: &[u64], b: &[u64]) -> HashMap<String, Vec<U256>> {
+    let values = |v: &[u64]| v.iter().copied().map(U256::from).collect();
+    HashMap::from([("a".to_string(), values(a)), ("b".to_string(), values(b))])
+}
+
+/// Calculates the witness with the graph and the flat graph, which must agree
+fn calculate(inputs: HashMap<String, Vec<U256>>) -> Result<Vec<U256>, String> {
+    let graph = graph();
+    let bytes = flat::encode(&graph).unwrap();
+    let flat = flat::FlatGraph::from_bytes(&bytes).unwrap();
+    let witness = calculate_witness(inputs.clone(), &graph, None).map_err(|e| e.to_string());
+    let flat_witness = flat
+        .calculate_witness(inputs, None)
+        .map_err(|e| e.to_string());
+    assert_eq!(witness, flat_witness);
+    witness
+}
+
+#[test]
+fn exact_lengths() {
+    let witness = calculate(inputs(&[5], &[7])).unwrap();
+    assert_eq!(witness, [12, 5, 7].map(U256::from));
+}
+
+#[test]
+fn too_long() {
+    let error = calculate(inputs(&[5, 7], &[1])).unwrap_err();
+    assert_eq!(error, "input \"a\" has 2 values, expected 1");
+}
+
+#[test]
+fn too_short() {
+    let error = calculate(inputs(&[], &[1])).unwrap_err();
+    assert_eq!(error, "input \"a\" has 0 values, expected 1");
+}
+
+#[test]
+fn last_input_overflow() {
+    let error = calculate(inputs(&[5], &[1, 2])).unwrap_err();
+    assert_eq!(error, "input \"b\" has 2 values, expected 1");
+}
+
+#[test]
+fn out_of_bounds() {
+    // An input that ends past the buffer of a corrupt graph is an error too.
+    let mut graph = graph();
+    graph.inputs_size = 2;
+    let error = calculate_witness(inputs(&[5], &[7]), &graph, None).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "input \"b\" is out of bounds of the inputs buffer"
+    );
+}
@@ -0,0 +1,39 @@
+#![cfg(feature = "jit")]
+
+mod common;
+
+use ark_bn254::Fr;
+use circom_witness_rs::{bytecode, graph, jit::JitGraph, HashMap};
+use common::{bbfs, graph, random_inputs};
+use ruint::aliases::U256;
+
+fn compile() -> JitGraph {
+    JitGraph::compile(&bytecode::lower(&graph()).unwrap()).unwrap()
+}
+
+#[test]
+fn matches_graph_evaluation() {
+    let (graph, jit, bbfs) = (graph(), compile(), bbfs());
+    for round in 0..32 {
+        let inputs = random_inputs(round);
+        let expected =
+            graph::evaluate::<Fr>(&graph.nodes, &inputs, &graph.signals, Some(&bbfs)).unwrap();
+        assert_eq!(jit.evaluate(&inputs, Some(&bbfs)).unwrap(), expected);
+    }
+}
+
+#[test]
+fn missing_black_box_functions() {
+    let (jit, inputs) = (compile(), random_inputs(0));
+    let error = jit.evaluate(&inputs, None).unwrap_err();
+    assert_eq!(error.to_string(), "no black box functions provided");
+    let error = jit.evaluate(&inputs, Some(&HashMap::new())).unwrap_err();
+    assert_eq!(error.to_string(), "black box function \"f\" not found");
+}
+
+#[test]
+fn division_by_zero() {
+    let inputs = [U256::from(1), U256::from(5), U256::ZERO];
+    let error = compile().evaluate(&inputs, None).unwrap_err();
+    assert_eq!(error.to_string(), "operation Div failed");
+}
@@ -0,0 +1,72 @@
+use circom_witness_rs::{
+    graph::{Node, Operation},
+    init_graph,
+    nodes::{Nodes, Opcode},
+};
+use ruint::aliases::U256;
+
+fn nodes() -> Vec<Node> {
+    vec![
+        Node::Input(1),
+        Node::Constant(U256::from(7)),
+        Node::MontConstant(U256::from(9)),
+        Node::Op(Operation::Add, 0, 1),
+        Node::Op(Operation::Neg, 3, 3),
+        Node::BBF("f_0".to_string(), vec![0, 3, 4]),
+        Node::Constant(U256::from(7)),
+        Node::BBF("g_1".to_string(), vec![]),
+    ]
+}
+
+#[test]
+fn round_trip() {
+    let nodes = Nodes::from(self::nodes());
+    assert_eq!(nodes.len(), 8);
+    assert_eq!(nodes.opcode(4), Opcode::Op(Operation::Neg));
+    assert_eq!(nodes.operands(3), (0, 1));
+    assert_eq!(nodes.constant(6), U256::from(7));
+    assert_eq!((nodes.name(5), nodes.params(5)), ("f_0", &[0, 3, 4][..]));
+    assert_eq!(nodes.iter().collect::<Vec<_>>(), self::nodes());
+
+    // Serialized the same as a vector of nodes.
+    let bytes = postcard::to_allocvec(&nodes).unwrap();
+    assert_eq!(bytes, postcard::to_allocvec(&self::nodes()).unwrap());
+    let decoded: Nodes = postcard::from_bytes(&bytes).unwrap();
+    assert_eq!(decoded.iter().collect::<Vec<_>>(), self::nodes());
+}
+
+#[test]
+fn retain_keeps_referenced_pools() {
+    let mut nodes = Nodes::from(self::nodes());
+    nodes.retain(&[true, true, false, true, true, false, false, true]);
+    let expected = [
+        Node::Input(1),
+        Node::Constant(U256::from(7)),
+        Node::Op(Operation::Add, 0, 1),
+        Node::Op(Operation::Neg, 3, 3),
+        Node::BBF("g_1".to_string(), vec![]),
+    ];
+    assert_eq!(nodes.iter().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn rejects_oversized_indices() {
+    for node in [
+        Node::Input(1 << 33),
+        Node::Op(Operation::Add, 0, 1 << 32),
+        Node::BBF("f_0".to_string(), vec![1, 1 << 40]),
+    ] {
+        let bytes = postcard::to_allocvec(&vec![node]).unwrap();
+        let error = postcard::from_bytes::<Nodes>(&bytes).unwrap_err();
+        assert_eq!(error, postcard::Error::SerdeDeCustom);
+    }
+
+    // An unversioned graph file with such a node is an error, not a panic.
+    let legacy = (
+        vec![Node::Input(1 << 33)],
+        vec![0usize],
+        Vec::<(u64, u64, u64)>::new(),
+    );
+    let bytes = postcard::to_allocvec(&legacy).unwrap();
+    assert!(init_graph(&bytes).is_err());
+}
@@ -0,0 +1,202 @@
+use ark_bn254::Fr;
+use circom_witness_rs::{
+    format,
+    graph::{self, Node, Operation, OptimizeOptions},
+    nodes::Nodes,
+    prime::Prime,
+    Graph, M,
+};
+use ruint::aliases::U256;
+
+/// Builds `x^exponent` by square and multiply
+fn pow(nodes: &mut Nodes, x: usize, exponent: U256) -> usize {
+    let mut acc = x;
+    for bit in (0..exponent.bit_len() - 1).rev() {
+        acc = nodes.push(Node::Op(Operation::Mul, acc, acc));
+        if exponent.bit(bit) {
+            acc = nodes.push(Node::Op(Operation::Mul, acc, x));
+        }
+    }
+    acc
+}
+
+/// Builds the Legendre symbol `x^((M - 1) / 2)` of input `x`, which is 1 for
+/// half of the field and -1 for the other half. It is the product of two
+/// powers with unstructured exponents, since a single chain ends in powers
+/// `x^(k 2^j)` that are roots of unity and collide by chance as well.
+fn legendre(nodes: &mut Nodes, x: usize) -> usize {
+    let exponent: U256 = (M - U256::from(1)) >> 1;
+    let split = U256::from(0x9e37_79b9_7f4a_7c15_f39c_c060_5ced_c835_u128);
+    let a = pow(nodes, x, exponent - split);
+    let b = pow(nodes, x, split);
+    nodes.push(Node::Op(Operation::Mul, a, b))
+}
+
+/// A constant 1 and the Legendre symbol of input 1
+fn legendre_graph() -> (Nodes, Vec<usize>) {
+    let mut nodes = Nodes::new();
+    let one = nodes.push(Node::Constant(U256::from(1)));
+    let x = nodes.push(Node::Input(1));
+    let symbol = legendre(&mut nodes, x);
+    (nodes, vec![one, symbol])
+}
+
+fn evaluate(nodes: &Nodes, outputs: &[usize], x: u64) -> Vec<U256> {
+    let inputs = [U256::from(1), U256::from(x)];
+    graph::evaluate::<Fr>(nodes, &inputs, outputs, None).unwrap()
+}
+
+#[test]
+fn small_range_merges_need_an_exact_check() {
+    let (nodes, outputs) = legendre_graph();
+    let minus_one = M - U256::from(1);
+
+    // 4 is a square and 5 is not, so a single random evaluation makes the
+    // symbol look like the constant 1 with probability 1/2.
+    assert_eq!(
+        evaluate(&nodes, &outputs, 4),
+        [U256::from(1), U256::from(1)]
+    );
+    assert_eq!(evaluate(&nodes, &outputs, 5), [U256::from(1), minus_one]);
+
+    // Several rounds alone still merge it with probability 2^-rounds, over
+    // many seeds the exact check has to reject every merge and constant.
+    for rounds in [1, 2] {
+        for seed in 0..64 {
+            let (mut nodes, mut outputs) = legendre_graph();
+            let options = OptimizeOptions {
+                seed: Some(seed),
+                rounds,
+            };
+            graph::optimize_with::<Fr>(&mut nodes, &mut outputs, options);
+            assert_ne!(outputs[0], outputs[1], "merged with seed {seed}");
+            assert_eq!(evaluate(&nodes, &outputs, 5), [U256::from(1), minus_one]);
+        }
+    }
+}
+
+#[test]
+fn exact_identities_are_kept() {
+    // x - x is 0 in every evaluation, the algebraic rules find it exactly.
+    let mut nodes = Nodes::new();
+    let x = nodes.push(Node::Input(1));
+    let zero = nodes.push(Node::Op(Operation::Sub, x, x));
+    let mut outputs = vec![zero];
+    graph::optimize::<Fr>(&mut nodes, &mut outputs);
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(evaluate(&nodes, &outputs, 7), [U256::ZERO]);
+}
+
+/// [`legendre_graph`] with duplicates and a hidden constant in a [`Graph`]
+fn redundant_graph() -> Graph {
+    let (mut nodes, mut signals) = legendre_graph();
+    let x = 1;
+    let two = nodes.push(Node::Constant(U256::from(2)));
+    let a = nodes.push(Node::Op(Operation::Add, x, two));
+    let b = nodes.push(Node::Op(Operation::Add, two, x));
+    let c = nodes.push(Node::Op(Operation::Sub, a, x));
+    let d = nodes.push(Node::Op(Operation::Mul, a, b));
+    signals.extend([a, b, c, d]);
+    Graph {
+        prime: Prime::Bn128,
+        metadata: Default::default(),
+        nodes,
+        signals,
+        input_mapping: vec![],
+        inputs_size: 2,
+    }
+}
+
+/// Encoding of [`redundant_graph`] optimized with `options`
+fn optimized(options: OptimizeOptions) -> Vec<u8> {
+    let mut graph = redundant_graph();
+    graph::optimize_with::<Fr>(&mut graph.nodes, &mut graph.signals, options);
+    format::encode(&graph)
+}
+
+#[test]
+fn optimization_is_deterministic() {
+    let bytes = optimized(OptimizeOptions::default());
+    assert_eq!(optimized(OptimizeOptions::default()), bytes);
+
+    // The default seed is the seed of the unoptimized graph.
+    let graph = redundant_graph();
+    let seed = graph::graph_seed(&graph.nodes, &graph.signals);
+    let options = OptimizeOptions {
+        seed: Some(seed),
+        ..Default::default()
+    };
+    assert_eq!(optimized(options), bytes);
+}
+
+#[test]
+fn zero_rounds_count_as_one() {
+    let options = |rounds| OptimizeOptions {
+        seed: Some(7),
+        rounds,
+    };
+    let bytes = optimized(options(0));
+    assert_eq!(bytes, optimized(options(1)));
+
+    let mut graph = redundant_graph();
+    let expected = evaluate(&graph.nodes, &graph.signals, 5);
+    graph::optimize_with::<Fr>(&mut graph.nodes, &mut graph.signals, options(0));
+    assert_eq!(evaluate(&graph.nodes, &graph.signals, 5), expected);
+}
+
+/// Inputs 1 and 2, the outputs are the nodes added by `build`
+fn two_inputs(build: impl FnOnce(&mut Nodes, usize, usize) -> Vec<usize>) -> (Nodes, Vec<usize>) {
+    let mut nodes = Nodes::new();
+    let x = nodes.push(Node::Input(1));
+    let y = nodes.push(Node::Input(2));
+    let outputs = build(&mut nodes, x, y);
+    (nodes, outputs)
+}
+
+#[test]
+fn commutative_operands_merge() {
+    let (mut nodes, mut outputs) = two_inputs(|nodes, x, y| {
+        vec![
+            nodes.push(Node::Op(Operation::Add, x, y)),
+            nodes.push(Node::Op(Operation::Add, y, x)),
+            nodes.push(Node::Op(Operation::Mul, x, y)),
+            nodes.push(Node::Op(Operation::Mul, y, x)),
+        ]
+    });
+    assert_eq!(graph::common_subexpressions(&mut nodes, &mut outputs), 2);
+    assert_eq!(outputs, [2, 2, 4, 4]);
+}
+
+#[test]
+fn identical_black_box_calls_merge() {
+    let (mut nodes, mut outputs) = two_inputs(|nodes, x, y| {
+        let a = nodes.push(Node::Op(Operation::Add, x, y));
+        let b = nodes.push(Node::Op(Operation::Add, y, x));
+        vec![
+            nodes.push(Node::BBF("f_0".to_string(), vec![a, y])),
+            // The same call after merging `b` into `a`.
+            nodes.push(Node::BBF("f_0".to_string(), vec![b, y])),
+            // Another instance of `f`, which is looked up by the same name.
+            nodes.push(Node::BBF("f_1".to_string(), vec![a, y])),
+            nodes.push(Node::BBF("f_0".to_string(), vec![y, a])),
+            nodes.push(Node::BBF("g_0".to_string(), vec![a, y])),
+        ]
+    });
+    assert_eq!(graph::common_subexpressions(&mut nodes, &mut outputs), 3);
+    assert_eq!(outputs, [4, 4, 4, 7, 8]);
+}
+
+#[test]
+fn swapped_operands_of_other_operations_stay() {
+    use Operation::*;
+    for op in [Sub, Div, Lt] {
+        let (mut nodes, mut outputs) = two_inputs(|nodes, x, y| {
+            vec![
+                nodes.push(Node::Op(op, x, y)),
+                nodes.push(Node::Op(op, y, x)),
+            ]
+        });
+        assert_eq!(graph::common_subexpressions(&mut nodes, &mut outputs), 0);
+        assert_eq!(outputs, [2, 3], "{op:?}");
+    }
+}
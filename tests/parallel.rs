@@ -0,0 +1,86 @@
+#![cfg(feature = "parallel")]
+
+mod common;
+
+use ark_bn254::Fr;
+use circom_witness_rs::{
+    graph::{self, Node, Operation},
+    parallel::ParallelGraph,
+    prime::Prime,
+    Graph, HashMap,
+};
+use common::{bbfs, graph, random_inputs};
+
+/// [`graph`] with levels wide enough to be split into chunks: every
+/// operation of the graph on the inputs and a constant, then black box calls
+/// on the results
+fn wide_graph() -> Graph {
+    use Operation::*;
+    let mut graph = graph();
+    let nodes = &mut graph.nodes;
+    let (a, b) = (0, 1);
+    for j in 0..1024 {
+        let c = nodes.push(Node::Constant((j + 1).try_into().unwrap()));
+        let op = [Add, Sub, Mul, Eq, Neg, Div, IDiv, Mod][j % 8];
+        let x = nodes.push(Node::Op(op, a, c));
+        let y = nodes.push(Node::BBF(format!("f_{j}"), vec![x, b]));
+        graph.signals.extend([x, y]);
+    }
+    graph
+}
+
+/// [`wide_graph`] followed by narrow levels that are merged into one step:
+/// a chain summing the results of the black box calls
+fn chained_graph() -> Graph {
+    let mut graph = wide_graph();
+    let calls: Vec<_> = (0..graph.nodes.len())
+        .filter(|&i| matches!(graph.nodes.get(i), Node::BBF(..)))
+        .collect();
+    let mut sum = calls[0];
+    for &y in &calls[1..] {
+        sum = graph.nodes.push(Node::Op(Operation::Add, sum, y));
+    }
+    graph.signals.push(sum);
+    graph
+}
+
+#[test]
+fn matches_graph_evaluation() {
+    let bbfs = bbfs();
+    for graph in [graph(), wide_graph(), chained_graph()] {
+        let parallel = ParallelGraph::new(&graph).unwrap();
+        for round in 0..8 {
+            let inputs = random_inputs(round);
+            let expected =
+                graph::evaluate::<Fr>(&graph.nodes, &inputs, &graph.signals, Some(&bbfs)).unwrap();
+            assert_eq!(parallel.evaluate(&inputs, Some(&bbfs)).unwrap(), expected);
+        }
+    }
+}
+
+#[test]
+fn missing_black_box_functions() {
+    let inputs = random_inputs(0);
+    for graph in [graph(), wide_graph()] {
+        let parallel = ParallelGraph::new(&graph).unwrap();
+        let error = parallel.evaluate(&inputs, None).unwrap_err();
+        assert_eq!(error.to_string(), "no black box functions provided");
+        let error = parallel
+            .evaluate(&inputs, Some(&HashMap::new()))
+            .unwrap_err();
+        assert_eq!(error.to_string(), "black box function \"f\" not found");
+    }
+}
+
+#[test]
+fn rejects_other_primes() {
+    let graph = Graph {
+        prime: Prime::Goldilocks,
+        ..graph()
+    };
+    let error = ParallelGraph::new(&graph).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "parallel evaluation is not supported for goldilocks"
+    );
+}
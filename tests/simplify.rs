@@ -0,0 +1,172 @@
+use ark_bn254::Fr;
+use circom_witness_rs::{
+    graph::{self, Node, Operation, SimplifyStats},
+    nodes::{Nodes, Opcode},
+    M,
+};
+use rand::Rng;
+use ruint::aliases::U256;
+
+/// Simplifies the graph, checks the statistics and that the outputs are
+/// unchanged for random inputs, and returns the simplified graph
+fn simplify(
+    mut nodes: Nodes,
+    mut outputs: Vec<usize>,
+    expected: SimplifyStats,
+) -> (Nodes, Vec<usize>) {
+    let mut rng = rand::thread_rng();
+    let inputs: Vec<_> = (0..8)
+        .map(|_| [U256::from(1), rng.gen::<U256>() % M])
+        .collect();
+    let evaluate = |nodes: &Nodes, outputs: &[usize]| -> Vec<_> {
+        inputs
+            .iter()
+            .map(|inputs| graph::evaluate::<Fr>(nodes, inputs, outputs, None).unwrap())
+            .collect()
+    };
+
+    let before = evaluate(&nodes, &outputs);
+    let stats = graph::simplify::<Fr>(&mut nodes, &mut outputs);
+    assert_eq!(stats, expected);
+    assert_eq!(evaluate(&nodes, &outputs), before);
+    (nodes, outputs)
+}
+
+/// Nodes starting with input 1, the outputs are the nodes added by `build`
+fn graph(build: impl FnOnce(&mut Nodes, usize) -> Vec<usize>) -> (Nodes, Vec<usize>) {
+    let mut nodes = Nodes::new();
+    let x = nodes.push(Node::Input(1));
+    let outputs = build(&mut nodes, x);
+    (nodes, outputs)
+}
+
+fn constant(nodes: &mut Nodes, c: u64) -> usize {
+    nodes.push(Node::Constant(U256::from(c)))
+}
+
+#[test]
+fn mul_one() {
+    let (nodes, outputs) = graph(|nodes, x| {
+        let one = constant(nodes, 1);
+        vec![
+            nodes.push(Node::Op(Operation::Mul, x, one)),
+            nodes.push(Node::Op(Operation::Mul, one, x)),
+        ]
+    });
+    let expected = SimplifyStats {
+        mul_one: 2,
+        ..Default::default()
+    };
+    let (_, outputs) = simplify(nodes, outputs, expected);
+    assert_eq!(outputs, [0, 0]);
+}
+
+#[test]
+fn add_zero() {
+    let (nodes, outputs) = graph(|nodes, x| {
+        let zero = constant(nodes, 0);
+        vec![
+            nodes.push(Node::Op(Operation::Add, x, zero)),
+            nodes.push(Node::Op(Operation::Add, zero, x)),
+        ]
+    });
+    let expected = SimplifyStats {
+        add_zero: 2,
+        ..Default::default()
+    };
+    let (_, outputs) = simplify(nodes, outputs, expected);
+    assert_eq!(outputs, [0, 0]);
+}
+
+#[test]
+fn sub_zero() {
+    let (nodes, outputs) = graph(|nodes, x| {
+        let zero = constant(nodes, 0);
+        vec![nodes.push(Node::Op(Operation::Sub, x, zero))]
+    });
+    let expected = SimplifyStats {
+        sub_zero: 1,
+        ..Default::default()
+    };
+    let (_, outputs) = simplify(nodes, outputs, expected);
+    assert_eq!(outputs, [0]);
+}
+
+#[test]
+fn sub_self() {
+    let (nodes, outputs) = graph(|nodes, x| vec![nodes.push(Node::Op(Operation::Sub, x, x))]);
+    let expected = SimplifyStats {
+        sub_self: 1,
+        ..Default::default()
+    };
+    let (nodes, outputs) = simplify(nodes, outputs, expected);
+    assert_eq!(nodes.get(outputs[0]), Node::Constant(U256::ZERO));
+}
+
+#[test]
+fn mul_zero() {
+    let (nodes, outputs) = graph(|nodes, x| {
+        let zero = constant(nodes, 0);
+        vec![
+            nodes.push(Node::Op(Operation::Mul, x, zero)),
+            nodes.push(Node::Op(Operation::Mul, zero, x)),
+        ]
+    });
+    let expected = SimplifyStats {
+        mul_zero: 2,
+        ..Default::default()
+    };
+    let (nodes, outputs) = simplify(nodes, outputs, expected);
+    for output in outputs {
+        assert_eq!(nodes.get(output), Node::Constant(U256::ZERO));
+    }
+}
+
+#[test]
+fn double_neg() {
+    let (nodes, outputs) = graph(|nodes, x| {
+        let neg = nodes.push(Node::Op(Operation::Neg, x, x));
+        vec![nodes.push(Node::Op(Operation::Neg, neg, neg))]
+    });
+    let expected = SimplifyStats {
+        double_neg: 1,
+        ..Default::default()
+    };
+    let (_, outputs) = simplify(nodes, outputs, expected);
+    assert_eq!(outputs, [0]);
+}
+
+#[test]
+fn div_constant() {
+    let (nodes, outputs) = graph(|nodes, x| {
+        let c = constant(nodes, 3);
+        vec![nodes.push(Node::Op(Operation::Div, x, c))]
+    });
+    let expected = SimplifyStats {
+        div_constant: 1,
+        ..Default::default()
+    };
+    let (nodes, outputs) = simplify(nodes, outputs, expected);
+    let (a, b) = nodes.operands(outputs[0]);
+    assert_eq!(nodes.opcode(outputs[0]), Opcode::Op(Operation::Mul));
+    assert_eq!(
+        (a, nodes.constant(b)),
+        (0, U256::from(3).inv_mod(M).unwrap())
+    );
+}
+
+#[test]
+fn sub_constant() {
+    let (nodes, outputs) = graph(|nodes, x| {
+        let c = constant(nodes, 5);
+        vec![nodes.push(Node::Op(Operation::Sub, x, c))]
+    });
+    let expected = SimplifyStats {
+        sub_constant: 1,
+        ..Default::default()
+    };
+    let (nodes, outputs) = simplify(nodes, outputs, expected);
+    let (a, b) = nodes.operands(outputs[0]);
+    assert_eq!(nodes.opcode(outputs[0]), Opcode::Op(Operation::Add));
+    assert_eq!((a, nodes.constant(b)), (0, M - U256::from(5)));
+}
@@ -0,0 +1,129 @@
+//! A tokenizer for the subset of C++ that circom generates.
+//!
+//! Every byte of the source ends up in exactly one token, so concatenating the
+//! token texts gives back the source. Whitespace and comments are kept as
+//! trivia to preserve the layout of untouched code.
+
+use crate::Diagnostic;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Ident,
+    Number,
+    String,
+    Char,
+    Punct,
+    /// A whole preprocessor line such as `#include <stdio.h>`
+    Preprocessor,
+    Comment,
+    Whitespace,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Token<'a> {
+    pub kind: Kind,
+    pub text: &'a str,
+    /// Byte offset in the source
+    pub offset: usize,
+}
+
+impl Token<'_> {
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind, Kind::Comment | Kind::Whitespace)
+    }
+
+    pub fn end(&self) -> usize {
+        self.offset + self.text.len()
+    }
+}
+
+/// Punctuators of more than one character, longest first
+const PUNCTS: [&str; 22] = [
+    "<<=", ">>=", "::", "->", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "++", "--", "+=",
+    "-=", "*=", "/=", "%=", "&=", "|=", "^=",
+];
+
+pub fn tokenize(source: &str) -> Result<Vec<Token<'_>>, Diagnostic> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut line_start = true;
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let c = bytes[i];
+        let kind = if c.is_ascii_whitespace() {
+            while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                line_start |= bytes[i] == b'\n';
+                i += 1;
+            }
+            Kind::Whitespace
+        } else if c == b'#' && line_start {
+            // Runs to the end of the line, including continuations.
+            while i < bytes.len() && bytes[i] != b'\n' {
+                i += if bytes[i] == b'\\' { 2 } else { 1 };
+            }
+            i = i.min(bytes.len());
+            Kind::Preprocessor
+        } else if source[i..].starts_with("//") {
+            i = source[i..].find('\n').map_or(bytes.len(), |n| i + n);
+            Kind::Comment
+        } else if source[i..].starts_with("/*") {
+            let n = source[i + 2..]
+                .find("*/")
+                .ok_or_else(|| Diagnostic::at(source, start, "unterminated comment"))?;
+            i += n + 4;
+            Kind::Comment
+        } else if c == b'"' || c == b'\'' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != c {
+                if bytes[i] == b'\n' {
+                    break;
+                }
+                i += if bytes[i] == b'\\' { 2 } else { 1 };
+            }
+            if i >= bytes.len() || bytes[i] != c {
+                return Err(Diagnostic::at(source, start, "unterminated literal"));
+            }
+            i += 1;
+            if c == b'"' {
+                Kind::String
+            } else {
+                Kind::Char
+            }
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            Kind::Ident
+        } else if c.is_ascii_digit() {
+            while i < bytes.len()
+                && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.' || bytes[i] == b'_')
+            {
+                i += 1;
+            }
+            Kind::Number
+        } else if c.is_ascii_punctuation() {
+            i += PUNCTS
+                .iter()
+                .find(|p| source[i..].starts_with(*p))
+                .map_or(1, |p| p.len());
+            Kind::Punct
+        } else {
+            let c = source[i..].chars().next().unwrap();
+            return Err(Diagnostic::at(
+                source,
+                start,
+                format!("unexpected character {c:?}"),
+            ));
+        };
+        if kind != Kind::Whitespace {
+            line_start = false;
+        }
+        tokens.push(Token {
+            kind,
+            text: &source[start..i],
+            offset: start,
+        });
+    }
+    Ok(tokens)
+}
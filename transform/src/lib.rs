@@ -0,0 +1,467 @@
+//! Rewrites the C++ generated by `circom --c` to run against the field hooks
+//! of circom-witness-rs.
+//!
+//! The hooks replace the field arithmetic of circom with Rust functions over
+//! `FrElement` handles, and the witness calculator context lives in Rust
+//! vectors shared through `cxx`. The generated code is tokenized and split into
+//! statements, which are rewritten by pattern:
+//!
+//! - arrays of field elements and subcomponent indices become `rust::Vec`,
+//! - `std::string` becomes `rust::string`,
+//! - calls to functions with `bbf` in their name become black box calls,
+//! - assertions, logs and memory management are removed.
+//!
+//! Constructs the hooks cannot support, for example parallel components, are
+//! reported as a [`Diagnostic`] pointing at the offending code. The circom
+//! versions the transformation is tested with are [`SUPPORTED_VERSIONS`].
+//!
+//! The names and shapes of the main inputs are read from the symbol file with
+//! [`main_signals`] and [`signal_arrays`], and the prime of the circuit from
+//! its field arithmetic with [`detect_prime`].
+
+use std::fmt;
+
+mod lexer;
+mod prime;
+mod sym;
+mod version;
+
+use lexer::{tokenize, Kind, Token};
+
+pub use prime::detect_prime;
+pub use sym::{input_name, main_signals, signal_arrays, SignalArray};
+pub use version::{CircomVersion, SUPPORTED_VERSIONS};
+
+/// An unsupported or unrecognized construct in the generated C++
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// One based line number
+    pub line: usize,
+    /// One based column in bytes
+    pub column: usize,
+    pub message: String,
+    /// The source line containing the construct
+    pub source_line: String,
+}
+
+impl Diagnostic {
+    fn at(source: &str, offset: usize, message: impl Into<String>) -> Self {
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |i| offset + i);
+        Diagnostic {
+            line: source[..offset].matches('\n').count() + 1,
+            column: offset - line_start + 1,
+            message: message.into(),
+            source_line: source[line_start..line_end].trim_end().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}\n    {}",
+            self.line, self.column, self.message, self.source_line
+        )
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+const HEADER: &str = r#"#include "witness/include/witness.h"
+#include "witness/src/generate.rs.h"
+
+namespace {namespace} {
+
+/// We need this accessor since cxx doesn't support hashmaps yet
+class IOSignalInfoAccessor {
+private:
+  Circom_CalcWit *calcWitContext;
+
+public:
+  explicit IOSignalInfoAccessor(Circom_CalcWit *calcWit)
+      : calcWitContext(calcWit) {}
+  auto operator[](size_t index) const -> decltype(auto) {
+    return (calcWitContext
+                ->templateInsId2IOSignalInfoList)[index];
+  }
+};
+
+typedef void (*Circom_TemplateFunction)(uint __cIdx, Circom_CalcWit* __ctx);
+
+//////////////////////////////////////////////////////////////////
+/// Generated code from circom compiler below
+//////////////////////////////////////////////////////////////////
+
+"#;
+
+/// Members of the witness calculator context that the Rust side provides
+const CONTEXT_MEMBERS: [&str; 5] = [
+    "signalValues",
+    "componentMemory",
+    "circuitConstants",
+    "busInsId2FieldInfo",
+    "listOfTemplateMessages",
+];
+
+/// Field operations hooked by the graph builder
+const FIELD_OPERATIONS: [&str; 24] = [
+    "Fr_mul",
+    "Fr_add",
+    "Fr_sub",
+    "Fr_copy",
+    "Fr_copyn",
+    "Fr_neg",
+    "Fr_inv",
+    "Fr_div",
+    "Fr_mod",
+    "Fr_shl",
+    "Fr_shr",
+    "Fr_band",
+    "Fr_land",
+    "Fr_lor",
+    "Fr_eq",
+    "Fr_neq",
+    "Fr_lt",
+    "Fr_gt",
+    "Fr_leq",
+    "Fr_geq",
+    "Fr_isTrue",
+    "Fr_toInt",
+    "Fr_pow",
+    "Fr_idiv",
+];
+
+/// Identifiers of statements that only produce debug output
+const OUTPUT_IDENTS: [&str; 4] = ["getTrace", "printf", "cout", "Fr_element2str"];
+
+/// Rewrites the generated C++ `source` into a translation unit with all
+/// definitions in `namespace`
+pub fn transform(source: &str, namespace: &str) -> Result<String, Diagnostic> {
+    let tokens = tokenize(source)?;
+    let mut out = HEADER.replace("{namespace}", namespace);
+    let mut skip_newline = false;
+    for piece in pieces(source, &tokens)? {
+        match piece {
+            Piece::Trivia(token) => {
+                let text = match token.text.find('\n') {
+                    Some(i) if skip_newline && token.kind == Kind::Whitespace => {
+                        &token.text[i + 1..]
+                    }
+                    _ => token.text,
+                };
+                out += text;
+            }
+            Piece::Statement(statement) => match rewrite(source, statement)? {
+                Some(text) => out += &text,
+                None => {
+                    // Remove the whole line if the statement was alone on it.
+                    let trimmed = out.trim_end_matches([' ', '\t']).len();
+                    if out[..trimmed].ends_with('\n') {
+                        out.truncate(trimmed);
+                        skip_newline = true;
+                        continue;
+                    }
+                }
+            },
+        }
+        skip_newline = false;
+    }
+    // Buses were added in circom 2.2, earlier versions have no bus field map.
+    if !tokens.iter().any(|t| t.text == "get_size_of_bus_field_map") {
+        out += "\nuint get_size_of_bus_field_map() {return 0;}\n";
+    }
+    out += &format!("\n}} // namespace {namespace}\n");
+    Ok(out)
+}
+
+enum Piece<'t, 's> {
+    Trivia(&'t Token<'s>),
+    /// Tokens from the first significant one up to and including `;`, `{` or
+    /// `}`, or a single preprocessor line
+    Statement(&'t [Token<'s>]),
+}
+
+/// Splits the tokens into statements and the trivia between them
+fn pieces<'t, 's>(source: &str, tokens: &'t [Token<'s>]) -> Result<Vec<Piece<'t, 's>>, Diagnostic> {
+    let mut pieces = Vec::new();
+    let mut start = None;
+    let mut parens = 0_usize;
+    let mut initializers = 0_usize;
+    let mut prev = "";
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_trivia() {
+            if start.is_none() {
+                pieces.push(Piece::Trivia(token));
+            }
+            continue;
+        }
+        if token.kind == Kind::Preprocessor {
+            if start.is_some() {
+                return Err(Diagnostic::at(
+                    source,
+                    token.offset,
+                    "preprocessor directive inside a statement",
+                ));
+            }
+            pieces.push(Piece::Statement(&tokens[i..=i]));
+            continue;
+        }
+        let first = *start.get_or_insert(i) == i;
+        let end = match token.text {
+            "(" | "[" => {
+                parens += 1;
+                false
+            }
+            ")" | "]" => {
+                parens = parens
+                    .checked_sub(1)
+                    .ok_or_else(|| Diagnostic::at(source, token.offset, "unbalanced brackets"))?;
+                false
+            }
+            // Braces after `=` or `new uint[n]` open an initializer list.
+            "{" if initializers > 0 || prev == "=" || prev == "]" => {
+                initializers += 1;
+                false
+            }
+            "{" if parens > 0 => {
+                return Err(Diagnostic::at(
+                    source,
+                    token.offset,
+                    "unexpected `{` inside brackets",
+                ));
+            }
+            "{" => true,
+            "}" if initializers > 0 => {
+                initializers -= 1;
+                false
+            }
+            "}" if !first => {
+                return Err(Diagnostic::at(
+                    source,
+                    token.offset,
+                    "expected `;` before `}`",
+                ));
+            }
+            "}" => true,
+            ";" => parens == 0,
+            _ => false,
+        };
+        prev = token.text;
+        if end {
+            pieces.push(Piece::Statement(&tokens[start.take().unwrap()..=i]));
+            prev = "";
+        }
+    }
+    if let Some(start) = start {
+        return Err(Diagnostic::at(
+            source,
+            tokens[start].offset,
+            "unterminated statement",
+        ));
+    }
+    Ok(pieces)
+}
+
+/// Source text from the start of `first` to the end of `last`
+fn span<'s>(source: &'s str, first: &Token, last: &Token) -> &'s str {
+    &source[first.offset..last.end()]
+}
+
+/// Index of the bracket closing the one at `open`
+fn closing(tokens: &[&Token], open: usize) -> Option<usize> {
+    let mut depth = 0_usize;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        match token.text {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Splits the tokens between the brackets at `open` and `close` at top level
+/// commas
+fn arguments<'a, 't>(
+    tokens: &'a [&'t Token<'t>],
+    open: usize,
+    close: usize,
+) -> Vec<&'a [&'t Token<'t>]> {
+    let mut arguments = Vec::new();
+    let mut depth = 0_usize;
+    let mut start = open + 1;
+    for i in open + 1..close {
+        match tokens[i].text {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth -= 1,
+            "," if depth == 0 => {
+                arguments.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    if start < close {
+        arguments.push(&tokens[start..close]);
+    }
+    arguments
+}
+
+/// Rewrites a statement, `None` removes it
+fn rewrite(source: &str, statement: &[Token]) -> Result<Option<String>, Diagnostic> {
+    let s: Vec<&Token> = statement.iter().filter(|t| !t.is_trivia()).collect();
+    let texts: Vec<&str> = s.iter().map(|t| t.text).collect();
+    let error = |i: usize, message: String| Err(Diagnostic::at(source, s[i].offset, message));
+    let n = s.len();
+
+    if s[0].kind == Kind::Preprocessor {
+        let directive = s[0].text[1..].trim_start();
+        return Ok((!directive.starts_with("include")).then(|| s[0].text.to_string()));
+    }
+
+    // Assertions, debug output and deallocations are removed.
+    let delete = texts.iter().position(|&t| t == "delete");
+    if texts[0] == "assert"
+        || texts.contains(&"mySubcomponentsParallel")
+        || texts.iter().any(|t| OUTPUT_IDENTS.contains(t))
+        || delete.is_some()
+    {
+        if texts[n - 1] != ";" {
+            return error(0, "cannot remove a block statement".to_string());
+        }
+        if let Some(d) = delete {
+            // `delete []x;`, optionally guarded by an `if`
+            let guarded = d == 0 || (texts[0] == "if" && closing(&s, 1) == Some(d - 1));
+            if !guarded || texts.get(d + 1..d + 3) != Some(&["[", "]"]) {
+                return error(d, "unsupported `delete`".to_string());
+            }
+        }
+        return Ok(None);
+    }
+
+    match texts[..] {
+        // `FrElement lvar[3];`
+        ["FrElement", name, "[", len, "]", ";"] => {
+            return Ok(Some(format!(
+                "rust::Vec<FrElement> {name} = create_vec({len});"
+            )));
+        }
+        // `uint aux_dimensions[2] = {1,2};`
+        ["uint", name, "[", _, "]", "=", "{", .., "}", ";"] => {
+            let values = span(source, s[6], s[n - 2]);
+            return Ok(Some(format!(
+                "rust::Vec<uint32_t> {name} = rust::Vec<uint32_t>{values};"
+            )));
+        }
+        // `Foo_bbf_0(ctx,lvarcall,myId,&lvar[0],1);`
+        [name, "(", .., ")", ";"]
+            if s[0].kind == Kind::Ident
+                && name.contains("bbf")
+                && closing(&s, 1) == Some(n - 2) =>
+        {
+            let arguments = arguments(&s, 1, n - 2);
+            if arguments.len() != 5 || arguments.iter().any(|a| a.is_empty()) {
+                return error(0, format!("unexpected arguments in call to `{name}`"));
+            }
+            let parameters = span(source, arguments[1][0], arguments[1].last().unwrap());
+            let destination = span(source, arguments[3][0], arguments[3].last().unwrap());
+            return Ok(Some(format!(
+                "bbf(\"{name}\", {parameters}, {destination});"
+            )));
+        }
+        _ => (),
+    }
+
+    let mut out = String::new();
+    let mut k = 0;
+    while k < n {
+        if k > 0 {
+            out += &source[s[k - 1].end()..s[k].offset];
+        }
+        let t = |j: usize| texts.get(k + j).copied().unwrap_or("");
+        let consumed = match (t(0), t(1), t(2)) {
+            // Context members held in Rust vectors
+            ("FrElement", "*", name @ ("signalValues" | "circuitConstants")) if k == 0 => {
+                out += &format!("rust::Vec<FrElement> &{name}");
+                3
+            }
+            ("u32", "*", "mySubcomponents") if k == 0 => {
+                out += "rust::Vec<u32> mySubcomponents";
+                3
+            }
+            ("std", "::", "string")
+                if k == 0 && t(3) == "*" && t(4) == "listOfTemplateMessages" =>
+            {
+                out += "rust::Vec<rust::string> &listOfTemplateMessages";
+                5
+            }
+            // Function parameter, the caller passes `lvarcall`
+            ("FrElement", "*", "lvar") if t(3) == "," || t(3) == ")" => {
+                out += "rust::Vec<FrElement>& lvar";
+                3
+            }
+            ("std", "::", "string") => {
+                out += "rust::string";
+                3
+            }
+            ("std", "::", name) => return error(k, format!("unsupported `std::{name}`")),
+            ("PFrElement", ..) => {
+                out += "FrElement*";
+                1
+            }
+            ("ctx", "->", "templateInsId2IOSignalInfo") => {
+                out += "IOSignalInfoAccessor(ctx)";
+                3
+            }
+            // `"name"+ctx->generate_position_array(...)`
+            (_, "+", "ctx")
+                if s[k].kind == Kind::String
+                    && t(3) == "->"
+                    && t(4) == "generate_position_array"
+                    && t(5) == "(" =>
+            {
+                let Some(close) = closing(&s, k + 5) else {
+                    return error(k, "unbalanced brackets".to_string());
+                };
+                let arguments = &source[s[k + 5].end()..s[close].offset];
+                out += &format!("generate_position_array({}, {arguments})", s[k].text);
+                close + 1 - k
+            }
+            ("ctx", "->", member) if !CONTEXT_MEMBERS.contains(&member) => {
+                return error(k + 2, format!("unsupported context member `ctx->{member}`"));
+            }
+            // `new uint[2]` and `new uint[2]{0}`
+            ("new", "uint", "[")
+                if s.get(k + 3).is_some_and(|t| t.kind == Kind::Number) && t(4) == "]" =>
+            {
+                out += &format!("create_vec_u32({})", t(3));
+                match t(5) {
+                    "{" | "(" => match closing(&s, k + 5) {
+                        Some(close) => close + 1 - k,
+                        None => return error(k, "unbalanced brackets".to_string()),
+                    },
+                    _ => 5,
+                }
+            }
+            ("new", ..) => return error(k, "unsupported allocation".to_string()),
+            (name, "(", _) if name.starts_with("Fr_") && !FIELD_OPERATIONS.contains(&name) => {
+                return error(k, format!("unsupported field operation `{name}`"));
+            }
+            (text, ..) => {
+                out += text;
+                1
+            }
+        };
+        k += consumed;
+    }
+    Ok(Some(out))
+}
@@ -0,0 +1,95 @@
+//! Detection of the prime that `circom --c` generated a circuit for.
+
+/// The primes of `circom --prime` with their modulus in decimal and in 64 bit
+/// limbs, least significant first
+const PRIMES: [(&str, &str, &[u64]); 7] = [
+    (
+        "bn128",
+        "21888242871839275222246405745257275088548364400416034343698204186575808495617",
+        &[
+            0x43e1f593f0000001,
+            0x2833e84879b97091,
+            0xb85045b68181585d,
+            0x30644e72e131a029,
+        ],
+    ),
+    (
+        "bls12381",
+        "52435875175126190479447740508185965837690552500527637822603658699938581184513",
+        &[
+            0xffffffff00000001,
+            0x53bda402fffe5bfe,
+            0x3339d80809a1d805,
+            0x73eda753299d7d48,
+        ],
+    ),
+    ("goldilocks", "18446744069414584321", &[0xffffffff00000001]),
+    (
+        "grumpkin",
+        "21888242871839275222246405745257275088696311157297823662689037894645226208583",
+        &[
+            0x3c208c16d87cfd47,
+            0x97816a916871ca8d,
+            0xb85045b68181585d,
+            0x30644e72e131a029,
+        ],
+    ),
+    (
+        "pallas",
+        "28948022309329048855892746252171976963363056481941560715954676764349967630337",
+        &[
+            0x992d30ed00000001,
+            0x224698fc094cf91b,
+            0,
+            0x4000000000000000,
+        ],
+    ),
+    (
+        "vesta",
+        "28948022309329048855892746252171976963363056481941647379679742748393362948097",
+        &[
+            0x8c46eb2100000001,
+            0x224698fc0994a8dd,
+            0,
+            0x4000000000000000,
+        ],
+    ),
+    (
+        "secq256r1",
+        "115792089210356248762697446949407573530086143415290314195533631308867097853951",
+        &[0xffffffffffffffff, 0xffffffff, 0, 0xffffffff00000001],
+    ),
+];
+
+/// The `circom --prime` name of the field in the field arithmetic that
+/// `circom --c` writes next to the circuit, `fr.cpp`, `fr.hpp` and `fr.asm`.
+///
+/// The modulus is found either in decimal or as consecutive hexadecimal limbs.
+/// When several primes match, for example Goldilocks is the lowest limb of
+/// BLS12-381, the one with the most limbs is taken.
+pub fn detect_prime(source: &str) -> Option<&'static str> {
+    let words: Vec<&str> = source
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.trim_end_matches(['u', 'U', 'l', 'L']))
+        .collect();
+    let limbs: Vec<Option<u64>> = words
+        .iter()
+        .map(|word| {
+            let hex = word
+                .strip_prefix("0x")
+                .or_else(|| word.strip_prefix("0X"))?;
+            u64::from_str_radix(hex, 16).ok()
+        })
+        .collect();
+    PRIMES
+        .into_iter()
+        .filter(|(_, decimal, modulus)| {
+            words.contains(decimal)
+                || limbs
+                    .windows(modulus.len())
+                    .any(|window| window.iter().copied().eq(modulus.iter().copied().map(Some)))
+        })
+        .max_by_key(|(_, _, modulus)| modulus.len())
+        .map(|(name, _, _)| name)
+}
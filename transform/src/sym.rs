@@ -0,0 +1,90 @@
+//! Parser for the symbol file written by `circom --sym`.
+
+use std::collections::HashMap;
+
+/// Signals of the main component as `(signal index, name)`, with the `main.`
+/// prefix removed from the names.
+///
+/// Every line of the file is `signal,witness,component,name`, the main
+/// component has index 0.
+pub fn main_signals(sym: &str) -> Result<Vec<(usize, String)>, String> {
+    let mut signals = Vec::new();
+    for (i, line) in sym.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let error = || format!("line {}: invalid symbol {line:?}", i + 1);
+        let mut fields = line.splitn(4, ',');
+        let mut number = || fields.next().and_then(|f| f.parse::<i64>().ok());
+        let (Some(signal), Some(_witness), Some(component)) = (number(), number(), number()) else {
+            return Err(error());
+        };
+        let name = fields.next().ok_or_else(error)?;
+        if component != 0 {
+            continue;
+        }
+        let signal = usize::try_from(signal).map_err(|_| error())?;
+        let name = name.strip_prefix("main.").ok_or_else(error)?;
+        signals.push((signal, name.to_string()));
+    }
+    Ok(signals)
+}
+
+/// The name of the input that a signal belongs to, with the array indices at
+/// the end removed. Indices of bus arrays stay, `p[1].x[2]` is `p[1].x`.
+pub fn input_name(signal: &str) -> &str {
+    let mut name = signal;
+    while let Some(open) = name.strip_suffix(']').and_then(|n| n.rfind('[')) {
+        name = &name[..open];
+    }
+    name
+}
+
+/// A signal of the main component with all of its elements
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalArray {
+    /// Name without the trailing indices, see [`input_name`]
+    pub name: String,
+    /// Signal index of the first element
+    pub first: usize,
+    /// Dimensions, empty for a single signal
+    pub shape: Vec<usize>,
+}
+
+/// Groups the elements of the signals from [`main_signals`] into arrays, in
+/// the order of their first element. Each dimension is the largest index
+/// plus one.
+pub fn signal_arrays(signals: &[(usize, String)]) -> Result<Vec<SignalArray>, String> {
+    let mut arrays: Vec<SignalArray> = Vec::new();
+    let mut positions = HashMap::new();
+    for (signal, full_name) in signals {
+        let name = input_name(full_name);
+        let indices = full_name[name.len()..]
+            .split_terminator(']')
+            .map(|index| index.strip_prefix('[')?.parse::<usize>().ok())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| format!("invalid index in signal {full_name:?}"))?;
+        let position = *positions.entry(name).or_insert_with(|| {
+            arrays.push(SignalArray {
+                name: name.to_string(),
+                first: *signal,
+                shape: vec![0; indices.len()],
+            });
+            arrays.len() - 1
+        });
+        let array = &mut arrays[position];
+        if array.shape.len() != indices.len() {
+            return Err(format!(
+                "signal {full_name:?} has {} indices, other elements of {name:?} have {}",
+                indices.len(),
+                array.shape.len()
+            ));
+        }
+        array.first = array.first.min(*signal);
+        for (length, index) in array.shape.iter_mut().zip(indices) {
+            *length = (*length).max(index + 1);
+        }
+    }
+    arrays.sort_by_key(|array| array.first);
+    Ok(arrays)
+}
@@ -0,0 +1,60 @@
+//! Versions of the circom compiler.
+
+use std::{fmt, ops::RangeInclusive, str::FromStr};
+
+/// A circom compiler version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CircomVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+/// Versions whose output the transformation is tested with, see the fixtures
+pub const SUPPORTED_VERSIONS: RangeInclusive<CircomVersion> =
+    CircomVersion::new(2, 1, 6)..=CircomVersion::new(2, 2, 2);
+
+impl CircomVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        CircomVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parses the output of `circom --version`, for example
+    /// `circom compiler 2.2.2`
+    pub fn from_output(output: &str) -> Option<Self> {
+        output.split_whitespace().find_map(|word| word.parse().ok())
+    }
+
+    pub fn is_supported(self) -> bool {
+        SUPPORTED_VERSIONS.contains(&self)
+    }
+}
+
+impl fmt::Display for CircomVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Parses `major.minor.patch`
+impl FromStr for CircomVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("invalid circom version {s:?}");
+        let mut parts = s.split('.').map(|p| p.parse::<u32>().map_err(|_| error()));
+        let version = CircomVersion::new(
+            parts.next().ok_or_else(error)??,
+            parts.next().ok_or_else(error)??,
+            parts.next().ok_or_else(error)??,
+        );
+        if parts.next().is_some() {
+            return Err(error());
+        }
+        Ok(version)
+    }
+}
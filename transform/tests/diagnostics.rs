@@ -0,0 +1,66 @@
+use circom_witness_transform::{transform, Diagnostic};
+
+fn diagnose(source: &str) -> Diagnostic {
+    transform(source, "circuit_test").unwrap_err()
+}
+
+#[test]
+fn unsupported_context_member() {
+    let diagnostic = diagnose("void run(Circom_CalcWit* ctx){\nuint n = ctx->numThread;\n}\n");
+    assert_eq!((diagnostic.line, diagnostic.column), (2, 15));
+    assert_eq!(
+        diagnostic.message,
+        "unsupported context member `ctx->numThread`"
+    );
+    assert_eq!(diagnostic.source_line, "uint n = ctx->numThread;");
+}
+
+#[test]
+fn parallel_components() {
+    let diagnostic = diagnose("std::thread t(Square_0_run,i,ctx);\n");
+    assert_eq!(diagnostic.message, "unsupported `std::thread`");
+}
+
+#[test]
+fn unsupported_field_operation() {
+    let diagnostic = diagnose("{\nFr_bxor(&expaux[0],&lvar[0],&lvar[1]);\n}\n");
+    assert_eq!((diagnostic.line, diagnostic.column), (2, 1));
+    assert_eq!(diagnostic.message, "unsupported field operation `Fr_bxor`");
+}
+
+#[test]
+fn unsupported_allocation() {
+    let diagnostic = diagnose("ctx->componentMemory[0].outputIsSet = new bool[2]();\n");
+    assert_eq!(diagnostic.message, "unsupported allocation");
+}
+
+#[test]
+fn black_box_arguments() {
+    let diagnostic = diagnose("hash_bbf_0(ctx,lvarcall);\n");
+    assert_eq!(
+        diagnostic.message,
+        "unexpected arguments in call to `hash_bbf_0`"
+    );
+}
+
+#[test]
+fn removed_block() {
+    let diagnostic = diagnose("if (!mySubcomponentsParallel[0]) {\nrun(ctx);\n}\n");
+    assert_eq!(diagnostic.message, "cannot remove a block statement");
+}
+
+#[test]
+fn malformed_source() {
+    assert_eq!(diagnose("Fr_copy(a, b\n").message, "unterminated statement");
+    assert_eq!(diagnose("/* open").message, "unterminated comment");
+    assert_eq!(diagnose("x = \"open;\n").message, "unterminated literal");
+}
+
+#[test]
+fn display() {
+    let diagnostic = diagnose("\n  x = ctx->numThread;\n");
+    assert_eq!(
+        diagnostic.to_string(),
+        "2:12: unsupported context member `ctx->numThread`\n      x = ctx->numThread;"
+    );
+}
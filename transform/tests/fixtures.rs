@@ -0,0 +1,39 @@
+//! Translates circom output of several versions and compares it with the
+//! expected translation unit next to it. Set `BLESS=1` to update them.
+
+use std::{env, fs, path::Path};
+
+use circom_witness_transform::{transform, CircomVersion};
+
+#[test]
+fn fixtures() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut count = 0;
+    for version in fs::read_dir(fixtures).unwrap() {
+        let version = version.unwrap().path();
+        let name = version.file_name().unwrap().to_str().unwrap();
+        let circom: CircomVersion = name.strip_prefix("circom-").unwrap().parse().unwrap();
+        assert!(
+            circom.is_supported(),
+            "fixtures of unsupported circom {circom}"
+        );
+        for entry in fs::read_dir(&version).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|e| e != "cpp") {
+                continue;
+            }
+            let source = fs::read_to_string(&path).unwrap();
+            let actual = transform(&source, "circuit_test")
+                .unwrap_or_else(|e| panic!("{}:{e}", path.display()));
+            let expected = path.with_extension("cc");
+            if env::var_os("BLESS").is_some() {
+                fs::write(&expected, actual).unwrap();
+            } else {
+                let expected = fs::read_to_string(&expected).unwrap();
+                assert!(actual == expected, "{} changed", path.display());
+            }
+            count += 1;
+        }
+    }
+    assert!(count >= 3);
+}
@@ -0,0 +1,125 @@
+#include <stdio.h>
+#include <iostream>
+#include <assert.h>
+#include "circom.hpp"
+#include "calcwit.hpp"
+void Multiplier_0_create(uint soffset,uint coffset,Circom_CalcWit* ctx,std::string componentName,uint componentFather);
+void Multiplier_0_run(uint ctx_index,Circom_CalcWit* ctx);
+void square_0(Circom_CalcWit* ctx,FrElement* lvar,uint componentFather,FrElement* destination,int destination_size);
+Circom_TemplateFunction _functionTable[1] = { 
+Multiplier_0_run };
+Circom_TemplateFunction _functionTableParallel[1] = { 
+NULL };
+uint get_main_input_signal_start() {return 2;}
+
+uint get_main_input_signal_no() {return 2;}
+
+uint get_total_signal_no() {return 4;}
+
+uint get_number_of_components() {return 1;}
+
+uint get_size_of_input_hashmap() {return 256;}
+
+uint get_size_of_witness() {return 4;}
+
+uint get_size_of_constants() {return 1;}
+
+uint get_size_of_io_map() {return 0;}
+
+void release_memory_component(Circom_CalcWit* ctx, uint pos) {{
+
+if (pos != 0){{
+
+if(ctx->componentMemory[pos].subcomponents)
+delete []ctx->componentMemory[pos].subcomponents;
+
+if(ctx->componentMemory[pos].subcomponentsParallel)
+delete []ctx->componentMemory[pos].subcomponentsParallel;
+
+if(ctx->componentMemory[pos].outputIsSet)
+delete []ctx->componentMemory[pos].outputIsSet;
+
+if(ctx->componentMemory[pos].mutexes)
+delete []ctx->componentMemory[pos].mutexes;
+
+if(ctx->componentMemory[pos].cvs)
+delete []ctx->componentMemory[pos].cvs;
+
+if(ctx->componentMemory[pos].sbct)
+delete []ctx->componentMemory[pos].sbct;
+
+}}
+
+
+}}
+
+
+// function declarations
+void square_0(Circom_CalcWit* ctx,FrElement* lvar,uint componentFather,FrElement* destination,int destination_size){
+FrElement* circuitConstants = ctx->circuitConstants;
+FrElement expaux[1];
+std::string myTemplateName = "square";
+u64 myId = componentFather;
+// return bucket
+Fr_mul(&expaux[0],&lvar[0],&lvar[0]); // line circom 4
+Fr_copy(destination,&expaux[0]);
+return;
+}
+
+// template declarations
+void Multiplier_0_create(uint soffset,uint coffset,Circom_CalcWit* ctx,std::string componentName,uint componentFather){
+ctx->componentMemory[coffset].templateId = 0;
+ctx->componentMemory[coffset].templateName = "Multiplier";
+ctx->componentMemory[coffset].signalStart = soffset;
+ctx->componentMemory[coffset].inputCounter = 2;
+ctx->componentMemory[coffset].componentName = componentName;
+ctx->componentMemory[coffset].idFather = componentFather;
+ctx->componentMemory[coffset].subcomponents = new uint[0];
+}
+
+void Multiplier_0_run(uint ctx_index,Circom_CalcWit* ctx){
+FrElement* signalValues = ctx->signalValues;
+u64 mySignalStart = ctx->componentMemory[ctx_index].signalStart;
+std::string myTemplateName = ctx->componentMemory[ctx_index].templateName;
+std::string myComponentName = ctx->componentMemory[ctx_index].componentName;
+u64 myFather = ctx->componentMemory[ctx_index].idFather;
+u64 myId = ctx_index;
+u32* mySubcomponents = ctx->componentMemory[ctx_index].subcomponents;
+bool* mySubcomponentsParallel = ctx->componentMemory[ctx_index].subcomponentsParallel;
+FrElement* circuitConstants = ctx->circuitConstants;
+std::string* listOfTemplateMessages = ctx->listOfTemplateMessages;
+FrElement expaux[2];
+FrElement lvar[0];
+uint sub_component_aux;
+uint index_multiple_eq;
+{
+PFrElement aux_dest = &signalValues[mySignalStart + 0];
+// load src
+{
+// start of call bucket
+FrElement lvarcall[1];
+// copying argument 0
+Fr_copy(&lvarcall[0],&signalValues[mySignalStart + 1]);
+// end copying argument 0
+square_0(ctx,lvarcall,myId,&expaux[0],1);
+// end call bucket
+}
+
+Fr_mul(&expaux[1],&expaux[0],&signalValues[mySignalStart + 2]); // line circom 12
+// end load src
+Fr_copy(aux_dest,&expaux[1]);
+}
+Fr_neq(&expaux[0],&signalValues[mySignalStart + 2],&circuitConstants[0]); // line circom 13
+if (!Fr_isTrue(&expaux[0])) std::cout << "Failed assert in template/function " << myTemplateName << " line 13. " <<  "Followed trace of components: " << ctx->getTrace(myId) << std::endl;
+assert(Fr_isTrue(&expaux[0]));
+for (uint i = 0; i < 0; i++){
+uint index_subc = ctx->componentMemory[ctx_index].subcomponents[i];
+if (index_subc != 0)release_memory_component(ctx,index_subc);
+}
+}
+
+void run(Circom_CalcWit* ctx){
+Multiplier_0_create(1,0,ctx,"main",0);
+Multiplier_0_run(0,ctx);
+}
+
@@ -0,0 +1,150 @@
+#include "witness/include/witness.h"
+#include "witness/src/generate.rs.h"
+
+namespace circuit_test {
+
+/// We need this accessor since cxx doesn't support hashmaps yet
+class IOSignalInfoAccessor {
+private:
+  Circom_CalcWit *calcWitContext;
+
+public:
+  explicit IOSignalInfoAccessor(Circom_CalcWit *calcWit)
+      : calcWitContext(calcWit) {}
+  auto operator[](size_t index) const -> decltype(auto) {
+    return (calcWitContext
+                ->templateInsId2IOSignalInfoList)[index];
+  }
+};
+
+typedef void (*Circom_TemplateFunction)(uint __cIdx, Circom_CalcWit* __ctx);
+
+//////////////////////////////////////////////////////////////////
+/// Generated code from circom compiler below
+//////////////////////////////////////////////////////////////////
+
+void Pair_0_create(uint soffset,uint coffset,Circom_CalcWit* ctx,rust::string componentName,uint componentFather);
+void Pair_0_run(uint ctx_index,Circom_CalcWit* ctx);
+void swap_0(Circom_CalcWit* ctx,rust::Vec<FrElement>& lvar,uint componentFather,FrElement* destination,int destination_size);
+Circom_TemplateFunction _functionTable[1] = { 
+Pair_0_run };
+Circom_TemplateFunction _functionTableParallel[1] = { 
+NULL };
+uint get_main_input_signal_start() {return 3;}
+
+uint get_main_input_signal_no() {return 2;}
+
+uint get_total_signal_no() {return 5;}
+
+uint get_number_of_components() {return 1;}
+
+uint get_size_of_input_hashmap() {return 256;}
+
+uint get_size_of_witness() {return 5;}
+
+uint get_size_of_constants() {return 2;}
+
+uint get_size_of_io_map() {return 0;}
+
+void release_memory_component(Circom_CalcWit* ctx, uint pos) {{
+
+if (pos != 0){{
+
+
+
+
+
+
+
+}}
+
+
+}}
+
+
+// function declarations
+void swap_0(Circom_CalcWit* ctx,rust::Vec<FrElement>& lvar,uint componentFather,FrElement* destination,int destination_size){
+rust::Vec<FrElement> &circuitConstants = ctx->circuitConstants;
+rust::Vec<FrElement> expaux = create_vec(1);
+rust::string myTemplateName = "swap";
+u64 myId = componentFather;
+{
+FrElement* aux_dest = &lvar[2];
+// load src
+// end load src
+Fr_copy(aux_dest,&lvar[1]);
+}
+{
+FrElement* aux_dest = &lvar[3];
+// load src
+// end load src
+Fr_copy(aux_dest,&lvar[0]);
+}
+// return bucket
+Fr_copyn(destination,&lvar[2],destination_size);
+return;
+}
+
+// template declarations
+void Pair_0_create(uint soffset,uint coffset,Circom_CalcWit* ctx,rust::string componentName,uint componentFather){
+ctx->componentMemory[coffset].templateId = 0;
+ctx->componentMemory[coffset].templateName = "Pair";
+ctx->componentMemory[coffset].signalStart = soffset;
+ctx->componentMemory[coffset].inputCounter = 2;
+ctx->componentMemory[coffset].componentName = componentName;
+ctx->componentMemory[coffset].idFather = componentFather;
+ctx->componentMemory[coffset].subcomponents = create_vec_u32(0);
+}
+
+void Pair_0_run(uint ctx_index,Circom_CalcWit* ctx){
+rust::Vec<FrElement> &signalValues = ctx->signalValues;
+u64 mySignalStart = ctx->componentMemory[ctx_index].signalStart;
+rust::string myTemplateName = ctx->componentMemory[ctx_index].templateName;
+rust::string myComponentName = ctx->componentMemory[ctx_index].componentName;
+u64 myFather = ctx->componentMemory[ctx_index].idFather;
+u64 myId = ctx_index;
+rust::Vec<u32> mySubcomponents = ctx->componentMemory[ctx_index].subcomponents;
+rust::Vec<FrElement> &circuitConstants = ctx->circuitConstants;
+rust::Vec<rust::string> &listOfTemplateMessages = ctx->listOfTemplateMessages;
+rust::Vec<FrElement> expaux = create_vec(2);
+rust::Vec<FrElement> lvar = create_vec(1);
+uint sub_component_aux;
+uint index_multiple_eq;
+int cmp_index_ref_load = -1;
+{
+FrElement* aux_dest = &lvar[0];
+// load src
+// end load src
+Fr_copy(aux_dest,&circuitConstants[0]);
+}
+Fr_lt(&expaux[0],&lvar[0],&circuitConstants[1]); // line circom 10
+while(Fr_isTrue(&expaux[0])){
+Fr_add(&expaux[0],&lvar[0],&circuitConstants[1]); // line circom 10
+Fr_copy(&lvar[0],&expaux[0]);
+Fr_lt(&expaux[0],&lvar[0],&circuitConstants[1]); // line circom 10
+}
+{
+// start of call bucket
+rust::Vec<FrElement> lvarcall = create_vec(4);
+// copying argument 0
+Fr_copyn(&lvarcall[0],&signalValues[mySignalStart + 3],2);
+// end copying argument 0
+swap_0(ctx,lvarcall,myId,&signalValues[mySignalStart + 0],2);
+// end call bucket
+}
+
+for (uint i = 0; i < 0; i++){
+uint index_subc = ctx->componentMemory[ctx_index].subcomponents[i];
+if (index_subc != 0)release_memory_component(ctx,index_subc);
+}
+}
+
+void run(Circom_CalcWit* ctx){
+Pair_0_create(1,0,ctx,"main",0);
+Pair_0_run(0,ctx);
+}
+
+
+uint get_size_of_bus_field_map() {return 0;}
+
+} // namespace circuit_test
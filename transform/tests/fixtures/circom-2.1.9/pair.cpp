@@ -0,0 +1,139 @@
+#include <stdio.h>
+#include <iostream>
+#include <assert.h>
+#include "circom.hpp"
+#include "calcwit.hpp"
+void Pair_0_create(uint soffset,uint coffset,Circom_CalcWit* ctx,std::string componentName,uint componentFather);
+void Pair_0_run(uint ctx_index,Circom_CalcWit* ctx);
+void swap_0(Circom_CalcWit* ctx,FrElement* lvar,uint componentFather,FrElement* destination,int destination_size);
+Circom_TemplateFunction _functionTable[1] = { 
+Pair_0_run };
+Circom_TemplateFunction _functionTableParallel[1] = { 
+NULL };
+uint get_main_input_signal_start() {return 3;}
+
+uint get_main_input_signal_no() {return 2;}
+
+uint get_total_signal_no() {return 5;}
+
+uint get_number_of_components() {return 1;}
+
+uint get_size_of_input_hashmap() {return 256;}
+
+uint get_size_of_witness() {return 5;}
+
+uint get_size_of_constants() {return 2;}
+
+uint get_size_of_io_map() {return 0;}
+
+void release_memory_component(Circom_CalcWit* ctx, uint pos) {{
+
+if (pos != 0){{
+
+if(ctx->componentMemory[pos].subcomponents)
+delete []ctx->componentMemory[pos].subcomponents;
+
+if(ctx->componentMemory[pos].subcomponentsParallel)
+delete []ctx->componentMemory[pos].subcomponentsParallel;
+
+if(ctx->componentMemory[pos].outputIsSet)
+delete []ctx->componentMemory[pos].outputIsSet;
+
+if(ctx->componentMemory[pos].mutexes)
+delete []ctx->componentMemory[pos].mutexes;
+
+if(ctx->componentMemory[pos].cvs)
+delete []ctx->componentMemory[pos].cvs;
+
+if(ctx->componentMemory[pos].sbct)
+delete []ctx->componentMemory[pos].sbct;
+
+}}
+
+
+}}
+
+
+// function declarations
+void swap_0(Circom_CalcWit* ctx,FrElement* lvar,uint componentFather,FrElement* destination,int destination_size){
+FrElement* circuitConstants = ctx->circuitConstants;
+FrElement expaux[1];
+std::string myTemplateName = "swap";
+u64 myId = componentFather;
+{
+PFrElement aux_dest = &lvar[2];
+// load src
+// end load src
+Fr_copy(aux_dest,&lvar[1]);
+}
+{
+PFrElement aux_dest = &lvar[3];
+// load src
+// end load src
+Fr_copy(aux_dest,&lvar[0]);
+}
+// return bucket
+Fr_copyn(destination,&lvar[2],destination_size);
+return;
+}
+
+// template declarations
+void Pair_0_create(uint soffset,uint coffset,Circom_CalcWit* ctx,std::string componentName,uint componentFather){
+ctx->componentMemory[coffset].templateId = 0;
+ctx->componentMemory[coffset].templateName = "Pair";
+ctx->componentMemory[coffset].signalStart = soffset;
+ctx->componentMemory[coffset].inputCounter = 2;
+ctx->componentMemory[coffset].componentName = componentName;
+ctx->componentMemory[coffset].idFather = componentFather;
+ctx->componentMemory[coffset].subcomponents = new uint[0];
+}
+
+void Pair_0_run(uint ctx_index,Circom_CalcWit* ctx){
+FrElement* signalValues = ctx->signalValues;
+u64 mySignalStart = ctx->componentMemory[ctx_index].signalStart;
+std::string myTemplateName = ctx->componentMemory[ctx_index].templateName;
+std::string myComponentName = ctx->componentMemory[ctx_index].componentName;
+u64 myFather = ctx->componentMemory[ctx_index].idFather;
+u64 myId = ctx_index;
+u32* mySubcomponents = ctx->componentMemory[ctx_index].subcomponents;
+bool* mySubcomponentsParallel = ctx->componentMemory[ctx_index].subcomponentsParallel;
+FrElement* circuitConstants = ctx->circuitConstants;
+std::string* listOfTemplateMessages = ctx->listOfTemplateMessages;
+FrElement expaux[2];
+FrElement lvar[1];
+uint sub_component_aux;
+uint index_multiple_eq;
+int cmp_index_ref_load = -1;
+{
+PFrElement aux_dest = &lvar[0];
+// load src
+// end load src
+Fr_copy(aux_dest,&circuitConstants[0]);
+}
+Fr_lt(&expaux[0],&lvar[0],&circuitConstants[1]); // line circom 10
+while(Fr_isTrue(&expaux[0])){
+Fr_add(&expaux[0],&lvar[0],&circuitConstants[1]); // line circom 10
+Fr_copy(&lvar[0],&expaux[0]);
+Fr_lt(&expaux[0],&lvar[0],&circuitConstants[1]); // line circom 10
+}
+{
+// start of call bucket
+FrElement lvarcall[4];
+// copying argument 0
+Fr_copyn(&lvarcall[0],&signalValues[mySignalStart + 3],2);
+// end copying argument 0
+swap_0(ctx,lvarcall,myId,&signalValues[mySignalStart + 0],2);
+// end call bucket
+}
+
+for (uint i = 0; i < 0; i++){
+uint index_subc = ctx->componentMemory[ctx_index].subcomponents[i];
+if (index_subc != 0)release_memory_component(ctx,index_subc);
+}
+}
+
+void run(Circom_CalcWit* ctx){
+Pair_0_create(1,0,ctx,"main",0);
+Pair_0_run(0,ctx);
+}
+
@@ -0,0 +1,215 @@
+#include "witness/include/witness.h"
+#include "witness/src/generate.rs.h"
+
+namespace circuit_test {
+
+/// We need this accessor since cxx doesn't support hashmaps yet
+class IOSignalInfoAccessor {
+private:
+  Circom_CalcWit *calcWitContext;
+
+public:
+  explicit IOSignalInfoAccessor(Circom_CalcWit *calcWit)
+      : calcWitContext(calcWit) {}
+  auto operator[](size_t index) const -> decltype(auto) {
+    return (calcWitContext
+                ->templateInsId2IOSignalInfoList)[index];
+  }
+};
+
+typedef void (*Circom_TemplateFunction)(uint __cIdx, Circom_CalcWit* __ctx);
+
+//////////////////////////////////////////////////////////////////
+/// Generated code from circom compiler below
+//////////////////////////////////////////////////////////////////
+
+void Square_0_create(uint soffset,uint coffset,Circom_CalcWit* ctx,rust::string componentName,uint componentFather);
+void Square_0_run(uint ctx_index,Circom_CalcWit* ctx);
+void Hasher_1_create(uint soffset,uint coffset,Circom_CalcWit* ctx,rust::string componentName,uint componentFather);
+void Hasher_1_run(uint ctx_index,Circom_CalcWit* ctx);
+void hash_bbf_0(Circom_CalcWit* ctx,rust::Vec<FrElement>& lvar,uint componentFather,FrElement* destination,int destination_size);
+Circom_TemplateFunction _functionTable[2] = { 
+Square_0_run,
+Hasher_1_run };
+Circom_TemplateFunction _functionTableParallel[2] = { 
+NULL,
+NULL };
+uint get_main_input_signal_start() {return 2;}
+
+uint get_main_input_signal_no() {return 2;}
+
+uint get_total_signal_no() {return 7;}
+
+uint get_number_of_components() {return 3;}
+
+uint get_size_of_input_hashmap() {return 256;}
+
+uint get_size_of_witness() {return 7;}
+
+uint get_size_of_constants() {return 2;}
+
+uint get_size_of_io_map() {return 1;}
+
+uint get_size_of_bus_field_map() {return 0;}
+
+void release_memory_component(Circom_CalcWit* ctx, uint pos) {{
+
+if (pos != 0){{
+
+
+
+
+
+
+
+}}
+
+
+}}
+
+
+// function declarations
+void hash_bbf_0(Circom_CalcWit* ctx,rust::Vec<FrElement>& lvar,uint componentFather,FrElement* destination,int destination_size){
+rust::Vec<FrElement> &circuitConstants = ctx->circuitConstants;
+rust::Vec<FrElement> expaux = create_vec(1);
+rust::string myTemplateName = "hash_bbf";
+u64 myId = componentFather;
+// return bucket
+Fr_add(&expaux[0],&lvar[0],&lvar[1]); // line circom 3
+Fr_copy(destination,&expaux[0]);
+return;
+}
+
+// template declarations
+void Square_0_create(uint soffset,uint coffset,Circom_CalcWit* ctx,rust::string componentName,uint componentFather){
+ctx->componentMemory[coffset].templateId = 0;
+ctx->componentMemory[coffset].templateName = "Square";
+ctx->componentMemory[coffset].signalStart = soffset;
+ctx->componentMemory[coffset].inputCounter = 1;
+ctx->componentMemory[coffset].componentName = componentName;
+ctx->componentMemory[coffset].idFather = componentFather;
+ctx->componentMemory[coffset].subcomponents = create_vec_u32(0);
+}
+
+void Square_0_run(uint ctx_index,Circom_CalcWit* ctx){
+rust::Vec<FrElement> &signalValues = ctx->signalValues;
+u64 mySignalStart = ctx->componentMemory[ctx_index].signalStart;
+rust::string myTemplateName = ctx->componentMemory[ctx_index].templateName;
+rust::string myComponentName = ctx->componentMemory[ctx_index].componentName;
+u64 myFather = ctx->componentMemory[ctx_index].idFather;
+u64 myId = ctx_index;
+rust::Vec<u32> mySubcomponents = ctx->componentMemory[ctx_index].subcomponents;
+rust::Vec<FrElement> &circuitConstants = ctx->circuitConstants;
+rust::Vec<rust::string> &listOfTemplateMessages = ctx->listOfTemplateMessages;
+rust::Vec<FrElement> expaux = create_vec(1);
+rust::Vec<FrElement> lvar = create_vec(0);
+uint sub_component_aux;
+uint index_multiple_eq;
+int cmp_index_ref_load = -1;
+{
+FrElement* aux_dest = &signalValues[mySignalStart + 0];
+// load src
+Fr_mul(&expaux[0],&signalValues[mySignalStart + 1],&signalValues[mySignalStart + 1]); // line circom 8
+// end load src
+Fr_copy(aux_dest,&expaux[0]);
+}
+{
+}
+for (uint i = 0; i < 0; i++){
+uint index_subc = ctx->componentMemory[ctx_index].subcomponents[i];
+if (index_subc != 0){
+release_memory_component(ctx,index_subc);
+}
+}
+}
+
+void Hasher_1_create(uint soffset,uint coffset,Circom_CalcWit* ctx,rust::string componentName,uint componentFather){
+ctx->componentMemory[coffset].templateId = 1;
+ctx->componentMemory[coffset].templateName = "Hasher";
+ctx->componentMemory[coffset].signalStart = soffset;
+ctx->componentMemory[coffset].inputCounter = 2;
+ctx->componentMemory[coffset].componentName = componentName;
+ctx->componentMemory[coffset].idFather = componentFather;
+ctx->componentMemory[coffset].subcomponents = create_vec_u32(2);
+}
+
+void Hasher_1_run(uint ctx_index,Circom_CalcWit* ctx){
+rust::Vec<FrElement> &signalValues = ctx->signalValues;
+u64 mySignalStart = ctx->componentMemory[ctx_index].signalStart;
+rust::string myTemplateName = ctx->componentMemory[ctx_index].templateName;
+rust::string myComponentName = ctx->componentMemory[ctx_index].componentName;
+u64 myFather = ctx->componentMemory[ctx_index].idFather;
+u64 myId = ctx_index;
+rust::Vec<u32> mySubcomponents = ctx->componentMemory[ctx_index].subcomponents;
+rust::Vec<FrElement> &circuitConstants = ctx->circuitConstants;
+rust::Vec<rust::string> &listOfTemplateMessages = ctx->listOfTemplateMessages;
+rust::Vec<FrElement> expaux = create_vec(2);
+rust::Vec<FrElement> lvar = create_vec(1);
+uint sub_component_aux;
+uint index_multiple_eq;
+int cmp_index_ref_load = -1;
+{
+uint aux_create = 0;
+int aux_cmp_num = 0+ctx_index+1;
+uint csoffset = mySignalStart+4;
+rust::Vec<uint32_t> aux_dimensions = rust::Vec<uint32_t>{2};
+for (uint i = 0; i < 2; i++) {
+rust::string new_cmp_name = generate_position_array("squares", aux_dimensions, 1, i);
+Square_0_create(csoffset,aux_cmp_num,ctx,new_cmp_name,myId);
+mySubcomponents[aux_create+ i] = aux_cmp_num;
+csoffset += 2 ;
+aux_cmp_num += 1;
+}
+}
+{
+FrElement* aux_dest = &lvar[0];
+// load src
+// end load src
+Fr_copy(aux_dest,&circuitConstants[0]);
+}
+for (uint i = 0; i < 2; i++){
+{
+uint cmp_index_ref = ((1 * Fr_toInt(&lvar[0])) + 0);
+{
+FrElement* aux_dest = &signalValues[mySignalStart + ((1 * Fr_toInt(&lvar[0])) + 0)];
+// load src
+cmp_index_ref_load = ((1 * Fr_toInt(&lvar[0])) + 0);
+// end load src
+Fr_copy(aux_dest,&ctx->signalValues[ctx->componentMemory[mySubcomponents[cmp_index_ref_load]].signalStart + IOSignalInfoAccessor(ctx)[ctx->componentMemory[mySubcomponents[cmp_index_ref_load]].templateId].defs[0].offset]);
+}
+}
+}
+{
+FrElement* aux_dest = &signalValues[mySignalStart + 0];
+// load src
+{
+// start of call bucket
+rust::Vec<FrElement> lvarcall = create_vec(2);
+// copying argument 0
+Fr_copy(&lvarcall[0],&signalValues[mySignalStart + 2]);
+// end copying argument 0
+// copying argument 1
+Fr_copy(&lvarcall[1],&signalValues[mySignalStart + 3]);
+// end copying argument 1
+bbf("hash_bbf_0", lvarcall, &expaux[0]);
+// end call bucket
+}
+
+// end load src
+Fr_copy(aux_dest,&expaux[0]);
+}
+for (uint i = 0; i < 2; i++){
+uint index_subc = ctx->componentMemory[ctx_index].subcomponents[i];
+if (index_subc != 0){
+release_memory_component(ctx,index_subc);
+}
+}
+}
+
+void run(Circom_CalcWit* ctx){
+Hasher_1_create(1,0,ctx,"main",0);
+Hasher_1_run(0,ctx);
+}
+
+
+} // namespace circuit_test
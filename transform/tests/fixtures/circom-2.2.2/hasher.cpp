@@ -0,0 +1,213 @@
+#include <stdio.h>
+#include <iostream>
+#include <assert.h>
+#include "circom.hpp"
+#include "calcwit.hpp"
+void Square_0_create(uint soffset,uint coffset,Circom_CalcWit* ctx,std::string componentName,uint componentFather);
+void Square_0_run(uint ctx_index,Circom_CalcWit* ctx);
+void Hasher_1_create(uint soffset,uint coffset,Circom_CalcWit* ctx,std::string componentName,uint componentFather);
+void Hasher_1_run(uint ctx_index,Circom_CalcWit* ctx);
+void hash_bbf_0(Circom_CalcWit* ctx,FrElement* lvar,uint componentFather,FrElement* destination,int destination_size);
+Circom_TemplateFunction _functionTable[2] = { 
+Square_0_run,
+Hasher_1_run };
+Circom_TemplateFunction _functionTableParallel[2] = { 
+NULL,
+NULL };
+uint get_main_input_signal_start() {return 2;}
+
+uint get_main_input_signal_no() {return 2;}
+
+uint get_total_signal_no() {return 7;}
+
+uint get_number_of_components() {return 3;}
+
+uint get_size_of_input_hashmap() {return 256;}
+
+uint get_size_of_witness() {return 7;}
+
+uint get_size_of_constants() {return 2;}
+
+uint get_size_of_io_map() {return 1;}
+
+uint get_size_of_bus_field_map() {return 0;}
+
+void release_memory_component(Circom_CalcWit* ctx, uint pos) {{
+
+if (pos != 0){{
+
+if(ctx->componentMemory[pos].subcomponents)
+delete []ctx->componentMemory[pos].subcomponents;
+
+if(ctx->componentMemory[pos].subcomponentsParallel)
+delete []ctx->componentMemory[pos].subcomponentsParallel;
+
+if(ctx->componentMemory[pos].outputIsSet)
+delete []ctx->componentMemory[pos].outputIsSet;
+
+if(ctx->componentMemory[pos].mutexes)
+delete []ctx->componentMemory[pos].mutexes;
+
+if(ctx->componentMemory[pos].cvs)
+delete []ctx->componentMemory[pos].cvs;
+
+if(ctx->componentMemory[pos].sbct)
+delete []ctx->componentMemory[pos].sbct;
+
+}}
+
+
+}}
+
+
+// function declarations
+void hash_bbf_0(Circom_CalcWit* ctx,FrElement* lvar,uint componentFather,FrElement* destination,int destination_size){
+FrElement* circuitConstants = ctx->circuitConstants;
+FrElement expaux[1];
+std::string myTemplateName = "hash_bbf";
+u64 myId = componentFather;
+// return bucket
+Fr_add(&expaux[0],&lvar[0],&lvar[1]); // line circom 3
+Fr_copy(destination,&expaux[0]);
+return;
+}
+
+// template declarations
+void Square_0_create(uint soffset,uint coffset,Circom_CalcWit* ctx,std::string componentName,uint componentFather){
+ctx->componentMemory[coffset].templateId = 0;
+ctx->componentMemory[coffset].templateName = "Square";
+ctx->componentMemory[coffset].signalStart = soffset;
+ctx->componentMemory[coffset].inputCounter = 1;
+ctx->componentMemory[coffset].componentName = componentName;
+ctx->componentMemory[coffset].idFather = componentFather;
+ctx->componentMemory[coffset].subcomponents = new uint[0];
+}
+
+void Square_0_run(uint ctx_index,Circom_CalcWit* ctx){
+FrElement* signalValues = ctx->signalValues;
+u64 mySignalStart = ctx->componentMemory[ctx_index].signalStart;
+std::string myTemplateName = ctx->componentMemory[ctx_index].templateName;
+std::string myComponentName = ctx->componentMemory[ctx_index].componentName;
+u64 myFather = ctx->componentMemory[ctx_index].idFather;
+u64 myId = ctx_index;
+u32* mySubcomponents = ctx->componentMemory[ctx_index].subcomponents;
+bool* mySubcomponentsParallel = ctx->componentMemory[ctx_index].subcomponentsParallel;
+FrElement* circuitConstants = ctx->circuitConstants;
+std::string* listOfTemplateMessages = ctx->listOfTemplateMessages;
+FrElement expaux[1];
+FrElement lvar[0];
+uint sub_component_aux;
+uint index_multiple_eq;
+int cmp_index_ref_load = -1;
+{
+PFrElement aux_dest = &signalValues[mySignalStart + 0];
+// load src
+Fr_mul(&expaux[0],&signalValues[mySignalStart + 1],&signalValues[mySignalStart + 1]); // line circom 8
+// end load src
+Fr_copy(aux_dest,&expaux[0]);
+}
+{
+char* temp = Fr_element2str(&signalValues[mySignalStart + 0]);
+printf("%s",temp);
+delete [] temp;
+}
+printf("\n");
+for (uint i = 0; i < 0; i++){
+uint index_subc = ctx->componentMemory[ctx_index].subcomponents[i];
+if (index_subc != 0){
+assert(!(ctx->componentMemory[index_subc].subcomponentsParallel));
+release_memory_component(ctx,index_subc);
+}
+}
+}
+
+void Hasher_1_create(uint soffset,uint coffset,Circom_CalcWit* ctx,std::string componentName,uint componentFather){
+ctx->componentMemory[coffset].templateId = 1;
+ctx->componentMemory[coffset].templateName = "Hasher";
+ctx->componentMemory[coffset].signalStart = soffset;
+ctx->componentMemory[coffset].inputCounter = 2;
+ctx->componentMemory[coffset].componentName = componentName;
+ctx->componentMemory[coffset].idFather = componentFather;
+ctx->componentMemory[coffset].subcomponents = new uint[2]{0};
+}
+
+void Hasher_1_run(uint ctx_index,Circom_CalcWit* ctx){
+FrElement* signalValues = ctx->signalValues;
+u64 mySignalStart = ctx->componentMemory[ctx_index].signalStart;
+std::string myTemplateName = ctx->componentMemory[ctx_index].templateName;
+std::string myComponentName = ctx->componentMemory[ctx_index].componentName;
+u64 myFather = ctx->componentMemory[ctx_index].idFather;
+u64 myId = ctx_index;
+u32* mySubcomponents = ctx->componentMemory[ctx_index].subcomponents;
+bool* mySubcomponentsParallel = ctx->componentMemory[ctx_index].subcomponentsParallel;
+FrElement* circuitConstants = ctx->circuitConstants;
+std::string* listOfTemplateMessages = ctx->listOfTemplateMessages;
+FrElement expaux[2];
+FrElement lvar[1];
+uint sub_component_aux;
+uint index_multiple_eq;
+int cmp_index_ref_load = -1;
+{
+uint aux_create = 0;
+int aux_cmp_num = 0+ctx_index+1;
+uint csoffset = mySignalStart+4;
+uint aux_dimensions[1] = {2};
+for (uint i = 0; i < 2; i++) {
+std::string new_cmp_name = "squares"+ctx->generate_position_array(aux_dimensions, 1, i);
+Square_0_create(csoffset,aux_cmp_num,ctx,new_cmp_name,myId);
+mySubcomponents[aux_create+ i] = aux_cmp_num;
+csoffset += 2 ;
+aux_cmp_num += 1;
+}
+}
+{
+PFrElement aux_dest = &lvar[0];
+// load src
+// end load src
+Fr_copy(aux_dest,&circuitConstants[0]);
+}
+for (uint i = 0; i < 2; i++){
+{
+uint cmp_index_ref = ((1 * Fr_toInt(&lvar[0])) + 0);
+{
+PFrElement aux_dest = &signalValues[mySignalStart + ((1 * Fr_toInt(&lvar[0])) + 0)];
+// load src
+cmp_index_ref_load = ((1 * Fr_toInt(&lvar[0])) + 0);
+// end load src
+Fr_copy(aux_dest,&ctx->signalValues[ctx->componentMemory[mySubcomponents[cmp_index_ref_load]].signalStart + ctx->templateInsId2IOSignalInfo[ctx->componentMemory[mySubcomponents[cmp_index_ref_load]].templateId].defs[0].offset]);
+}
+}
+}
+{
+PFrElement aux_dest = &signalValues[mySignalStart + 0];
+// load src
+{
+// start of call bucket
+FrElement lvarcall[2];
+// copying argument 0
+Fr_copy(&lvarcall[0],&signalValues[mySignalStart + 2]);
+// end copying argument 0
+// copying argument 1
+Fr_copy(&lvarcall[1],&signalValues[mySignalStart + 3]);
+// end copying argument 1
+hash_bbf_0(ctx,lvarcall,myId,&expaux[0],1);
+// end call bucket
+}
+
+// end load src
+Fr_copy(aux_dest,&expaux[0]);
+}
+for (uint i = 0; i < 2; i++){
+uint index_subc = ctx->componentMemory[ctx_index].subcomponents[i];
+if (index_subc != 0){
+assert(!(ctx->componentMemory[index_subc].subcomponentsParallel));
+release_memory_component(ctx,index_subc);
+}
+}
+}
+
+void run(Circom_CalcWit* ctx){
+Hasher_1_create(1,0,ctx,"main",0);
+Hasher_1_run(0,ctx);
+}
+
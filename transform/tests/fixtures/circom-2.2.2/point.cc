@@ -0,0 +1,186 @@
+#include "witness/include/witness.h"
+#include "witness/src/generate.rs.h"
+
+namespace circuit_test {
+
+/// We need this accessor since cxx doesn't support hashmaps yet
+class IOSignalInfoAccessor {
+private:
+  Circom_CalcWit *calcWitContext;
+
+public:
+  explicit IOSignalInfoAccessor(Circom_CalcWit *calcWit)
+      : calcWitContext(calcWit) {}
+  auto operator[](size_t index) const -> decltype(auto) {
+    return (calcWitContext
+                ->templateInsId2IOSignalInfoList)[index];
+  }
+};
+
+typedef void (*Circom_TemplateFunction)(uint __cIdx, Circom_CalcWit* __ctx);
+
+//////////////////////////////////////////////////////////////////
+/// Generated code from circom compiler below
+//////////////////////////////////////////////////////////////////
+
+void Double_0_create(uint soffset,uint coffset,Circom_CalcWit* ctx,rust::string componentName,uint componentFather);
+void Double_0_run(uint ctx_index,Circom_CalcWit* ctx);
+void Main_1_create(uint soffset,uint coffset,Circom_CalcWit* ctx,rust::string componentName,uint componentFather);
+void Main_1_run(uint ctx_index,Circom_CalcWit* ctx);
+Circom_TemplateFunction _functionTable[2] = { 
+Double_0_run,
+Main_1_run };
+Circom_TemplateFunction _functionTableParallel[2] = { 
+NULL,
+NULL };
+uint get_main_input_signal_start() {return 2;}
+
+uint get_main_input_signal_no() {return 2;}
+
+uint get_total_signal_no() {return 8;}
+
+uint get_number_of_components() {return 2;}
+
+uint get_size_of_input_hashmap() {return 256;}
+
+uint get_size_of_witness() {return 8;}
+
+uint get_size_of_constants() {return 1;}
+
+uint get_size_of_io_map() {return 1;}
+
+uint get_size_of_bus_field_map() {return 1;}
+
+void release_memory_component(Circom_CalcWit* ctx, uint pos) {{
+
+if (pos != 0){{
+
+
+
+
+
+
+
+}}
+
+
+}}
+
+
+// function declarations
+// template declarations
+void Double_0_create(uint soffset,uint coffset,Circom_CalcWit* ctx,rust::string componentName,uint componentFather){
+ctx->componentMemory[coffset].templateId = 0;
+ctx->componentMemory[coffset].templateName = "Double";
+ctx->componentMemory[coffset].signalStart = soffset;
+ctx->componentMemory[coffset].inputCounter = 2;
+ctx->componentMemory[coffset].componentName = componentName;
+ctx->componentMemory[coffset].idFather = componentFather;
+ctx->componentMemory[coffset].subcomponents = create_vec_u32(0);
+}
+
+void Double_0_run(uint ctx_index,Circom_CalcWit* ctx){
+rust::Vec<FrElement> &signalValues = ctx->signalValues;
+u64 mySignalStart = ctx->componentMemory[ctx_index].signalStart;
+rust::string myTemplateName = ctx->componentMemory[ctx_index].templateName;
+rust::string myComponentName = ctx->componentMemory[ctx_index].componentName;
+u64 myFather = ctx->componentMemory[ctx_index].idFather;
+u64 myId = ctx_index;
+rust::Vec<u32> mySubcomponents = ctx->componentMemory[ctx_index].subcomponents;
+rust::Vec<FrElement> &circuitConstants = ctx->circuitConstants;
+rust::Vec<rust::string> &listOfTemplateMessages = ctx->listOfTemplateMessages;
+rust::Vec<FrElement> expaux = create_vec(1);
+rust::Vec<FrElement> lvar = create_vec(0);
+uint sub_component_aux;
+uint index_multiple_eq;
+int cmp_index_ref_load = -1;
+{
+FrElement* aux_dest = &signalValues[mySignalStart + 0];
+// load src
+Fr_mul(&expaux[0],&circuitConstants[0],&signalValues[mySignalStart + 2]); // line circom 6
+// end load src
+Fr_copy(aux_dest,&expaux[0]);
+}
+{
+FrElement* aux_dest = &signalValues[mySignalStart + 1];
+// load src
+Fr_mul(&expaux[0],&circuitConstants[0],&signalValues[mySignalStart + 3]); // line circom 7
+// end load src
+Fr_copy(aux_dest,&expaux[0]);
+}
+for (uint i = 0; i < 0; i++){
+uint index_subc = ctx->componentMemory[ctx_index].subcomponents[i];
+if (index_subc != 0){
+release_memory_component(ctx,index_subc);
+}
+}
+}
+
+void Main_1_create(uint soffset,uint coffset,Circom_CalcWit* ctx,rust::string componentName,uint componentFather){
+ctx->componentMemory[coffset].templateId = 1;
+ctx->componentMemory[coffset].templateName = "Main";
+ctx->componentMemory[coffset].signalStart = soffset;
+ctx->componentMemory[coffset].inputCounter = 2;
+ctx->componentMemory[coffset].componentName = componentName;
+ctx->componentMemory[coffset].idFather = componentFather;
+ctx->componentMemory[coffset].subcomponents = create_vec_u32(1);
+}
+
+void Main_1_run(uint ctx_index,Circom_CalcWit* ctx){
+rust::Vec<FrElement> &signalValues = ctx->signalValues;
+u64 mySignalStart = ctx->componentMemory[ctx_index].signalStart;
+rust::string myTemplateName = ctx->componentMemory[ctx_index].templateName;
+rust::string myComponentName = ctx->componentMemory[ctx_index].componentName;
+u64 myFather = ctx->componentMemory[ctx_index].idFather;
+u64 myId = ctx_index;
+rust::Vec<u32> mySubcomponents = ctx->componentMemory[ctx_index].subcomponents;
+rust::Vec<FrElement> &circuitConstants = ctx->circuitConstants;
+rust::Vec<rust::string> &listOfTemplateMessages = ctx->listOfTemplateMessages;
+rust::Vec<FrElement> expaux = create_vec(2);
+rust::Vec<FrElement> lvar = create_vec(0);
+uint sub_component_aux;
+uint index_multiple_eq;
+int cmp_index_ref_load = -1;
+{
+rust::string new_cmp_name = "d";
+Double_0_create(mySignalStart+3,0+ctx_index+1,ctx,new_cmp_name,myId);
+mySubcomponents[0] = 0+ctx_index+1;
+}
+{
+uint cmp_index_ref = 0;
+{
+FrElement* aux_dest = &ctx->signalValues[ctx->componentMemory[mySubcomponents[cmp_index_ref]].signalStart + IOSignalInfoAccessor(ctx)[ctx->componentMemory[mySubcomponents[cmp_index_ref]].templateId].defs[1].offset];
+// load src
+// end load src
+Fr_copyn(aux_dest,&signalValues[mySignalStart + 1],2);
+}
+// run sub component if needed
+if(!(ctx->componentMemory[mySubcomponents[cmp_index_ref]].inputCounter -= 2)){
+Double_0_run(mySubcomponents[cmp_index_ref],ctx);
+
+}
+}
+{
+FrElement* aux_dest = &signalValues[mySignalStart + 0];
+// load src
+cmp_index_ref_load = 0;
+cmp_index_ref_load = 0;
+Fr_add(&expaux[0],&ctx->signalValues[ctx->componentMemory[mySubcomponents[0]].signalStart + IOSignalInfoAccessor(ctx)[ctx->componentMemory[mySubcomponents[0]].templateId].defs[0].offset + ctx->busInsId2FieldInfo[IOSignalInfoAccessor(ctx)[ctx->componentMemory[mySubcomponents[0]].templateId].defs[0].busId].defs[0].offset],&ctx->signalValues[ctx->componentMemory[mySubcomponents[0]].signalStart + IOSignalInfoAccessor(ctx)[ctx->componentMemory[mySubcomponents[0]].templateId].defs[0].offset + ctx->busInsId2FieldInfo[IOSignalInfoAccessor(ctx)[ctx->componentMemory[mySubcomponents[0]].templateId].defs[0].busId].defs[1].offset]); // line circom 15
+// end load src
+Fr_copy(aux_dest,&expaux[0]);
+}
+for (uint i = 0; i < 1; i++){
+uint index_subc = ctx->componentMemory[ctx_index].subcomponents[i];
+if (index_subc != 0){
+release_memory_component(ctx,index_subc);
+}
+}
+}
+
+void run(Circom_CalcWit* ctx){
+Main_1_create(1,0,ctx,"main",0);
+Main_1_run(0,ctx);
+}
+
+
+} // namespace circuit_test
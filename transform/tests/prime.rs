@@ -0,0 +1,36 @@
+use circom_witness_transform::detect_prime;
+
+#[test]
+fn limbs() {
+    let fr_cpp = "FrRawElement Fr_rawq = {0x43e1f593f0000001,0x2833e84879b97091,0xb85045b68181585d,0x30644e72e131a029};";
+    assert_eq!(detect_prime(fr_cpp), Some("bn128"));
+    let fr_asm = "q dq 0x3c208c16d87cfd47,0x97816a916871ca8d,0xb85045b68181585d,0x30644e72e131a029";
+    assert_eq!(detect_prime(fr_asm), Some("grumpkin"));
+    let zero_limb = "{0x992D30ED00000001ULL, 0x224698FC094CF91BULL, 0x0ULL, 0x4000000000000000ULL}";
+    assert_eq!(detect_prime(zero_limb), Some("pallas"));
+}
+
+#[test]
+fn decimal() {
+    let fr_cpp = "mpz_init_set_str(q, \"28948022309329048855892746252171976963363056481941647379679742748393362948097\", 10);";
+    assert_eq!(detect_prime(fr_cpp), Some("vesta"));
+}
+
+#[test]
+fn longest_match() {
+    // The Goldilocks modulus is the lowest limb of BLS12-381.
+    assert_eq!(
+        detect_prime("uint64_t q = 0xffffffff00000001;"),
+        Some("goldilocks")
+    );
+    let fr_cpp = "{0xffffffff00000001,0x53bda402fffe5bfe,0x3339d80809a1d805,0x73eda753299d7d48}";
+    assert_eq!(detect_prime(fr_cpp), Some("bls12381"));
+}
+
+#[test]
+fn unknown() {
+    assert_eq!(detect_prime(""), None);
+    // Limbs out of order are not a modulus.
+    let fr_cpp = "{0x2833e84879b97091,0x43e1f593f0000001,0xb85045b68181585d,0x30644e72e131a029}";
+    assert_eq!(detect_prime(fr_cpp), None);
+}
@@ -0,0 +1,56 @@
+use circom_witness_transform::{input_name, main_signals, signal_arrays};
+
+#[test]
+fn main_component() {
+    let sym =
+        "1,1,0,main.out\n2,2,0,main.a[0]\n3,-1,0,main.a[1]\n4,3,1,main.d.q.x\n5,4,0,main.p.x\n";
+    assert_eq!(
+        main_signals(sym).unwrap(),
+        [
+            (1, "out".to_string()),
+            (2, "a[0]".to_string()),
+            (3, "a[1]".to_string()),
+            (5, "p.x".to_string()),
+        ]
+    );
+    assert_eq!(
+        main_signals("1,1,0,main.a\n2,x,0,main.b\n").unwrap_err(),
+        "line 2: invalid symbol \"2,x,0,main.b\""
+    );
+}
+
+#[test]
+fn input_names() {
+    assert_eq!(input_name("a"), "a");
+    assert_eq!(input_name("a[0][12]"), "a");
+    assert_eq!(input_name("p.x"), "p.x");
+    assert_eq!(input_name("ps[1].x[2]"), "ps[1].x");
+}
+
+#[test]
+fn arrays() {
+    let signals = main_signals(
+        "1,1,0,main.out\n2,2,0,main.a[0][0]\n3,3,0,main.a[0][1]\n4,4,0,main.a[1][0]\n\
+         5,5,0,main.a[1][1]\n6,6,0,main.a[2][0]\n7,7,0,main.a[2][1]\n8,8,0,main.p[0].x\n\
+         9,9,0,main.p[1].x\n",
+    )
+    .unwrap();
+    let arrays: Vec<_> = signal_arrays(&signals)
+        .unwrap()
+        .into_iter()
+        .map(|array| (array.name, array.first, array.shape))
+        .collect();
+    assert_eq!(
+        arrays,
+        [
+            ("out".to_string(), 1, vec![]),
+            ("a".to_string(), 2, vec![3, 2]),
+            ("p[0].x".to_string(), 8, vec![]),
+            ("p[1].x".to_string(), 9, vec![]),
+        ]
+    );
+    assert_eq!(
+        signal_arrays(&[(1, "a[0]".to_string()), (2, "a".to_string())]).unwrap_err(),
+        "signal \"a\" has 0 indices, other elements of \"a\" have 1"
+    );
+}
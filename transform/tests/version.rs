@@ -0,0 +1,24 @@
+use circom_witness_transform::{CircomVersion, SUPPORTED_VERSIONS};
+
+#[test]
+fn parse() {
+    assert_eq!(
+        CircomVersion::from_output("circom compiler 2.2.2\n"),
+        Some(CircomVersion::new(2, 2, 2))
+    );
+    assert_eq!(CircomVersion::from_output("circom compiler"), None);
+    assert_eq!("2.1.10".parse(), Ok(CircomVersion::new(2, 1, 10)));
+    assert!("2.1".parse::<CircomVersion>().is_err());
+    assert!("2.1.6.1".parse::<CircomVersion>().is_err());
+    assert!("2.x.6".parse::<CircomVersion>().is_err());
+    assert_eq!(CircomVersion::new(2, 1, 10).to_string(), "2.1.10");
+}
+
+#[test]
+fn supported() {
+    assert!(CircomVersion::new(2, 1, 10) > CircomVersion::new(2, 1, 9));
+    assert!(SUPPORTED_VERSIONS.end().is_supported());
+    assert!(CircomVersion::new(2, 2, 0).is_supported());
+    assert!(!CircomVersion::new(2, 0, 9).is_supported());
+    assert!(!CircomVersion::new(2, 2, 3).is_supported());
+}